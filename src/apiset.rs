@@ -0,0 +1,270 @@
+//! API set schema.
+//!
+//! Api sets are virtual dlls, eg. `api-ms-win-core-synch-l1-2-0.dll`, which the loader redirects to the dll actually hosting the functions.
+//! The redirections are stored in the `.apiset` section of `apisetschema.dll`, which the system maps into every process.
+//!
+//! The schema changed layout between Windows releases, this parser supports:
+//!
+//! * Version 2, Windows 7 and 8.
+//! * Version 4, Windows 8.1.
+//! * Version 6, Windows 10 and later.
+//!
+//! An api set may redirect to a different host depending on the importing module, which is how a host can import its own api set.
+//!
+//! Use `pe64::apiset::PeApiSet` or `pe32::apiset::PeApiSet` to read the schema from `apisetschema.dll`.
+
+use std::fmt;
+
+use error::PeError;
+
+//----------------------------------------------------------------
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, PeError> {
+	let bytes = try!(bytes.get(offset..offset + 4).ok_or(PeError::Bounds));
+	Ok(bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24)
+}
+
+fn read_utf16(bytes: &[u8], offset: u32, len: u32) -> Result<String, PeError> {
+	let bytes = try!(bytes.get(offset as usize..offset as usize + len as usize).ok_or(PeError::Bounds));
+	let words: Vec<u16> = bytes.chunks(2).map(|word| word[0] as u16 | (*word.get(1).unwrap_or(&0) as u16) << 8).collect();
+	String::from_utf16(&words).map_err(|_| PeError::Corrupt)
+}
+
+// Versions 2 and 4 store names without the `api-` prefix.
+fn add_prefix(name: String) -> String {
+	if name.get(..4).map(|prefix| prefix.eq_ignore_ascii_case("ext-")).unwrap_or(false) { name }
+	else { format!("api-{}", name) }
+}
+
+// Strips the `.dll` extension, if any.
+fn strip_dll(name: &str) -> &str {
+	let len = name.len();
+	if len > 4 && name.get(len - 4..).map(|ext| ext.eq_ignore_ascii_case(".dll")).unwrap_or(false) { &name[..len - 4] }
+	else { name }
+}
+
+//----------------------------------------------------------------
+
+/// API set schema.
+#[derive(Clone, Debug)]
+pub struct ApiSetSchema {
+	version: u32,
+	entries: Vec<ApiSetEntry>,
+}
+
+impl ApiSetSchema {
+	/// Parse the schema.
+	///
+	/// # Parameters
+	///
+	/// * `bytes`
+	///
+	///   Contents of the `.apiset` section, all offsets in the schema are relative to its start.
+	///
+	/// # Return value
+	///
+	/// `PeError::BadMagic` if the schema version is not supported.
+	///
+	/// `PeError::Bounds` if any of the offsets point out of bounds of the section.
+	///
+	/// `PeError::Corrupt` if any name is not valid UTF-16.
+	pub fn parse(bytes: &[u8]) -> Result<ApiSetSchema, PeError> {
+		let version = try!(read_u32(bytes, 0));
+		let entries = match version {
+			2 => try!(Self::parse_v2(bytes)),
+			4 => try!(Self::parse_v4(bytes)),
+			6 => try!(Self::parse_v6(bytes)),
+			_ => return Err(PeError::BadMagic),
+		};
+		Ok(ApiSetSchema {
+			version: version,
+			entries: entries,
+		})
+	}
+	// Version, Count, then entries of NameOffset, NameLength, DataOffset.
+	// The data is a Count followed by values of NameOffset, NameLength, ValueOffset, ValueLength.
+	fn parse_v2(bytes: &[u8]) -> Result<Vec<ApiSetEntry>, PeError> {
+		let count = try!(read_u32(bytes, 4)) as usize;
+		let mut entries = Vec::new();
+		for i in 0..count {
+			let entry = 8 + i * 12;
+			let name = add_prefix(try!(read_utf16(bytes, try!(read_u32(bytes, entry)), try!(read_u32(bytes, entry + 4)))));
+			let data = try!(read_u32(bytes, entry + 8)) as usize;
+			let value_count = try!(read_u32(bytes, data)) as usize;
+			let hosts = try!(Self::parse_values(bytes, data + 4, value_count, 0));
+			entries.push(ApiSetEntry::new(name, None, hosts));
+		}
+		Ok(entries)
+	}
+	// Version, Size, Flags, Count, then entries of Flags, NameOffset, NameLength, AliasOffset, AliasLength, DataOffset.
+	// The data is Flags, Count followed by values of Flags, NameOffset, NameLength, ValueOffset, ValueLength.
+	fn parse_v4(bytes: &[u8]) -> Result<Vec<ApiSetEntry>, PeError> {
+		let count = try!(read_u32(bytes, 12)) as usize;
+		let mut entries = Vec::new();
+		for i in 0..count {
+			let entry = 16 + i * 24;
+			let name = add_prefix(try!(read_utf16(bytes, try!(read_u32(bytes, entry + 4)), try!(read_u32(bytes, entry + 8)))));
+			let data = try!(read_u32(bytes, entry + 20)) as usize;
+			let value_count = try!(read_u32(bytes, data + 4)) as usize;
+			let hosts = try!(Self::parse_values(bytes, data + 8, value_count, 4));
+			entries.push(ApiSetEntry::new(name, None, hosts));
+		}
+		Ok(entries)
+	}
+	// Version, Size, Flags, Count, EntryOffset, HashOffset, HashFactor,
+	// then entries of Flags, NameOffset, NameLength, HashedLength, ValueOffset, ValueCount
+	// pointing to values of Flags, NameOffset, NameLength, ValueOffset, ValueLength.
+	fn parse_v6(bytes: &[u8]) -> Result<Vec<ApiSetEntry>, PeError> {
+		let count = try!(read_u32(bytes, 12)) as usize;
+		let entry_offset = try!(read_u32(bytes, 16)) as usize;
+		let mut entries = Vec::new();
+		for i in 0..count {
+			let entry = entry_offset + i * 24;
+			let name = try!(read_utf16(bytes, try!(read_u32(bytes, entry + 4)), try!(read_u32(bytes, entry + 8))));
+			let hashed_len = try!(read_u32(bytes, entry + 12)) as usize / 2;
+			let value_offset = try!(read_u32(bytes, entry + 16)) as usize;
+			let value_count = try!(read_u32(bytes, entry + 20)) as usize;
+			let hosts = try!(Self::parse_values(bytes, value_offset, value_count, 4));
+			entries.push(ApiSetEntry::new(name, Some(hashed_len), hosts));
+		}
+		Ok(entries)
+	}
+	// Values are NameOffset, NameLength, ValueOffset, ValueLength after a Flags field in later versions.
+	fn parse_values(bytes: &[u8], offset: usize, count: usize, flags: usize) -> Result<Vec<ApiSetHost>, PeError> {
+		let mut hosts = Vec::new();
+		for i in 0..count {
+			let value = offset + i * (flags + 16) + flags;
+			hosts.push(ApiSetHost {
+				importer: try!(read_utf16(bytes, try!(read_u32(bytes, value)), try!(read_u32(bytes, value + 4)))),
+				host: try!(read_utf16(bytes, try!(read_u32(bytes, value + 8)), try!(read_u32(bytes, value + 12)))),
+			});
+		}
+		Ok(hosts)
+	}
+	/// Get the schema version.
+	#[inline]
+	pub fn version(&self) -> u32 {
+		self.version
+	}
+	/// Get the api sets.
+	#[inline]
+	pub fn entries(&self) -> &[ApiSetEntry] {
+		&self.entries
+	}
+	/// Find an api set by name.
+	///
+	/// # Parameters
+	///
+	/// * `name`
+	///
+	///   Name of the api set, case insensitive and with or without the `.dll` extension.
+	///
+	/// # Remarks
+	///
+	/// Since version 6 the last number of the name is ignored, the same as the loader does.
+	/// This lets eg. `api-ms-win-core-synch-l1-2-1.dll` find `api-ms-win-core-synch-l1-2-0`.
+	pub fn find(&self, name: &str) -> Option<&ApiSetEntry> {
+		let name = strip_dll(name);
+		self.entries.iter().find(|entry| entry.matches(name))
+	}
+	/// Find the host dll of an api set.
+	///
+	/// # Parameters
+	///
+	/// * `name`
+	///
+	///   Name of the api set, see `find`.
+	///
+	/// * `importer`
+	///
+	///   Name of the module importing the api set, or an empty string if not known.
+	///
+	/// # Return value
+	///
+	/// `None` if the api set is not in the schema or it has no host.
+	///
+	/// Otherwise the host dll specific to the importer or the default host dll.
+	pub fn resolve(&self, name: &str, importer: &str) -> Option<&str> {
+		self.find(name).and_then(|entry| entry.host(importer))
+	}
+}
+
+impl fmt::Display for ApiSetSchema {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "ApiSetSchema Version {}", self.version));
+		for entry in &self.entries {
+			try!(write!(f, "  {}", entry.name));
+			for host in &entry.hosts {
+				if host.importer.is_empty() {
+					try!(write!(f, " -> {}", host.host));
+				}
+				else {
+					try!(write!(f, " ({} -> {})", host.importer, host.host));
+				}
+			}
+			try!(writeln!(f, ""));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Api set entry.
+#[derive(Clone, Debug)]
+pub struct ApiSetEntry {
+	name: String,
+	hashed_len: usize,
+	hosts: Vec<ApiSetHost>,
+}
+
+impl ApiSetEntry {
+	fn new(name: String, hashed_len: Option<usize>, hosts: Vec<ApiSetHost>) -> ApiSetEntry {
+		let hashed_len = hashed_len.unwrap_or(name.len());
+		ApiSetEntry {
+			name: name,
+			hashed_len: hashed_len,
+			hosts: hosts,
+		}
+	}
+	fn matches(&self, name: &str) -> bool {
+		if self.hashed_len == self.name.len() {
+			return self.name.eq_ignore_ascii_case(name);
+		}
+		// Compare up to the last hyphen, both names must agree on where that is
+		match (self.name.get(..self.hashed_len), name.rfind('-')) {
+			(Some(hashed), Some(len)) => len == self.hashed_len && hashed.eq_ignore_ascii_case(&name[..len]),
+			_ => false,
+		}
+	}
+	/// Get the name of the api set, without the `.dll` extension.
+	#[inline]
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+	/// Get the hosts.
+	///
+	/// The default host has no importer, the others apply only to their importer.
+	#[inline]
+	pub fn hosts(&self) -> &[ApiSetHost] {
+		&self.hosts
+	}
+	/// Get the host dll for an importer.
+	///
+	/// Returns the default host unless a host specific to `importer` exists, `None` if the api set has no host.
+	pub fn host(&self, importer: &str) -> Option<&str> {
+		let importer = strip_dll(importer);
+		let specific = self.hosts.iter().find(|host| !host.importer.is_empty() && !importer.is_empty() && strip_dll(&host.importer).eq_ignore_ascii_case(importer));
+		let default = self.hosts.iter().find(|host| host.importer.is_empty());
+		specific.or(default).map(|host| &host.host[..]).and_then(|host| if host.is_empty() { None } else { Some(host) })
+	}
+}
+
+/// Api set host.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApiSetHost {
+	/// Name of the importing module this host applies to, empty for the default host.
+	pub importer: String,
+	/// Name of the host dll.
+	pub host: String,
+}
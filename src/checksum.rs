@@ -0,0 +1,155 @@
+//! PE checksum.
+//!
+//! The checksum in the optional header is only verified by the loader for kernel drivers, boot drivers and dlls loaded into critical processes.
+//! It is computed the same way as `CheckSumMappedFile` from imagehlp: a 16-bit one's complement sum of the whole file
+//! (treating the checksum field itself as zero) to which the length of the file is added.
+
+use std::{io, mem};
+use std::io::{Read, Seek};
+
+use image::*;
+use error::PeError;
+
+//----------------------------------------------------------------
+
+/// Compute the checksum of a PE file.
+///
+/// # Parameters
+///
+/// * `bytes`
+///
+///   Contents of the file as it is stored on disk.
+///
+/// # Return value
+///
+/// `PeError::BadMagic` if the DOS header magic does not match.
+///
+/// `PeError::Bounds` if the file is too small to contain the checksum field.
+///
+/// The checksum is at the same offset for PE32 and PE64 images.
+pub fn compute_checksum(bytes: &[u8]) -> Result<u32, PeError> {
+	let offset = try!(checksum_offset(bytes));
+	if offset + 4 > bytes.len() {
+		return Err(PeError::Bounds);
+	}
+	let mut checksum = Checksum::new(offset);
+	checksum.update(bytes);
+	Ok(checksum.finish())
+}
+
+/// Compute the checksum of a PE file read from any seekable reader.
+///
+/// # Parameters
+///
+/// * `file`
+///
+///   Reader for the contents of the file as it is stored on disk.
+///   The file is expected to start at the beginning of the stream, it is rewound before reading.
+///
+/// # Return value
+///
+/// `PeError::Io` if reading the file fails.
+///
+/// Otherwise the same as `compute_checksum`, the file is read in chunks instead of all at once.
+pub fn compute_checksum_from_reader<R: Read + Seek>(file: &mut R) -> Result<u32, PeError> {
+	let mut chunk = [0u8; 0x1000];
+	try!(file.seek(io::SeekFrom::Start(0)));
+	let dos_bytes = mem::size_of::<ImageDosHeader>();
+	try!(file.read_exact(&mut chunk[..dos_bytes]));
+	let offset = try!(checksum_offset(&chunk[..dos_bytes]));
+	let mut checksum = Checksum::new(offset);
+	checksum.update(&chunk[..dos_bytes]);
+	loop {
+		let read = try!(file.read(&mut chunk));
+		if read == 0 {
+			break;
+		}
+		checksum.update(&chunk[..read]);
+	}
+	if offset + 4 > checksum.len {
+		return Err(PeError::Bounds);
+	}
+	Ok(checksum.finish())
+}
+
+/// Get the file offset of the checksum field from the DOS header.
+pub fn checksum_offset(bytes: &[u8]) -> Result<usize, PeError> {
+	if bytes.len() < mem::size_of::<ImageDosHeader>() {
+		return Err(PeError::Bounds);
+	}
+	// This is safe since the length was checked and the struct is packed
+	let dos = unsafe { &*(bytes.as_ptr() as *const ImageDosHeader) };
+	if dos.e_magic != IMAGE_DOS_HEADER_MAGIC {
+		return Err(PeError::BadMagic);
+	}
+	// Signature and file header followed by the optional header fields up to the checksum
+	Ok(dos.e_lfanew as usize + mem::size_of::<u32>() + mem::size_of::<ImageFileHeader>() + 64)
+}
+
+//----------------------------------------------------------------
+
+/// Incrementally computes the checksum of a file read in chunks.
+pub struct Checksum {
+	sum: u64,
+	len: usize,
+	skip: usize,
+}
+
+impl Checksum {
+	/// Start a new checksum.
+	///
+	/// # Parameters
+	///
+	/// * `checksum_offset`
+	///
+	///   File offset of the checksum field, these four bytes are treated as zero. See `checksum_offset`.
+	pub fn new(checksum_offset: usize) -> Checksum {
+		Checksum {
+			sum: 0,
+			len: 0,
+			skip: checksum_offset,
+		}
+	}
+	/// Add the next chunk of the file, chunks may have any length.
+	pub fn update(&mut self, bytes: &[u8]) {
+		let mut bytes = bytes;
+		// Complete the word split by the previous chunk
+		if self.len & 1 != 0 {
+			match bytes.split_first() {
+				Some((&byte, rest)) => {
+					self.update_byte(byte);
+					bytes = rest;
+				},
+				None => return,
+			}
+		}
+		for word in bytes.chunks(2) {
+			if self.len + 2 > self.skip && self.len < self.skip + 4 {
+				for &byte in word {
+					self.update_byte(byte);
+				}
+			}
+			else {
+				// Little endian words, an odd trailing byte is zero extended
+				self.sum += word[0] as u64 | (*word.get(1).unwrap_or(&0) as u64) << 8;
+				self.len += word.len();
+			}
+		}
+	}
+	// Adds a single byte, skipping the checksum field.
+	fn update_byte(&mut self, byte: u8) {
+		let pos = self.len;
+		self.len += 1;
+		if pos < self.skip || pos >= self.skip + 4 {
+			self.sum += (byte as u64) << ((pos & 1) * 8);
+		}
+	}
+	/// Get the checksum of all the chunks added so far.
+	pub fn finish(&self) -> u32 {
+		let mut sum = self.sum;
+		while sum >> 16 != 0 {
+			sum = (sum & 0xFFFF) + (sum >> 16);
+		}
+		(sum as u32).wrapping_add(self.len as u32)
+	}
+}
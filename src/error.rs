@@ -0,0 +1,74 @@
+//! Errors.
+
+use std::{io, str, fmt, error};
+
+//----------------------------------------------------------------
+
+#[derive(Debug)]
+pub enum PeError {
+	/// There was an error reading the file.
+	Io(io::Error),
+	/// Magic values didn't match.
+	BadMagic,
+	/// Sanity checks failed.
+	Insanity,
+	/// Attempted to read from a null address, typically means the requested data isn't present.
+	Null,
+	/// Address or size points out of bounds of the image.
+	Bounds,
+	/// Address is not correctly aligned for the type being read.
+	Misaligned,
+	/// String is not valid UTF-8.
+	Utf8(str::Utf8Error),
+	/// A data directory's structures contradict themselves.
+	Corrupt,
+}
+
+impl From<io::Error> for PeError {
+	fn from(err: io::Error) -> PeError {
+		PeError::Io(err)
+	}
+}
+
+impl From<str::Utf8Error> for PeError {
+	fn from(err: str::Utf8Error) -> PeError {
+		PeError::Utf8(err)
+	}
+}
+
+impl fmt::Display for PeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			PeError::Io(ref err) => write!(f, "io error: {}", err),
+			PeError::Utf8(ref err) => write!(f, "utf8 error: {}", err),
+			_ => write!(f, "{}", error::Error::description(self)),
+		}
+	}
+}
+
+impl error::Error for PeError {
+	fn description(&self) -> &str {
+		match *self {
+			PeError::Io(_) => "io error",
+			PeError::BadMagic => "bad magic",
+			PeError::Insanity => "insanity",
+			PeError::Null => "null address",
+			PeError::Bounds => "out of bounds",
+			PeError::Misaligned => "misaligned",
+			PeError::Utf8(_) => "utf8 error",
+			PeError::Corrupt => "corrupt",
+		}
+	}
+}
+
+/// Converts the result of a fallible accessor for its panicking convenience wrapper.
+///
+/// `PeError::Null` becomes `None`, every other error panics as it indicates corruption.
+#[inline]
+pub fn unwrap_null<T>(result: Result<T, PeError>) -> Option<T> {
+	match result {
+		Ok(val) => Some(val),
+		Err(PeError::Null) => None,
+		Err(err) => panic!("{}", err),
+	}
+}
@@ -2,6 +2,8 @@
 
 #![allow(non_snake_case)]
 
+use pod::Pod;
+
 #[cfg(windows)]
 extern "C" {
 	#[cfg(target_env = "msvc")]
@@ -54,6 +56,7 @@ pub struct ImageDosHeader {
 	pub e_res2: [u16; 10],
 	pub e_lfanew: u32,
 }
+unsafe impl Pod for ImageDosHeader {}
 
 //----------------------------------------------------------------
 
@@ -88,6 +91,7 @@ pub struct ImageFileHeader {
 	pub SizeOfOptionalHeader: u16,
 	pub Characteristics: u16,
 }
+unsafe impl Pod for ImageFileHeader {}
 
 //----------------------------------------------------------------
 
@@ -97,6 +101,7 @@ pub struct ImageDataDirectory {
 	pub VirtualAddress: u32,
 	pub Size: u32,
 }
+unsafe impl Pod for ImageDataDirectory {}
 
 pub const IMAGE_DIRECTORY_ENTRY_EXPORT: usize         = 0;
 pub const IMAGE_DIRECTORY_ENTRY_IMPORT: usize         = 1;
@@ -180,6 +185,7 @@ pub struct ImageOptionalHeader32 {
 	pub NumberOfRvaAndSizes: u32,
 	pub DataDirectory: [ImageDataDirectory; IMAGE_NUMBEROF_DIRECTORY_ENTRIES],
 }
+unsafe impl Pod for ImageOptionalHeader32 {}
 
 #[derive(Debug)]
 #[repr(C, packed)]
@@ -215,6 +221,7 @@ pub struct ImageOptionalHeader64 {
 	pub NumberOfRvaAndSizes: u32,
 	pub DataDirectory: [ImageDataDirectory; IMAGE_NUMBEROF_DIRECTORY_ENTRIES],
 }
+unsafe impl Pod for ImageOptionalHeader64 {}
 
 //----------------------------------------------------------------
 
@@ -227,6 +234,7 @@ pub struct ImageNtHeaders32 {
 	pub FileHeader: ImageFileHeader,
 	pub OptionalHeader: ImageOptionalHeader32,
 }
+unsafe impl Pod for ImageNtHeaders32 {}
 
 #[derive(Debug)]
 #[repr(C, packed)]
@@ -235,6 +243,7 @@ pub struct ImageNtHeaders64 {
 	pub FileHeader: ImageFileHeader,
 	pub OptionalHeader: ImageOptionalHeader64,
 }
+unsafe impl Pod for ImageNtHeaders64 {}
 
 //----------------------------------------------------------------
 
@@ -290,6 +299,7 @@ pub struct ImageSectionHeader {
 	pub NumberOfLinenumbers: u16,
 	pub Characteristics: u32,
 }
+unsafe impl Pod for ImageSectionHeader {}
 
 //----------------------------------------------------------------
 
@@ -308,6 +318,7 @@ pub struct ImageExportDirectory {
 	pub AddressOfNames: u32,         // RVA from base of image
 	pub AddressOfNameOrdinals: u32,  // RVA from base of image
 }
+unsafe impl Pod for ImageExportDirectory {}
 
 //----------------------------------------------------------------
 
@@ -320,10 +331,67 @@ pub struct ImageImportDescriptor {
 	pub Name: u32,
 	pub FirstThunk: u32,
 }
+unsafe impl Pod for ImageImportDescriptor {}
 
 pub const IMAGE_ORDINAL_FLAG32: u32 = 0x80000000;
 pub const IMAGE_ORDINAL_FLAG64: u64 = 0x8000000000000000;
 
+// Without this attribute the addresses are legacy (Visual C++ 6.0) Vas instead of Rvas
+pub const DLATTR_RVA: u32 = 0x1;
+
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageDelayloadDescriptor {
+	pub Attributes: u32,
+	pub DllNameRVA: u32,
+	pub ModuleHandleRVA: u32,
+	pub ImportAddressTableRVA: u32,
+	pub ImportNameTableRVA: u32,
+	pub BoundImportAddressTableRVA: u32,
+	pub UnloadInformationTableRVA: u32,
+	pub TimeDateStamp: u32,
+}
+unsafe impl Pod for ImageDelayloadDescriptor {}
+
+// Followed by NumberOfModuleForwarderRefs forwarder refs, module names are offsets from the start of the bound import directory
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageBoundImportDescriptor {
+	pub TimeDateStamp: u32,
+	pub OffsetModuleName: u16,
+	pub NumberOfModuleForwarderRefs: u16,
+}
+unsafe impl Pod for ImageBoundImportDescriptor {}
+
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageBoundForwarderRef {
+	pub TimeDateStamp: u32,
+	pub OffsetModuleName: u16,
+	pub Reserved: u16,
+}
+unsafe impl Pod for ImageBoundForwarderRef {}
+
+//----------------------------------------------------------------
+
+pub const WIN_CERT_REVISION_1_0: u16 = 0x0100;
+pub const WIN_CERT_REVISION_2_0: u16 = 0x0200;
+
+pub const WIN_CERT_TYPE_X509: u16             = 0x0001;
+pub const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 0x0002;
+pub const WIN_CERT_TYPE_RESERVED_1: u16       = 0x0003;
+pub const WIN_CERT_TYPE_TS_STACK_SIGNED: u16  = 0x0004;
+
+// Followed by the certificate data, the whole entry is padded to a multiple of 8 bytes
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct WinCertificate {
+	pub Length: u32,                 // Including this header
+	pub Revision: u16,
+	pub CertificateType: u16,
+}
+unsafe impl Pod for WinCertificate {}
+
 //----------------------------------------------------------------
 
 pub const RT_CURSOR: u16       = 1;
@@ -366,6 +434,7 @@ pub struct ImageResourceDirectory {
 	pub NumberOfNamedEntries: u16,
 	pub NumberOfIdEntries: u16,
 }
+unsafe impl Pod for ImageResourceDirectory {}
 
 #[derive(Debug)]
 #[repr(C, packed)]
@@ -376,6 +445,7 @@ pub struct ImageResourceDirectoryEntry {
 	// High bit set means this is offset points to an ImageResourceDirectory otherwise an ImageResourceDataEntry
 	pub Offset: u32,
 }
+unsafe impl Pod for ImageResourceDirectoryEntry {}
 
 #[derive(Debug)]
 #[repr(C, packed)]
@@ -385,6 +455,7 @@ pub struct ImageResourceDataEntry {
 	pub CodePage: u32,
 	pub Reserved: u32,
 }
+unsafe impl Pod for ImageResourceDataEntry {}
 
 //----------------------------------------------------------------
 
@@ -404,6 +475,7 @@ pub struct ImageBaseRelocation {
 	pub VirtualAddress: u32,
 	pub SizeOfBlock: u32,
 }
+unsafe impl Pod for ImageBaseRelocation {}
 
 #[derive(Debug)]
 #[repr(C, packed)]
@@ -413,3 +485,334 @@ pub struct ImageBaseRelocBlock {
 	// |Type|   Offset   |
 	pub TypeAndOffset: u16,
 }
+unsafe impl Pod for ImageBaseRelocBlock {}
+
+//----------------------------------------------------------------
+
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageTlsDirectory32 {
+	pub StartAddressOfRawData: u32,  // VA
+	pub EndAddressOfRawData: u32,    // VA
+	pub AddressOfIndex: u32,         // VA
+	pub AddressOfCallBacks: u32,     // VA of a null terminated array of VAs
+	pub SizeOfZeroFill: u32,
+	pub Characteristics: u32,
+}
+unsafe impl Pod for ImageTlsDirectory32 {}
+
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageTlsDirectory64 {
+	pub StartAddressOfRawData: u64,  // VA
+	pub EndAddressOfRawData: u64,    // VA
+	pub AddressOfIndex: u64,         // VA
+	pub AddressOfCallBacks: u64,     // VA of a null terminated array of VAs
+	pub SizeOfZeroFill: u32,
+	pub Characteristics: u32,
+}
+unsafe impl Pod for ImageTlsDirectory64 {}
+
+//----------------------------------------------------------------
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct ImageLoadConfigCodeIntegrity {
+	pub Flags: u16,
+	pub Catalog: u16,
+	pub CatalogOffset: u32,
+	pub Reserved: u32,
+}
+unsafe impl Pod for ImageLoadConfigCodeIntegrity {}
+
+// The load config directory has grown over time, its Size field tells which fields are present.
+// Any fields beyond Size are not part of the image.
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageLoadConfigDirectory32 {
+	pub Size: u32,
+	pub TimeDateStamp: u32,
+	pub MajorVersion: u16,
+	pub MinorVersion: u16,
+	pub GlobalFlagsClear: u32,
+	pub GlobalFlagsSet: u32,
+	pub CriticalSectionDefaultTimeout: u32,
+	pub DeCommitFreeBlockThreshold: u32,
+	pub DeCommitTotalFreeThreshold: u32,
+	pub LockPrefixTable: u32,                                // VA
+	pub MaximumAllocationSize: u32,
+	pub VirtualMemoryThreshold: u32,
+	pub ProcessAffinityMask: u32,
+	pub ProcessHeapFlags: u32,
+	pub CSDVersion: u16,
+	pub DependentLoadFlags: u16,
+	pub EditList: u32,                                       // VA
+	pub SecurityCookie: u32,                                 // VA
+	pub SEHandlerTable: u32,                                 // VA of an array of RVAs
+	pub SEHandlerCount: u32,
+	pub GuardCFCheckFunctionPointer: u32,                    // VA
+	pub GuardCFDispatchFunctionPointer: u32,                 // VA
+	pub GuardCFFunctionTable: u32,                           // VA of a guard table
+	pub GuardCFFunctionCount: u32,
+	pub GuardFlags: u32,
+	pub CodeIntegrity: ImageLoadConfigCodeIntegrity,
+	pub GuardAddressTakenIatEntryTable: u32,                 // VA of a guard table
+	pub GuardAddressTakenIatEntryCount: u32,
+	pub GuardLongJumpTargetTable: u32,                       // VA of a guard table
+	pub GuardLongJumpTargetCount: u32,
+	pub DynamicValueRelocTable: u32,                         // VA
+	pub CHPEMetadataPointer: u32,                            // VA
+	pub GuardRFFailureRoutine: u32,                          // VA
+	pub GuardRFFailureRoutineFunctionPointer: u32,           // VA
+	pub DynamicValueRelocTableOffset: u32,
+	pub DynamicValueRelocTableSection: u16,
+	pub Reserved2: u16,
+	pub GuardRFVerifyStackPointerFunctionPointer: u32,       // VA
+	pub HotPatchTableOffset: u32,
+	pub Reserved3: u32,
+	pub EnclaveConfigurationPointer: u32,                    // VA
+	pub VolatileMetadataPointer: u32,                        // VA
+	pub GuardEHContinuationTable: u32,                       // VA of a guard table
+	pub GuardEHContinuationCount: u32,
+	pub GuardXFGCheckFunctionPointer: u32,                   // VA
+	pub GuardXFGDispatchFunctionPointer: u32,                // VA
+	pub GuardXFGTableDispatchFunctionPointer: u32,           // VA
+	pub CastGuardOsDeterminedFailureMode: u32,               // VA
+	pub GuardMemcpyFunctionPointer: u32,                     // VA
+}
+unsafe impl Pod for ImageLoadConfigDirectory32 {}
+
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageLoadConfigDirectory64 {
+	pub Size: u32,
+	pub TimeDateStamp: u32,
+	pub MajorVersion: u16,
+	pub MinorVersion: u16,
+	pub GlobalFlagsClear: u32,
+	pub GlobalFlagsSet: u32,
+	pub CriticalSectionDefaultTimeout: u32,
+	pub DeCommitFreeBlockThreshold: u64,
+	pub DeCommitTotalFreeThreshold: u64,
+	pub LockPrefixTable: u64,                                // VA
+	pub MaximumAllocationSize: u64,
+	pub VirtualMemoryThreshold: u64,
+	pub ProcessAffinityMask: u64,
+	pub ProcessHeapFlags: u32,
+	pub CSDVersion: u16,
+	pub DependentLoadFlags: u16,
+	pub EditList: u64,                                       // VA
+	pub SecurityCookie: u64,                                 // VA
+	pub SEHandlerTable: u64,                                 // VA of an array of RVAs
+	pub SEHandlerCount: u64,
+	pub GuardCFCheckFunctionPointer: u64,                    // VA
+	pub GuardCFDispatchFunctionPointer: u64,                 // VA
+	pub GuardCFFunctionTable: u64,                           // VA of a guard table
+	pub GuardCFFunctionCount: u64,
+	pub GuardFlags: u32,
+	pub CodeIntegrity: ImageLoadConfigCodeIntegrity,
+	pub GuardAddressTakenIatEntryTable: u64,                 // VA of a guard table
+	pub GuardAddressTakenIatEntryCount: u64,
+	pub GuardLongJumpTargetTable: u64,                       // VA of a guard table
+	pub GuardLongJumpTargetCount: u64,
+	pub DynamicValueRelocTable: u64,                         // VA
+	pub CHPEMetadataPointer: u64,                            // VA
+	pub GuardRFFailureRoutine: u64,                          // VA
+	pub GuardRFFailureRoutineFunctionPointer: u64,           // VA
+	pub DynamicValueRelocTableOffset: u32,
+	pub DynamicValueRelocTableSection: u16,
+	pub Reserved2: u16,
+	pub GuardRFVerifyStackPointerFunctionPointer: u64,       // VA
+	pub HotPatchTableOffset: u32,
+	pub Reserved3: u32,
+	pub EnclaveConfigurationPointer: u64,                    // VA
+	pub VolatileMetadataPointer: u64,                        // VA
+	pub GuardEHContinuationTable: u64,                       // VA of a guard table
+	pub GuardEHContinuationCount: u64,
+	pub GuardXFGCheckFunctionPointer: u64,                   // VA
+	pub GuardXFGDispatchFunctionPointer: u64,                // VA
+	pub GuardXFGTableDispatchFunctionPointer: u64,           // VA
+	pub CastGuardOsDeterminedFailureMode: u64,               // VA
+	pub GuardMemcpyFunctionPointer: u64,                     // VA
+}
+unsafe impl Pod for ImageLoadConfigDirectory64 {}
+
+pub const IMAGE_GUARD_CF_INSTRUMENTED: u32                    = 0x00000100;
+pub const IMAGE_GUARD_CFW_INSTRUMENTED: u32                   = 0x00000200;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT: u32          = 0x00000400;
+pub const IMAGE_GUARD_SECURITY_COOKIE_UNUSED: u32             = 0x00000800;
+pub const IMAGE_GUARD_PROTECT_DELAYLOAD_IAT: u32              = 0x00001000;
+pub const IMAGE_GUARD_DELAYLOAD_IAT_IN_ITS_OWN_SECTION: u32   = 0x00002000;
+pub const IMAGE_GUARD_CF_EXPORT_SUPPRESSION_INFO_PRESENT: u32 = 0x00004000;
+pub const IMAGE_GUARD_CF_ENABLE_EXPORT_SUPPRESSION: u32       = 0x00008000;
+pub const IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT: u32          = 0x00010000;
+pub const IMAGE_GUARD_RF_INSTRUMENTED: u32                    = 0x00020000;
+pub const IMAGE_GUARD_RF_ENABLE: u32                          = 0x00040000;
+pub const IMAGE_GUARD_RF_STRICT: u32                          = 0x00080000;
+pub const IMAGE_GUARD_RETPOLINE_PRESENT: u32                  = 0x00100000;
+pub const IMAGE_GUARD_EH_CONTINUATION_TABLE_PRESENT: u32      = 0x00400000;
+pub const IMAGE_GUARD_XFG_ENABLED: u32                        = 0x00800000;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK: u32        = 0xF0000000;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT: u32       = 28;
+
+// Flags in the extra bytes following every RVA in the guard tables
+pub const IMAGE_GUARD_FLAG_FID_SUPPRESSED: u8         = 0x01;
+pub const IMAGE_GUARD_FLAG_EXPORT_SUPPRESSED: u8      = 0x02;
+pub const IMAGE_GUARD_FLAG_FID_LANGEXCPTHANDLER: u8   = 0x04;
+pub const IMAGE_GUARD_FLAG_FID_XFG: u8                = 0x08;
+
+//----------------------------------------------------------------
+
+// x64 function table entry in the exception directory
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageRuntimeFunctionEntry {
+	pub BeginAddress: u32,
+	pub EndAddress: u32,
+	pub UnwindInfoAddress: u32,     // RVA, if the low bit is set it points to another runtime function entry instead
+}
+unsafe impl Pod for ImageRuntimeFunctionEntry {}
+
+pub const UNW_FLAG_NHANDLER: u8  = 0x0;
+pub const UNW_FLAG_EHANDLER: u8  = 0x1;
+pub const UNW_FLAG_UHANDLER: u8  = 0x2;
+pub const UNW_FLAG_CHAININFO: u8 = 0x4;
+
+// Followed by CountOfCodes unwind codes, padded to an even count.
+// Then a runtime function entry if chained, else the handler RVA and its data if there is a handler.
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageUnwindInfo {
+	// bit field:
+	// |01234|567|
+	// |Flags|Ver|
+	pub VersionAndFlags: u8,
+	pub SizeOfProlog: u8,
+	pub CountOfCodes: u8,
+	// bit field:
+	// |0123|4567|
+	// |Offs|Reg |
+	pub FrameRegisterAndOffset: u8,
+}
+unsafe impl Pod for ImageUnwindInfo {}
+
+pub const UWOP_PUSH_NONVOL: u8     = 0;
+pub const UWOP_ALLOC_LARGE: u8     = 1;
+pub const UWOP_ALLOC_SMALL: u8     = 2;
+pub const UWOP_SET_FPREG: u8       = 3;
+pub const UWOP_SAVE_NONVOL: u8     = 4;
+pub const UWOP_SAVE_NONVOL_FAR: u8 = 5;
+pub const UWOP_EPILOG: u8          = 6;
+pub const UWOP_SPARE_CODE: u8      = 7;
+pub const UWOP_SAVE_XMM128: u8     = 8;
+pub const UWOP_SAVE_XMM128_FAR: u8 = 9;
+pub const UWOP_PUSH_MACHFRAME: u8  = 10;
+
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageUnwindCode {
+	pub CodeOffset: u8,
+	// bit field:
+	// |0123|4567|
+	// |Info| Op |
+	pub UnwindOpAndInfo: u8,
+}
+unsafe impl Pod for ImageUnwindCode {}
+
+//----------------------------------------------------------------
+
+pub const IMAGE_DEBUG_TYPE_UNKNOWN: u32               = 0;
+pub const IMAGE_DEBUG_TYPE_COFF: u32                  = 1;
+pub const IMAGE_DEBUG_TYPE_CODEVIEW: u32              = 2;
+pub const IMAGE_DEBUG_TYPE_FPO: u32                   = 3;
+pub const IMAGE_DEBUG_TYPE_MISC: u32                  = 4;
+pub const IMAGE_DEBUG_TYPE_EXCEPTION: u32             = 5;
+pub const IMAGE_DEBUG_TYPE_FIXUP: u32                 = 6;
+pub const IMAGE_DEBUG_TYPE_OMAP_TO_SRC: u32           = 7;
+pub const IMAGE_DEBUG_TYPE_OMAP_FROM_SRC: u32         = 8;
+pub const IMAGE_DEBUG_TYPE_BORLAND: u32               = 9;
+pub const IMAGE_DEBUG_TYPE_RESERVED10: u32            = 10;
+pub const IMAGE_DEBUG_TYPE_CLSID: u32                 = 11;
+pub const IMAGE_DEBUG_TYPE_VC_FEATURE: u32            = 12;
+pub const IMAGE_DEBUG_TYPE_POGO: u32                  = 13;
+pub const IMAGE_DEBUG_TYPE_ILTCG: u32                 = 14;
+pub const IMAGE_DEBUG_TYPE_MPX: u32                   = 15;
+pub const IMAGE_DEBUG_TYPE_REPRO: u32                 = 16;
+pub const IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS: u32 = 20;
+
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageDebugDirectory {
+	pub Characteristics: u32,
+	pub TimeDateStamp: u32,
+	pub MajorVersion: u16,
+	pub MinorVersion: u16,
+	pub Type: u32,
+	pub SizeOfData: u32,
+	pub AddressOfRawData: u32,       // RVA from base of image, zero if not mapped
+	pub PointerToRawData: u32,       // File offset
+}
+unsafe impl Pod for ImageDebugDirectory {}
+
+#[derive(Copy, Clone, Debug)]
+#[repr(C, packed)]
+pub struct Guid {
+	pub Data1: u32,
+	pub Data2: u16,
+	pub Data3: u16,
+	pub Data4: [u8; 8],
+}
+unsafe impl Pod for Guid {}
+
+pub const CV_SIGNATURE_RSDS: u32 = 0x53445352; // 'RSDS'
+pub const CV_SIGNATURE_NB10: u32 = 0x3031424E; // 'NB10'
+
+// Followed by the null terminated PDB file name
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageDebugCvInfoPdb70 {
+	pub CvSignature: u32,
+	pub Signature: Guid,
+	pub Age: u32,
+}
+unsafe impl Pod for ImageDebugCvInfoPdb70 {}
+
+// Followed by the null terminated PDB file name
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageDebugCvInfoPdb20 {
+	pub CvSignature: u32,
+	pub Offset: u32,
+	pub Signature: u32,
+	pub Age: u32,
+}
+unsafe impl Pod for ImageDebugCvInfoPdb20 {}
+
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageDebugVcFeature {
+	pub PreVcPlusPlusCount: u32,
+	pub CPlusPlusCount: u32,
+	pub GuardStackCount: u32,
+	pub SdlCount: u32,
+	pub GuardCount: u32,
+}
+unsafe impl Pod for ImageDebugVcFeature {}
+
+pub const POGO_SIGNATURE_LTCG: u32 = 0x4C544347; // 'LTCG'
+pub const POGO_SIGNATURE_PGU: u32  = 0x50475500; // 'PGU\0'
+
+// Followed by the null terminated name, padded to a multiple of 4 bytes
+#[derive(Debug)]
+#[repr(C, packed)]
+pub struct ImageDebugPogoEntry {
+	pub Rva: u32,
+	pub Size: u32,
+}
+unsafe impl Pod for ImageDebugPogoEntry {}
+
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT: u32                           = 0x0001;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT_STRICT_MODE: u32               = 0x0002;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_SET_CONTEXT_IP_VALIDATION_RELAXED_MODE: u32 = 0x0004;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_DYNAMIC_APIS_ALLOW_IN_PROC: u32       = 0x0008;
+pub const IMAGE_DLLCHARACTERISTICS_EX_FORWARD_CFI_COMPAT: u32                   = 0x0040;
@@ -1,9 +1,23 @@
 // Reexported later under pe32 and pe64.
 mod image;
+mod error;
+mod pod;
+
+pub use error::PeError;
+pub use pod::Pod;
 
 pub mod pe32;
 pub mod pe64;
 pub mod resources;
+pub mod security;
+pub mod overlay;
+pub mod checksum;
+pub mod rich;
+pub mod pkcs7;
+pub mod apiset;
+pub mod wrap;
+
+pub use wrap::Wrap;
 
 /// Defaults to the current platform if it is available.
 #[cfg(all(windows, target_pointer_width = "32"))]
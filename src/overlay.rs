@@ -0,0 +1,58 @@
+//! Overlay.
+//!
+//! Data appended to the file after the last section, eg. installer payloads and self-extracting archives.
+//! Like the certificate table it is not part of any section and never mapped by the loader, only backends with access to the file on disk can find it.
+
+use std::fmt;
+use std::ops::Range;
+
+//----------------------------------------------------------------
+
+/// Overlay data.
+#[derive(Copy, Clone)]
+pub struct Overlay<'a> {
+	offset: usize,
+	data: &'a [u8],
+}
+
+impl<'a> Overlay<'a> {
+	/// Wrap the overlay data.
+	///
+	/// # Parameters
+	///
+	/// * `offset`
+	///
+	///   File offset of the start of the overlay.
+	///
+	/// * `data`
+	///
+	///   The bytes of the overlay.
+	#[inline]
+	pub fn new(offset: usize, data: &'a [u8]) -> Overlay<'a> {
+		Overlay {
+			offset: offset,
+			data: data,
+		}
+	}
+	/// Get the file offset of the start of the overlay.
+	#[inline]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+	/// Get the file offset range of the overlay.
+	#[inline]
+	pub fn range(&self) -> Range<usize> {
+		self.offset..self.offset + self.data.len()
+	}
+	/// Get the overlay data.
+	#[inline]
+	pub fn data(&self) -> &'a [u8] {
+		self.data
+	}
+}
+
+impl<'a> fmt::Display for Overlay<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "Overlay Offset {:#X} Length {}", self.offset, self.data.len())
+	}
+}
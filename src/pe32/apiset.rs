@@ -0,0 +1,34 @@
+//! API set schema.
+//!
+//! See `pelite::apiset` for more info.
+
+use std::cmp;
+
+use super::pe::Pe;
+use error::{PeError, unwrap_null};
+use apiset::ApiSetSchema;
+
+pub trait PeApiSet<'a>: Pe<'a> + Sized {
+	/// Get the API set schema, only present in `apisetschema.dll`.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if there is no `.apiset` section.
+	///
+	/// `PeError::Bounds` if the section is out of bounds of the image.
+	///
+	/// Otherwise any error from parsing the schema, see `ApiSetSchema::parse`.
+	fn try_api_set_schema(&self) -> Result<ApiSetSchema, PeError> {
+		let section = try!(self.section_headers().iter().find(|section| &section.Name == b".apiset\0").ok_or(PeError::Null));
+		let bytes = try!(self.rva_to_slice(section.VirtualAddress));
+		ApiSetSchema::parse(&bytes[..cmp::min(bytes.len(), section.VirtualSize as usize)])
+	}
+	/// Get the API set schema.
+	///
+	/// Convenience wrapper around `try_api_set_schema`, panics if the schema is corrupt.
+	fn api_set_schema(&self) -> Option<ApiSetSchema> {
+		unwrap_null(self.try_api_set_schema())
+	}
+}
+
+impl<'a, P: Pe<'a>> PeApiSet<'a> for P {}
@@ -0,0 +1,263 @@
+//! PE bound imports.
+//!
+//! Binding precomputes the import address table against specific versions of the imported DLLs.
+//! The loader only trusts the bound addresses if the timestamps recorded here match the DLLs it actually loads.
+
+use std::{fmt, mem, slice};
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use error::{PeError, unwrap_null};
+
+//----------------------------------------------------------------
+
+/// Bound imports directory.
+pub struct BoundImportDirectory<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	datadir_: &'a ImageDataDirectory,
+}
+
+impl<'a, 'b, P: Pe<'a>> BoundImportDirectory<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Iterate over the bound import descriptors.
+	#[inline]
+	pub fn iter<'c>(&'c self) -> BoundImportDescriptorIterator<'a, 'c, P> {
+		BoundImportDescriptorIterator {
+			bound: self,
+			it: self.datadir_.VirtualAddress,
+		}
+	}
+	/// Find the bound import descriptor for a DLL.
+	///
+	/// # Parameters
+	///
+	/// * `dll_name`
+	///
+	///   Name of the DLL as it appears in the import descriptor, compared case insensitively.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if the DLL is not bound.
+	///
+	/// Any error while iterating the descriptors.
+	pub fn try_find<'c>(&'c self, dll_name: &str) -> Result<BoundImportDescriptor<'a, 'c, P>, PeError> {
+		let mut it = self.iter();
+		while let Some(desc) = try!(it.try_next()) {
+			if try!(desc.try_module_name()).eq_ignore_ascii_case(dll_name) {
+				return Ok(desc);
+			}
+		}
+		Err(PeError::Null)
+	}
+	/// Find the bound import descriptor for a DLL.
+	///
+	/// Convenience wrapper around `try_find`.
+	#[inline]
+	pub fn find<'c>(&'c self, dll_name: &str) -> Option<BoundImportDescriptor<'a, 'c, P>> {
+		unwrap_null(self.try_find(dll_name))
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for BoundImportDirectory<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for desc in self.iter() {
+			try!(write!(f, "{}", desc));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+pub trait PeBoundImports<'a>: Pe<'a> + Sized {
+	/// Get the bound imports directory.
+	///
+	/// `PeError::Null` if the image is not bound.
+	fn try_bound_imports<'b>(&'b self) -> Result<BoundImportDirectory<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT).ok_or(PeError::Null));
+		if datadir.VirtualAddress == BADRVA {
+			return Err(PeError::Null);
+		}
+		Ok(BoundImportDirectory {
+			view_: self,
+			datadir_: datadir,
+		})
+	}
+	/// Get the bound imports directory.
+	///
+	/// Convenience wrapper around `try_bound_imports`.
+	fn bound_imports<'b>(&'b self) -> Option<BoundImportDirectory<'a, 'b, Self>> {
+		unwrap_null(self.try_bound_imports())
+	}
+}
+
+impl<'a, P: Pe<'a>> PeBoundImports<'a> for P {}
+
+//----------------------------------------------------------------
+
+pub struct BoundImportDescriptorIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	bound: &'b BoundImportDirectory<'a, 'b, P>,
+	it: Rva,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for BoundImportDescriptorIterator<'a, 'b, P> {
+	type Item = BoundImportDescriptor<'a, 'b, P>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> BoundImportDescriptorIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the bound import directory is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<BoundImportDescriptor<'a, 'b, P>>, PeError> {
+		let datadir = self.bound.datadir_;
+		if self.it as u64 >= datadir.VirtualAddress as u64 + datadir.Size as u64 {
+			return Ok(None);
+		}
+		let view = self.bound.view_;
+		let image = try!(view.try_read_struct::<ImageBoundImportDescriptor>(self.it));
+		if image.TimeDateStamp == 0 && image.OffsetModuleName == 0 {
+			return Ok(None);
+		}
+		let forwarders_rva = self.it + mem::size_of::<ImageBoundImportDescriptor>() as Rva;
+		let forwarders = try!(view.try_read_slice::<ImageBoundForwarderRef>(forwarders_rva, image.NumberOfModuleForwarderRefs as usize));
+		self.it = forwarders_rva + mem::size_of_val(forwarders) as Rva;
+		Ok(Some(BoundImportDescriptor {
+			bound: self.bound,
+			image_: image,
+			forwarders_: forwarders,
+		}))
+	}
+}
+
+//----------------------------------------------------------------
+
+/// DLL the imports are bound to.
+pub struct BoundImportDescriptor<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	bound: &'b BoundImportDirectory<'a, 'b, P>,
+	image_: &'a ImageBoundImportDescriptor,
+	forwarders_: &'a [ImageBoundForwarderRef],
+}
+
+impl<'a, 'b, P: Pe<'a>> BoundImportDescriptor<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.bound.view_
+	}
+	/// Get the underlying bound import descriptor image.
+	#[inline]
+	pub fn image(&self) -> &'a ImageBoundImportDescriptor {
+		self.image_
+	}
+	/// Get the timestamp of the DLL the imports were bound to.
+	///
+	/// The binding is stale if this does not match the `TimeDateStamp` in the file header of the DLL that gets loaded.
+	#[inline]
+	pub fn time_date_stamp(&self) -> u32 {
+		self.image_.TimeDateStamp
+	}
+	/// Get the name of the DLL the imports were bound to.
+	#[inline]
+	pub fn try_module_name(&self) -> Result<&'a str, PeError> {
+		self.bound.try_module_name(self.image_.OffsetModuleName)
+	}
+	/// Get the name of the DLL the imports were bound to.
+	///
+	/// Convenience wrapper around `try_module_name`, panics if the name is corrupt.
+	#[inline]
+	pub fn module_name(&self) -> &'a str {
+		self.try_module_name().unwrap()
+	}
+	/// Get the underlying forwarder refs image.
+	#[inline]
+	pub fn forwarders_image(&self) -> &'a [ImageBoundForwarderRef] {
+		self.forwarders_
+	}
+	/// Iterate over the DLLs this DLL forwards bound imports to.
+	#[inline]
+	pub fn forwarders<'c>(&'c self) -> BoundForwarderIterator<'a, 'c, P> {
+		BoundForwarderIterator {
+			bound: self.bound,
+			it: self.forwarders_.iter(),
+		}
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for BoundImportDescriptor<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "Bound to {} TimeDateStamp {:>08X}", self.module_name(), self.time_date_stamp()));
+		for fwd in self.forwarders() {
+			try!(writeln!(f, "  Forwarder {} TimeDateStamp {:>08X}", fwd.module_name(), fwd.time_date_stamp()));
+		}
+		Ok(())
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> BoundImportDirectory<'a, 'b, P> {
+	// Module names are relative to the start of the bound import directory
+	fn try_module_name(&self, offset: u16) -> Result<&'a str, PeError> {
+		self.view_.try_read_str(self.datadir_.VirtualAddress + offset as Rva)
+	}
+}
+
+//----------------------------------------------------------------
+
+pub struct BoundForwarderIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	bound: &'b BoundImportDirectory<'a, 'b, P>,
+	it: slice::Iter<'a, ImageBoundForwarderRef>,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for BoundForwarderIterator<'a, 'b, P> {
+	type Item = BoundForwarder<'a, 'b, P>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let bound = self.bound;
+		self.it.next().map(|image| BoundForwarder {
+			bound: bound,
+			image_: image,
+		})
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.it.size_hint()
+	}
+}
+
+/// DLL a bound DLL forwards some of the bound imports to.
+pub struct BoundForwarder<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	bound: &'b BoundImportDirectory<'a, 'b, P>,
+	image_: &'a ImageBoundForwarderRef,
+}
+
+impl<'a, 'b, P: Pe<'a>> BoundForwarder<'a, 'b, P> {
+	/// Get the underlying forwarder ref image.
+	#[inline]
+	pub fn image(&self) -> &'a ImageBoundForwarderRef {
+		self.image_
+	}
+	/// Get the timestamp of the DLL the forwarded imports were bound to.
+	#[inline]
+	pub fn time_date_stamp(&self) -> u32 {
+		self.image_.TimeDateStamp
+	}
+	/// Get the name of the DLL the forwarded imports were bound to.
+	#[inline]
+	pub fn try_module_name(&self) -> Result<&'a str, PeError> {
+		self.bound.try_module_name(self.image_.OffsetModuleName)
+	}
+	/// Get the name of the DLL the forwarded imports were bound to.
+	///
+	/// Convenience wrapper around `try_module_name`, panics if the name is corrupt.
+	#[inline]
+	pub fn module_name(&self) -> &'a str {
+		self.try_module_name().unwrap()
+	}
+}
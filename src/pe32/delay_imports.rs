@@ -0,0 +1,282 @@
+//! PE delay-load imports.
+//!
+//! Delay-loaded DLLs are only loaded the first time one of their imports is called.
+
+use std::{fmt, mem};
+use std::marker::PhantomData;
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use super::imports::ImportedSymbol;
+use error::{PeError, unwrap_null};
+
+//----------------------------------------------------------------
+
+/// Delay-load imports directory.
+pub struct DelayImportDirectory<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	datadir_: &'a ImageDataDirectory,
+}
+
+impl<'a, 'b, P: Pe<'a>> DelayImportDirectory<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Iterate over the delay-load import descriptors.
+	#[inline]
+	pub fn iter(&self) -> DelayImportDescriptorIterator<'a, 'b, P> {
+		DelayImportDescriptorIterator {
+			view: self.view_,
+			it: self.datadir_.VirtualAddress,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for DelayImportDirectory<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for desc in self.iter() {
+			try!(write!(f, "{}", desc));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+pub trait PeDelayImports<'a>: Pe<'a> + Sized {
+	/// Get the delay-load imports directory.
+	///
+	/// `PeError::Null` if the image has no delay-load imports.
+	fn try_delay_imports<'b>(&'b self) -> Result<DelayImportDirectory<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT).ok_or(PeError::Null));
+		if datadir.VirtualAddress == BADRVA {
+			return Err(PeError::Null);
+		}
+		Ok(DelayImportDirectory {
+			view_: self,
+			datadir_: datadir,
+		})
+	}
+	/// Get the delay-load imports directory.
+	///
+	/// Convenience wrapper around `try_delay_imports`.
+	fn delay_imports<'b>(&'b self) -> Option<DelayImportDirectory<'a, 'b, Self>> {
+		unwrap_null(self.try_delay_imports())
+	}
+}
+
+impl<'a, P: Pe<'a>> PeDelayImports<'a> for P {}
+
+//----------------------------------------------------------------
+
+pub struct DelayImportDescriptorIterator<'a, 'b, P: 'b = PeView<'a>> {
+	view: &'b P,
+	it: Rva,
+	_marker: PhantomData<&'a ()>,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for DelayImportDescriptorIterator<'a, 'b, P> {
+	type Item = DelayImportDescriptor<'a, 'b, P>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> DelayImportDescriptorIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the delay-load import directory is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<DelayImportDescriptor<'a, 'b, P>>, PeError> {
+		let image = try!(self.view.try_read_struct::<ImageDelayloadDescriptor>(self.it));
+		// The delay-load helper stops at the first descriptor without a name
+		if image.DllNameRVA == 0 {
+			Ok(None)
+		}
+		else {
+			self.it += mem::size_of::<ImageDelayloadDescriptor>() as Rva;
+			Ok(Some(DelayImportDescriptor {
+				view_: self.view,
+				image_: image,
+			}))
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+pub struct DelayImportDescriptor<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	image_: &'a ImageDelayloadDescriptor,
+}
+
+impl<'a, 'b, P: Pe<'a>> DelayImportDescriptor<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the underlying delay-load descriptor image.
+	#[inline]
+	pub fn image(&self) -> &'a ImageDelayloadDescriptor {
+		self.image_
+	}
+	/// Get whether the addresses in this descriptor are Rvas.
+	///
+	/// Images linked by Visual C++ 6.0 and older use Vas instead, they are converted to Rvas by the accessors.
+	#[inline]
+	pub fn is_rva_based(&self) -> bool {
+		self.image_.Attributes & DLATTR_RVA != 0
+	}
+	/// Convert an address stored in the descriptor or its thunks to an Rva according to the attributes.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if `address` is zero.
+	///
+	/// `PeError::Bounds` if a legacy Va does not point within the image.
+	pub fn try_to_rva(&self, address: Va) -> Result<Rva, PeError> {
+		if address == BADVA {
+			Err(PeError::Null)
+		}
+		else if self.is_rva_based() {
+			Ok(address as Rva)
+		}
+		else {
+			self.view_.try_va_to_rva(address)
+		}
+	}
+	/// Get the DLL name imported from.
+	#[inline]
+	pub fn try_dll_name(&self) -> Result<&'a str, PeError> {
+		let rva = try!(self.try_to_rva(self.image_.DllNameRVA as Va));
+		self.view_.try_read_str(rva)
+	}
+	/// Get the DLL name imported from.
+	///
+	/// Convenience wrapper around `try_dll_name`, panics if the name is corrupt.
+	#[inline]
+	pub fn dll_name(&self) -> &'a str {
+		self.try_dll_name().unwrap()
+	}
+	/// Iterate over the import name table.
+	#[inline]
+	pub fn int_iter<'c>(&'c self) -> DelayImportNameIterator<'a, 'c, P> {
+		DelayImportNameIterator {
+			desc: self,
+			it: self.image_.ImportNameTableRVA,
+		}
+	}
+	/// Iterate over the import address table.
+	///
+	/// Until the DLL is loaded these point to stubs calling the delay-load helper.
+	#[inline]
+	pub fn iat_iter<'c>(&'c self) -> DelayImportTableIterator<'a, 'c, P> {
+		DelayImportTableIterator {
+			desc: self,
+			it: self.image_.ImportAddressTableRVA,
+		}
+	}
+	// Reads the thunk at `it` which is an address in the format given by the attributes.
+	fn try_read_thunk(&self, it: u32) -> Result<&'a Va, PeError> {
+		let rva = try!(self.try_to_rva(it as Va));
+		self.view_.try_read_struct::<Va>(rva)
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for DelayImportDescriptor<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "Delay imports from {}", self.dll_name()));
+		let image = self.image_;
+		let (attributes, time_date_stamp, module_handle, iat) = (image.Attributes, image.TimeDateStamp, image.ModuleHandleRVA, image.ImportAddressTableRVA);
+		try!(writeln!(f, "  Attributes:     {:>08X}", attributes));
+		try!(writeln!(f, "  TimeDateStamp:  {}", time_date_stamp));
+		try!(writeln!(f, "  ModuleHandle:   {:>08X}", module_handle));
+		try!(writeln!(f, "  IAT:            {:>08X}", iat));
+		for thunk in self.int_iter() {
+			try!(writeln!(f, "  {}", thunk));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+pub struct DelayImportNameIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	desc: &'b DelayImportDescriptor<'a, 'b, P>,
+	it: u32,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for DelayImportNameIterator<'a, 'b, P> {
+	type Item = ImportedSymbol<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> DelayImportNameIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the import name table is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<ImportedSymbol<'a>>, PeError> {
+		// Without a table there is nothing to iterate over
+		if self.it == 0 {
+			return Ok(None);
+		}
+		let va = try!(self.desc.try_read_thunk(self.it));
+		if *va != BADVA {
+			let symbol = if *va & IMAGE_ORDINAL_FLAG == 0 {
+				let rva = try!(self.desc.try_to_rva(*va));
+				let hint = try!(self.desc.view_.try_read_struct::<u16>(rva));
+				let name = try!(self.desc.view_.try_read_str(rva.wrapping_add(2)));
+				ImportedSymbol::ByName { hint: *hint, name: name }
+			}
+			else {
+				ImportedSymbol::ByOrdinal { ord: (*va & 0xFFFF) as u16 }
+			};
+			self.it += mem::size_of::<Va>() as u32;
+			Ok(Some(symbol))
+		}
+		else {
+			Ok(None)
+		}
+	}
+}
+
+pub struct DelayImportTableIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	desc: &'b DelayImportDescriptor<'a, 'b, P>,
+	it: u32,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for DelayImportTableIterator<'a, 'b, P> {
+	type Item = &'a Va;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> DelayImportTableIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the import address table is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<&'a Va>, PeError> {
+		// Without a table there is nothing to iterate over
+		if self.it == 0 {
+			return Ok(None);
+		}
+		let va = try!(self.desc.try_read_thunk(self.it));
+		if *va != BADVA {
+			self.it += mem::size_of::<Va>() as u32;
+			Ok(Some(va))
+		}
+		else {
+			Ok(None)
+		}
+	}
+}
@@ -0,0 +1,156 @@
+//! Comparing a module dumped from memory against its file on disk.
+//!
+//! The loader modifies a mapped module in predictable ways: relocations are applied when it is not loaded at its preferred base
+//! and the import address tables are filled in with the addresses of the imported functions.
+//! Any other difference is a patch, eg. an inline hook or a modified pointer.
+
+use std::{cmp, fmt, mem, str};
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use super::relocs::PeRelocs;
+use super::imports::PeImports;
+use super::delay_imports::PeDelayImports;
+use error::PeError;
+
+//----------------------------------------------------------------
+
+/// Range of modified bytes.
+#[derive(Copy, Clone)]
+pub struct Patch<'a> {
+	/// Rva of the first modified byte.
+	pub begin: Rva,
+	/// Rva one past the last modified byte.
+	pub end: Rva,
+	/// The section containing the modified bytes.
+	pub section: &'a ImageSectionHeader,
+}
+
+impl<'a> Patch<'a> {
+	/// Get the name of the section containing the modified bytes.
+	///
+	/// Returns an empty string if the name is not valid UTF-8.
+	pub fn section_name(&self) -> &'a str {
+		let name = &self.section.Name;
+		let len = name.iter().position(|&byte| byte == 0).unwrap_or(name.len());
+		str::from_utf8(&name[..len]).unwrap_or("")
+	}
+}
+
+impl<'a> fmt::Display for Patch<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "Patch {:>08X}..{:>08X} ({} bytes) in {}", self.begin, self.end, self.end - self.begin, self.section_name())
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Compare a module dumped from memory against its file on disk.
+///
+/// # Parameters
+///
+/// * `file`
+///
+///   The module as mapped from disk, eg. with `super::pefile::PeFile`.
+///
+/// * `dump`
+///
+///   The module dumped from memory. Its image base is taken from its headers, the loader updates it when relocating the module.
+///
+/// # Return value
+///
+/// `PeError::Insanity` if the images have a different size, they're not the same module.
+///
+/// Any error from reading the relocations or imports of `file`.
+///
+/// Otherwise the modified ranges ordered by Rva.
+///
+/// # Remarks
+///
+/// The relocations of `file` are applied to a copy before comparing, so relocated slots are only reported if they point elsewhere.
+/// The import address tables of both the imports and the delay-load imports are ignored.
+///
+/// Only the sections are compared, the headers are expected to differ. Writable sections contain the module's global variables
+/// which are naturally modified at runtime, filter by `Patch::section` characteristics as needed.
+pub fn diff<'a, 'b>(file: &PeView<'a>, dump: &PeView<'b>) -> Result<Vec<Patch<'a>>, PeError> {
+	let image = file.image();
+	if image.len() != dump.image().len() {
+		return Err(PeError::Insanity);
+	}
+
+	//---------------- Normalize the relocated slots
+	let mut expected = image.to_vec();
+	if let Some(relocs) = file.relocs() {
+		let delta = (dump.virtual_base() as u64).wrapping_sub(file.virtual_base() as u64);
+		let mut it = relocs.fixups();
+		while let Some(fixup) = try!(it.try_next()) {
+			let bytes = try!(expected.get_mut(fixup.rva as usize..fixup.rva as usize + fixup.size()).ok_or(PeError::Bounds));
+			fixup.apply(bytes, delta);
+		}
+	}
+
+	//---------------- Mask the import address tables
+	let mut ignore = vec![false; image.len()];
+	{
+		let mut mask = |rva: Rva, count: usize| -> Result<(), PeError> {
+			let end = rva as usize + count * mem::size_of::<Va>();
+			let range = try!(ignore.get_mut(rva as usize..end).ok_or(PeError::Bounds));
+			for it in range {
+				*it = true;
+			}
+			Ok(())
+		};
+		if let Some(imports) = file.imports() {
+			let mut it = imports.iter();
+			while let Some(desc) = try!(it.try_next()) {
+				let mut count = 0;
+				let mut iat = desc.iat_iter();
+				while try!(iat.try_next()).is_some() {
+					count += 1;
+				}
+				try!(mask(desc.image().FirstThunk, count));
+			}
+		}
+		if let Some(delay_imports) = file.delay_imports() {
+			let mut it = delay_imports.iter();
+			while let Some(desc) = try!(it.try_next()) {
+				let mut count = 0;
+				let mut iat = desc.iat_iter();
+				while try!(iat.try_next()).is_some() {
+					count += 1;
+				}
+				let rva = try!(desc.try_to_rva(desc.image().ImportAddressTableRVA as Va));
+				try!(mask(rva, count));
+			}
+		}
+	}
+
+	//---------------- Compare the sections
+	let dump_image = dump.image();
+	let mut patches = Vec::new();
+	for section in file.section_headers() {
+		// Sections without a virtual size are mapped by their raw size, the same as the loader does
+		let begin = section.VirtualAddress as usize;
+		let size = cmp::max(section.VirtualSize, section.SizeOfRawData) as usize;
+		let end = cmp::min(begin.saturating_add(size), image.len());
+		let mut rva = begin;
+		while rva < end {
+			if ignore[rva] || expected[rva] == dump_image[rva] {
+				rva += 1;
+				continue;
+			}
+			let patch_begin = rva;
+			while rva < end && !ignore[rva] && expected[rva] != dump_image[rva] {
+				rva += 1;
+			}
+			patches.push(Patch {
+				begin: patch_begin as Rva,
+				end: rva as Rva,
+				section: section,
+			});
+		}
+	}
+	patches.sort_by_key(|patch| patch.begin);
+	Ok(patches)
+}
@@ -6,6 +6,8 @@ pub const IMAGE_NT_OPTIONAL_HDR_MAGIC: u16 = IMAGE_NT_OPTIONAL_HDR32_MAGIC;
 
 pub type ImageOptionalHeader = ImageOptionalHeader32;
 pub type ImageNtHeaders = ImageNtHeaders32;
+pub type ImageTlsDirectory = ImageTlsDirectory32;
+pub type ImageLoadConfigDirectory = ImageLoadConfigDirectory32;
 
 pub const IMAGE_ORDINAL_FLAG: u32 = IMAGE_ORDINAL_FLAG32;
 
@@ -1,9 +1,12 @@
 //! PE imports.
 
 use std::{fmt, mem};
+use std::marker::PhantomData;
 
 use super::image::*;
+use super::pe::Pe;
 use super::peview::PeView;
+use error::{PeError, unwrap_null};
 
 //----------------------------------------------------------------
 
@@ -34,28 +37,29 @@ impl<'a> fmt::Display for ImportedSymbol<'a> {
 //----------------------------------------------------------------
 
 /// Imports directory.
-pub struct ImportDirectory<'a: 'b, 'b> {
-	view_: &'b PeView<'a>,
+pub struct ImportDirectory<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
 	datadir_: &'a ImageDataDirectory,
 }
 
-impl<'a, 'b> ImportDirectory<'a, 'b> {
-	/// Get the associated `PeView`.
+impl<'a, 'b, P: Pe<'a>> ImportDirectory<'a, 'b, P> {
+	/// Get the associated `Pe` image.
 	#[inline]
-	pub fn view(&self) -> &PeView {
+	pub fn view(&self) -> &'b P {
 		self.view_
 	}
 	/// Iterate over the import descriptors.
 	#[inline]
-	pub fn iter(&'a self) -> ImportDescriptorIterator<'a, 'b> {
+	pub fn iter(&self) -> ImportDescriptorIterator<'a, 'b, P> {
 		ImportDescriptorIterator {
 			view: self.view_,
 			it: self.datadir_.VirtualAddress,
+			_marker: PhantomData,
 		}
 	}
 }
 
-impl<'a, 'b> fmt::Display for ImportDirectory<'a, 'b> {
+impl<'a, 'b, P: Pe<'a>> fmt::Display for ImportDirectory<'a, 'b, P> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		for desc in self.iter() {
 			try!(write!(f, "{}", desc));
@@ -66,41 +70,52 @@ impl<'a, 'b> fmt::Display for ImportDirectory<'a, 'b> {
 
 //----------------------------------------------------------------
 
-pub trait PeImports {
-	fn imports(&self) -> Option<ImportDirectory>;
-}
-
-impl<'a> PeImports for PeView<'a> {
-	fn imports(&self) -> Option<ImportDirectory> {
-		if let Some(datadir) = self.data_directory().get(IMAGE_DIRECTORY_ENTRY_IMPORT) {
-			if datadir.VirtualAddress != BADRVA {
-				Some(ImportDirectory {
-					view_: self,
-					datadir_: datadir,
-				})
-			}
-			else {
-				None
-			}
-		}
-		else {
-			None
+pub trait PeImports<'a>: Pe<'a> + Sized {
+	/// Get the imports directory.
+	///
+	/// `PeError::Null` if the image has no imports.
+	fn try_imports<'b>(&'b self) -> Result<ImportDirectory<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_IMPORT).ok_or(PeError::Null));
+		if datadir.VirtualAddress == BADRVA {
+			return Err(PeError::Null);
 		}
+		Ok(ImportDirectory {
+			view_: self,
+			datadir_: datadir,
+		})
+	}
+	/// Get the imports directory.
+	///
+	/// Convenience wrapper around `try_imports`.
+	fn imports<'b>(&'b self) -> Option<ImportDirectory<'a, 'b, Self>> {
+		unwrap_null(self.try_imports())
 	}
 }
 
+impl<'a, P: Pe<'a>> PeImports<'a> for P {}
+
 //----------------------------------------------------------------
 
-pub struct ImportDescriptorIterator<'a: 'b, 'b> {
-	view: &'b PeView<'a>,
+pub struct ImportDescriptorIterator<'a, 'b, P: 'b = PeView<'a>> {
+	view: &'b P,
 	it: Rva,
+	_marker: PhantomData<&'a ()>,
 }
 
-impl<'a, 'b> Iterator for ImportDescriptorIterator<'a, 'b> {
-	type Item = ImportDescriptor<'a, 'b>;
+impl<'a, 'b, P: Pe<'a>> Iterator for ImportDescriptorIterator<'a, 'b, P> {
+	type Item = ImportDescriptor<'a, 'b, P>;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let image = self.view.read_struct::<ImageImportDescriptor>(self.it).unwrap();
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> ImportDescriptorIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the import directory is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<ImportDescriptor<'a, 'b, P>>, PeError> {
+		let image = try!(self.view.try_read_struct::<ImageImportDescriptor>(self.it));
 		fn is_sentinel(image: &ImageImportDescriptor) -> bool {
 			// Documentation says all fields must be zeroed,
 			// but you can (probably) get away just checking OriginalFirstThunk...
@@ -111,29 +126,29 @@ impl<'a, 'b> Iterator for ImportDescriptorIterator<'a, 'b> {
 			image.FirstThunk == BADRVA
 		}
 		if is_sentinel(image) {
-			None
+			Ok(None)
 		}
 		else {
 			self.it += mem::size_of::<ImageImportDescriptor>() as Rva;
-			Some(ImportDescriptor {
+			Ok(Some(ImportDescriptor {
 				view_: self.view,
 				image_: image,
-			})
+			}))
 		}
 	}
 }
 
 //----------------------------------------------------------------
 
-pub struct ImportDescriptor<'a: 'b, 'b> {
-	view_: &'b PeView<'a>,
+pub struct ImportDescriptor<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
 	image_: &'a ImageImportDescriptor,
 }
 
-impl<'a, 'b> ImportDescriptor<'a, 'b> {
-	/// Get the associated `PeView`.
+impl<'a, 'b, P: Pe<'a>> ImportDescriptor<'a, 'b, P> {
+	/// Get the associated `Pe` image.
 	#[inline]
-	pub fn view(&self) -> &'b PeView {
+	pub fn view(&self) -> &'b P {
 		self.view_
 	}
 	/// Get the underlying import descriptor image.
@@ -143,20 +158,30 @@ impl<'a, 'b> ImportDescriptor<'a, 'b> {
 	}
 	/// Get the DLL name imported from.
 	#[inline]
+	pub fn try_dll_name(&self) -> Result<&'a str, PeError> {
+		self.view_.try_read_str(self.image_.Name)
+	}
+	/// Get the DLL name imported from.
+	///
+	/// Convenience wrapper around `try_dll_name`, panics if the name is corrupt.
+	#[inline]
 	pub fn dll_name(&self) -> &'a str {
-		self.view_.read_str(self.image_.Name).unwrap()
+		self.try_dll_name().unwrap()
 	}
 	/// Iterate over the import name table.
+	///
+	/// Some linkers and packers leave out the import name table, the names are then read from the import address table like the system loader does.
+	/// This only works before the imports are bound, the import address table is overwritten with the addresses of the symbols.
 	#[inline]
-	pub fn int_iter(&self) -> ImportNameIterator {
+	pub fn int_iter<'c>(&'c self) -> ImportNameIterator<'a, 'c, P> {
 		ImportNameIterator {
 			desc: self,
-			it: self.image_.OriginalFirstThunk,
+			it: if self.image_.OriginalFirstThunk != BADRVA { self.image_.OriginalFirstThunk } else { self.image_.FirstThunk },
 		}
 	}
 	/// Iterate over the import address table.
 	#[inline]
-	pub fn iat_iter(&self) -> ImportTableIterator {
+	pub fn iat_iter<'c>(&'c self) -> ImportTableIterator<'a, 'c, P> {
 		ImportTableIterator {
 			desc: self,
 			it: self.image_.FirstThunk,
@@ -164,7 +189,7 @@ impl<'a, 'b> ImportDescriptor<'a, 'b> {
 	}
 }
 
-impl<'a, 'b> fmt::Display for ImportDescriptor<'a, 'b> {
+impl<'a, 'b, P: Pe<'a>> fmt::Display for ImportDescriptor<'a, 'b, P> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		try!(writeln!(f, "Imports from {}", self.dll_name()));
 		try!(writeln!(f, "  TimeDateStamp:  {}", self.image_.TimeDateStamp));
@@ -179,49 +204,68 @@ impl<'a, 'b> fmt::Display for ImportDescriptor<'a, 'b> {
 
 //----------------------------------------------------------------
 
-pub struct ImportNameIterator<'a: 'b, 'b> {
-	desc: &'b ImportDescriptor<'a, 'b>,
+pub struct ImportNameIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	desc: &'b ImportDescriptor<'a, 'b, P>,
 	it: Rva,
 }
 
-impl<'a, 'b> Iterator for ImportNameIterator<'a, 'b> {
+impl<'a, 'b, P: Pe<'a>> Iterator for ImportNameIterator<'a, 'b, P> {
 	type Item = ImportedSymbol<'a>;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let va = self.desc.view_.read_struct::<Va>(self.it).unwrap();
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> ImportNameIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the import name table is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<ImportedSymbol<'a>>, PeError> {
+		let va = try!(self.desc.view_.try_read_struct::<Va>(self.it));
 		if *va != BADVA {
-			self.it += mem::size_of::<Va>() as Rva;
-			if *va & IMAGE_ORDINAL_FLAG == 0 {
-				let hint = self.desc.view_.read_struct::<u16>(*va as Rva).unwrap();
-				let name = self.desc.view_.read_str(*va as Rva + 2).unwrap();
-				Some(ImportedSymbol::ByName { hint: *hint, name: name })
+			let symbol = if *va & IMAGE_ORDINAL_FLAG == 0 {
+				let hint = try!(self.desc.view_.try_read_struct::<u16>(*va as Rva));
+				let name = try!(self.desc.view_.try_read_str((*va as Rva).wrapping_add(2)));
+				ImportedSymbol::ByName { hint: *hint, name: name }
 			}
 			else {
-				Some(ImportedSymbol::ByOrdinal { ord: (*va & 0xFFFF) as u16 })
-			}
+				ImportedSymbol::ByOrdinal { ord: (*va & 0xFFFF) as u16 }
+			};
+			self.it += mem::size_of::<Va>() as Rva;
+			Ok(Some(symbol))
 		}
 		else {
-			None
+			Ok(None)
 		}
 	}
 }
 
-pub struct ImportTableIterator<'a: 'b, 'b> {
-	desc: &'b ImportDescriptor<'a, 'b>,
+pub struct ImportTableIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	desc: &'b ImportDescriptor<'a, 'b, P>,
 	it: Rva,
 }
 
-impl<'a, 'b> Iterator for ImportTableIterator<'a, 'b> {
+impl<'a, 'b, P: Pe<'a>> Iterator for ImportTableIterator<'a, 'b, P> {
 	type Item = &'a Va;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let va = self.desc.view_.read_struct::<Va>(self.it).unwrap();
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> ImportTableIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the import address table is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<&'a Va>, PeError> {
+		let va = try!(self.desc.view_.try_read_struct::<Va>(self.it));
 		if *va != BADVA {
 			self.it += mem::size_of::<Va>() as Rva;
-			Some(va)
+			Ok(Some(va))
 		}
 		else {
-			None
+			Ok(None)
 		}
 	}
 }
@@ -0,0 +1,286 @@
+//! Load config directory.
+//!
+//! The load config directory has grown with every new security feature, its `Size` member tells which fields are present.
+
+use std::{cmp, fmt, mem, ptr, slice};
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use error::{PeError, unwrap_null};
+
+//----------------------------------------------------------------
+
+/// Load config directory.
+///
+/// Only the first `Size` bytes of the directory are read, the fields beyond are zero.
+/// Since a zero value means a feature is not used, the fields can be inspected without checking which version is present.
+pub struct LoadConfig<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	bytes_: &'a [u8],
+	image_: ImageLoadConfigDirectory,
+}
+
+impl<'a, 'b, P: Pe<'a>> LoadConfig<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the load config directory image.
+	///
+	/// This is a copy with the fields not present in this version set to zero.
+	#[inline]
+	pub fn image(&self) -> &ImageLoadConfigDirectory {
+		&self.image_
+	}
+	/// Get the bytes of the load config directory as they are in the image.
+	///
+	/// This may be larger than `ImageLoadConfigDirectory` for newer versions not yet known to this library.
+	#[inline]
+	pub fn bytes(&self) -> &'a [u8] {
+		self.bytes_
+	}
+	/// Get the size of the load config directory, identifies its version.
+	#[inline]
+	pub fn size(&self) -> u32 {
+		self.image_.Size
+	}
+	/// Get the Va of the security cookie used by `/GS`.
+	#[inline]
+	pub fn security_cookie(&self) -> Va {
+		self.image_.SecurityCookie
+	}
+	/// Get the control flow guard flags, see the `IMAGE_GUARD_*` constants.
+	#[inline]
+	pub fn guard_flags(&self) -> u32 {
+		self.image_.GuardFlags
+	}
+	/// Get the offset and section index of the dynamic value relocation table.
+	///
+	/// The section index is one based, zero means there is no dynamic value relocation table.
+	#[inline]
+	pub fn dynamic_value_reloc_table(&self) -> (u32, u16) {
+		(self.image_.DynamicValueRelocTableOffset, self.image_.DynamicValueRelocTableSection)
+	}
+	/// Get the Va of the hybrid PE metadata for ARM64X and CHPE images.
+	#[inline]
+	pub fn chpe_metadata_pointer(&self) -> Va {
+		self.image_.CHPEMetadataPointer
+	}
+	/// Get the safe exception handler table.
+	///
+	/// # Return value
+	///
+	/// The Rvas of the valid exception handlers, empty if there is no table.
+	///
+	/// `PeError::Bounds` if the table is out of range.
+	pub fn try_se_handlers(&self) -> Result<&'a [Rva], PeError> {
+		let (table, count) = (self.image_.SEHandlerTable, self.image_.SEHandlerCount);
+		if table == BADVA {
+			return Ok(&[]);
+		}
+		let rva = try!(self.view_.try_va_to_rva(table));
+		self.view_.try_read_slice::<Rva>(rva, count as usize)
+	}
+	/// Get the safe exception handler table.
+	///
+	/// Convenience wrapper around `try_se_handlers`, panics if the table is corrupt.
+	#[inline]
+	pub fn se_handlers(&self) -> &'a [Rva] {
+		self.try_se_handlers().unwrap()
+	}
+	/// Get the control flow guard valid call targets.
+	#[inline]
+	pub fn try_guard_cf_functions(&self) -> Result<GuardTable<'a>, PeError> {
+		self.guard_table(self.image_.GuardCFFunctionTable, self.image_.GuardCFFunctionCount)
+	}
+	/// Get the control flow guard valid call targets.
+	///
+	/// Convenience wrapper around `try_guard_cf_functions`, panics if the table is corrupt.
+	#[inline]
+	pub fn guard_cf_functions(&self) -> GuardTable<'a> {
+		self.try_guard_cf_functions().unwrap()
+	}
+	/// Get the control flow guard IAT entries whose address is taken.
+	#[inline]
+	pub fn try_guard_address_taken_iat_entries(&self) -> Result<GuardTable<'a>, PeError> {
+		self.guard_table(self.image_.GuardAddressTakenIatEntryTable, self.image_.GuardAddressTakenIatEntryCount)
+	}
+	/// Get the control flow guard IAT entries whose address is taken.
+	///
+	/// Convenience wrapper around `try_guard_address_taken_iat_entries`, panics if the table is corrupt.
+	#[inline]
+	pub fn guard_address_taken_iat_entries(&self) -> GuardTable<'a> {
+		self.try_guard_address_taken_iat_entries().unwrap()
+	}
+	/// Get the control flow guard valid longjmp targets.
+	#[inline]
+	pub fn try_guard_long_jump_targets(&self) -> Result<GuardTable<'a>, PeError> {
+		self.guard_table(self.image_.GuardLongJumpTargetTable, self.image_.GuardLongJumpTargetCount)
+	}
+	/// Get the control flow guard valid longjmp targets.
+	///
+	/// Convenience wrapper around `try_guard_long_jump_targets`, panics if the table is corrupt.
+	#[inline]
+	pub fn guard_long_jump_targets(&self) -> GuardTable<'a> {
+		self.try_guard_long_jump_targets().unwrap()
+	}
+	/// Get the valid exception handling continuation targets.
+	#[inline]
+	pub fn try_guard_eh_continuations(&self) -> Result<GuardTable<'a>, PeError> {
+		self.guard_table(self.image_.GuardEHContinuationTable, self.image_.GuardEHContinuationCount)
+	}
+	/// Get the valid exception handling continuation targets.
+	///
+	/// Convenience wrapper around `try_guard_eh_continuations`, panics if the table is corrupt.
+	#[inline]
+	pub fn guard_eh_continuations(&self) -> GuardTable<'a> {
+		self.try_guard_eh_continuations().unwrap()
+	}
+	fn guard_table(&self, table: Va, count: Va) -> Result<GuardTable<'a>, PeError> {
+		// Every Rva in the guard tables is followed by this many bytes of flags
+		let stride = 4 + ((self.image_.GuardFlags & IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK) >> IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT) as usize;
+		let bytes = if table == BADVA { &[][..] } else {
+			let rva = try!(self.view_.try_va_to_rva(table));
+			let len = try!((count as usize).checked_mul(stride).ok_or(PeError::Bounds));
+			try!(self.view_.try_read_slice::<u8>(rva, len))
+		};
+		Ok(GuardTable {
+			bytes: bytes,
+			stride: stride,
+		})
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for LoadConfig<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let image = &self.image_;
+		let (size, cookie, flags, cf_table, cf_count) = (image.Size, image.SecurityCookie, image.GuardFlags, image.GuardCFFunctionTable, image.GuardCFFunctionCount);
+		try!(writeln!(f, "LoadConfig"));
+		try!(writeln!(f, "  Size:           {}", size));
+		try!(writeln!(f, "  SecurityCookie: {:>08X}", cookie));
+		// The tables may point outside the image, print the error rather than panicking
+		match self.try_se_handlers() {
+			Ok(se_handlers) => try!(writeln!(f, "  SEHandlers:     {}", se_handlers.len())),
+			Err(err) => try!(writeln!(f, "  SEHandlers:     {}", err)),
+		}
+		try!(writeln!(f, "  GuardFlags:     {:>08X}", flags));
+		try!(writeln!(f, "  GuardCF:        {:>08X} count {}", cf_table, cf_count));
+		match self.try_guard_cf_functions() {
+			Ok(guard_cf_functions) => for it in guard_cf_functions {
+				try!(writeln!(f, "    {:>08X} {:02X}", it.rva, it.flags));
+			},
+			Err(err) => try!(writeln!(f, "    {}", err)),
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+pub trait PeLoadConfig<'a>: Pe<'a> + Sized {
+	/// Get the load config directory.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if the image has no load config directory.
+	///
+	/// `PeError::Corrupt` if its `Size` is too small to be valid.
+	fn try_load_config<'b>(&'b self) -> Result<LoadConfig<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG).ok_or(PeError::Null));
+		// Read the size first, it tells how many bytes of the directory are present
+		let size_bytes = try!(self.try_read_slice::<u8>(datadir.VirtualAddress, mem::size_of::<u32>()));
+		let size = size_bytes[0] as u32 | (size_bytes[1] as u32) << 8 | (size_bytes[2] as u32) << 16 | (size_bytes[3] as u32) << 24;
+		if size < mem::size_of::<u32>() as u32 {
+			return Err(PeError::Corrupt);
+		}
+		let bytes = try!(self.try_read_slice::<u8>(datadir.VirtualAddress, size as usize));
+		// Copy the known fields, anything not present stays zero
+		let image = unsafe {
+			let mut image: ImageLoadConfigDirectory = mem::zeroed();
+			let len = cmp::min(bytes.len(), mem::size_of::<ImageLoadConfigDirectory>());
+			ptr::copy_nonoverlapping(bytes.as_ptr(), &mut image as *mut _ as *mut u8, len);
+			image
+		};
+		Ok(LoadConfig {
+			view_: self,
+			bytes_: bytes,
+			image_: image,
+		})
+	}
+	/// Get the load config directory.
+	///
+	/// Convenience wrapper around `try_load_config`.
+	fn load_config<'b>(&'b self) -> Option<LoadConfig<'a, 'b, Self>> {
+		unwrap_null(self.try_load_config())
+	}
+}
+
+impl<'a, P: Pe<'a>> PeLoadConfig<'a> for P {}
+
+//----------------------------------------------------------------
+
+/// Control flow guard table entry.
+#[derive(Copy, Clone, Debug)]
+pub struct GuardEntry {
+	pub rva: Rva,
+	/// See the `IMAGE_GUARD_FLAG_*` constants, zero if the table has no flags.
+	pub flags: u8,
+}
+
+/// Control flow guard table.
+///
+/// Every entry is an Rva followed by a number of bytes of flags given by the guard flags.
+#[derive(Copy, Clone)]
+pub struct GuardTable<'a> {
+	bytes: &'a [u8],
+	stride: usize,
+}
+
+impl<'a> GuardTable<'a> {
+	/// Get the number of entries.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.bytes.len() / self.stride
+	}
+	/// Get whether the table is empty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+	/// Iterate over the entries.
+	#[inline]
+	pub fn iter(&self) -> GuardTableIterator<'a> {
+		GuardTableIterator {
+			it: self.bytes.chunks(self.stride),
+		}
+	}
+}
+
+impl<'a> IntoIterator for GuardTable<'a> {
+	type Item = GuardEntry;
+	type IntoIter = GuardTableIterator<'a>;
+
+	fn into_iter(self) -> GuardTableIterator<'a> {
+		self.iter()
+	}
+}
+
+pub struct GuardTableIterator<'a> {
+	it: slice::Chunks<'a, u8>,
+}
+
+impl<'a> Iterator for GuardTableIterator<'a> {
+	type Item = GuardEntry;
+
+	fn next(&mut self) -> Option<GuardEntry> {
+		self.it.next().map(|entry| GuardEntry {
+			rva: entry[0] as u32 | (entry[1] as u32) << 8 | (entry[2] as u32) << 16 | (entry[3] as u32) << 24,
+			flags: entry.get(4).cloned().unwrap_or(0),
+		})
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.it.size_hint()
+	}
+}
@@ -0,0 +1,404 @@
+//! Pe trait definition.
+//!
+//! The `Pe` trait is the core abstraction over the different ways a PE image can be laid out in memory.
+//! An implementation only has to provide the underlying bytes and the address translation,
+//! everything else (headers, reading structures and the directory parsers) is built on top of that.
+
+use std::{mem, slice, str};
+
+use super::image::*;
+use error::{PeError, unwrap_null};
+use pod::Pod;
+
+/// Abstracts over the different ways a PE image can be laid out in memory.
+///
+/// Implemented by `super::peview::PeView` for mapped images and `super::pefileview::PeFileView` for images as they are stored on disk.
+/// Implement this trait for your own backends to get access to the header accessors and every directory parser.
+///
+/// # Safety
+///
+/// The header accessors do not validate the headers every time they're called for efficiency.
+/// Implementors must guarantee `image` starts with headers that pass `validate_headers`.
+pub unsafe trait Pe<'a> {
+	/// Get the underlying bytes.
+	///
+	/// Starts with the PE headers, how the rest is laid out depends on the implementation.
+	fn image(&self) -> &'a [u8];
+	/// Address translation.
+	///
+	/// # Parameters
+	///
+	/// * `rva`
+	///
+	///   Rva to translate.
+	///
+	/// # Return value
+	///
+	/// The bytes from `rva` up to the end of the contiguous region it is in.
+	///
+	/// `PeError::Null` if `rva` is `BADRVA`.
+	///
+	/// `PeError::Bounds` if `rva` doesn't point into the image.
+	fn rva_to_slice(&self, rva: Rva) -> Result<&'a [u8], PeError>;
+	/// Get the virtual base address.
+	///
+	/// Defaults to the image base in the optional header.
+	#[inline]
+	fn virtual_base(&self) -> Va {
+		self.optional_header().ImageBase
+	}
+	/// Get the dos header image.
+	#[inline]
+	fn dos_header(&self) -> &'a ImageDosHeader {
+		unsafe {
+			// Guaranteed by the implementor so this is safe
+			&*(self.image().as_ptr() as *const ImageDosHeader)
+		}
+	}
+	/// Get the NT headers image.
+	#[inline]
+	fn nt_headers(&self) -> &'a ImageNtHeaders {
+		let dos = self.dos_header();
+		// Guaranteed by the implementor so this is safe
+		unsafe { &*((dos as *const _ as *const u8).offset(dos.e_lfanew as isize) as *const ImageNtHeaders) }
+	}
+	/// Get the file header image.
+	#[inline]
+	fn file_header(&self) -> &'a ImageFileHeader {
+		&self.nt_headers().FileHeader
+	}
+	/// Get the optional header image.
+	#[inline]
+	fn optional_header(&self) -> &'a ImageOptionalHeader {
+		&self.nt_headers().OptionalHeader
+	}
+	/// Get the section image headers.
+	#[inline]
+	fn section_headers(&self) -> &'a [ImageSectionHeader] {
+		let nt = self.nt_headers();
+		// Guaranteed by the implementor so this is safe
+		unsafe {
+			let begin = (&nt.OptionalHeader as *const _ as *const u8).offset(nt.FileHeader.SizeOfOptionalHeader as isize) as *const ImageSectionHeader;
+			slice::from_raw_parts(begin, nt.FileHeader.NumberOfSections as usize)
+		}
+	}
+	/// Get the data directory.
+	#[inline]
+	fn data_directory(&self) -> &'a [ImageDataDirectory] {
+		let opt = self.optional_header();
+		// Guaranteed by the implementor so this is safe
+		unsafe { slice::from_raw_parts(opt.DataDirectory.as_ptr(), opt.NumberOfRvaAndSizes as usize) }
+	}
+	/// Interpret as struct.
+	///
+	/// # Parameters
+	///
+	/// * `T`
+	///
+	///   Type of the struct to cast as.
+	///   Must be plain old data, see `pelite::Pod`.
+	///
+	/// * `rva`
+	///
+	///   Rva pointing to the instance to interpret as `T`.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if `rva` is `BADRVA`.
+	///
+	/// `PeError::Bounds` if `rva` is out of range.
+	///
+	/// `PeError::Misaligned` if `rva` has the wrong alignment for `T`.
+	///
+	/// No data is copied, a pointer to the underlying bytes is casted to a `&T`.
+	fn try_read_struct<T: Pod>(&self, rva: Rva) -> Result<&'a T, PeError> {
+		let bytes = try!(read_bytes(self, rva, mem::size_of::<T>(), mem::align_of::<T>()));
+		// This is now safe
+		Ok(unsafe { &*(bytes.as_ptr() as *const T) })
+	}
+	/// Interpret as struct.
+	///
+	/// Convenience wrapper around `try_read_struct`.
+	///
+	/// # Return value
+	///
+	/// If `rva` is `BADRVA` the result is `None`.
+	///
+	/// # Panics
+	///
+	/// If `rva` is out of range or has the wrong alignment.
+	///
+	/// This typically means data somewhere was corrupted resulting in an invalid `rva`.
+	/// Corruption may trigger a panic but it is not guaranteed if the result happens to look correct.
+	/// At no point will it read out of bounds memory.
+	#[inline]
+	fn read_struct<T: Pod>(&self, rva: Rva) -> Option<&'a T> {
+		unwrap_null(self.try_read_struct(rva))
+	}
+	/// Interpret as slice.
+	///
+	/// # Parameters
+	///
+	/// * `T`
+	///
+	///   Type of the slice.
+	///   Must be plain old data, see `pelite::Pod`.
+	///
+	/// * `rva`
+	///
+	///   Rva pointing to an array of `T` to be interpreted as a slice.
+	///
+	/// * `len`
+	///
+	///   Number of elements in the array pointed at by `rva`.
+	///
+	/// # Return value
+	///
+	/// If `len` is zero the result is an empty slice regardless of `rva`.
+	///
+	/// `PeError::Null` if `rva` is `BADRVA`.
+	///
+	/// `PeError::Bounds` if `rva` or `len` is out of range.
+	///
+	/// `PeError::Misaligned` if `rva` has the wrong alignment for `T`.
+	///
+	/// No data is copied, a pointer to the underlying bytes is casted to a `&[T]` with length `len`.
+	fn try_read_slice<T: Pod>(&self, rva: Rva, len: usize) -> Result<&'a [T], PeError> {
+		if len == 0 {
+			return Ok(&[]);
+		}
+		let size = try!(mem::size_of::<T>().checked_mul(len).ok_or(PeError::Bounds));
+		let bytes = try!(read_bytes(self, rva, size, mem::align_of::<T>()));
+		// This is now safe
+		Ok(unsafe { slice::from_raw_parts(bytes.as_ptr() as *const T, len) })
+	}
+	/// Interpret as slice.
+	///
+	/// Convenience wrapper around `try_read_slice`.
+	///
+	/// # Return value
+	///
+	/// If `rva` is `BADRVA` the result is `None`.
+	///
+	/// # Panics
+	///
+	/// If `rva` is out of range or has the wrong alignment.
+	///
+	/// This typically means data somewhere was corrupted resulting in an invalid `rva`.
+	/// Corruption may trigger a panic but it is not guaranteed if the result happens to look correct.
+	/// At no point will it read out of bounds memory.
+	#[inline]
+	fn read_slice<T: Pod>(&self, rva: Rva, len: usize) -> Option<&'a [T]> {
+		if rva == BADRVA { None }
+		else { unwrap_null(self.try_read_slice(rva, len)) }
+	}
+	/// Interpret as str.
+	///
+	/// # Parameters
+	///
+	/// * `rva`
+	///
+	///   Rva pointing to a valid UTF8, null terminated C string.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if `rva` is `BADRVA`.
+	///
+	/// `PeError::Bounds` if `rva` is out of range or the string is not terminated before the end of its region.
+	///
+	/// `PeError::Utf8` if the string is not valid UTF8.
+	///
+	/// No data is copied, a pointer to the underlying bytes is casted to a `&str`.
+	fn try_read_str(&self, rva: Rva) -> Result<&'a str, PeError> {
+		let tail = try!(self.rva_to_slice(rva));
+		// Scan for the null byte
+		let len = try!(tail.iter().position(|&byte| byte == 0u8).ok_or(PeError::Bounds));
+		// Convert to str
+		Ok(try!(str::from_utf8(&tail[..len])))
+	}
+	/// Interpret as str.
+	///
+	/// Convenience wrapper around `try_read_str`.
+	///
+	/// # Return value
+	///
+	/// If `rva` is `BADRVA` the result is `None`.
+	///
+	/// # Panics
+	///
+	/// If `rva` is out of range or points to invalid UTF8.
+	///
+	/// This typically means data somewhere was corrupted resulting in an invalid `rva`.
+	/// Corruption may trigger a panic but it is not guaranteed if the result happens to look correct.
+	/// At no point will it read out of bounds memory.
+	#[inline]
+	fn read_str(&self, rva: Rva) -> Option<&'a str> {
+		unwrap_null(self.try_read_str(rva))
+	}
+	/// Convert an Rva to FileOffset.
+	///
+	/// # Parameters
+	///
+	/// * `rva`
+	///
+	///   Rva to convert.
+	///
+	/// # Return value
+	///
+	/// `None` for invalid `rva`. Else the FileOffset to this `rva`.
+	fn rva_to_file_offset(&self, rva: Rva) -> Option<FileOffset> {
+		for it in self.section_headers() {
+			if rva >= it.VirtualAddress && rva - it.VirtualAddress < it.SizeOfRawData {
+				return Some((rva - it.VirtualAddress) as FileOffset + it.PointerToRawData as FileOffset);
+			}
+		}
+		None
+	}
+	/// Convert a FileOffset to Rva.
+	///
+	/// # Parameters
+	///
+	/// * `file_offset`
+	///
+	///   FileOffset to convert.
+	///
+	/// # Return value
+	///
+	/// `BADRVA` for invalid `file_offset`. Else the Rva to this `file_offset`.
+	fn file_offset_to_rva(&self, file_offset: FileOffset) -> Rva {
+		for it in self.section_headers() {
+			if file_offset >= it.PointerToRawData as FileOffset && file_offset < (it.PointerToRawData as FileOffset + it.SizeOfRawData as FileOffset) {
+				return file_offset as Rva - it.PointerToRawData + it.VirtualAddress;
+			}
+		}
+		BADRVA
+	}
+	/// Convert an Rva to Va.
+	///
+	/// # Parameters
+	///
+	/// * `rva`
+	///
+	///   Rva to convert.
+	///
+	/// # Return value
+	///
+	/// `BADVA` if `rva` is `BADRVA`.
+	///
+	/// # Remarks
+	///
+	/// The `rva` parameter isn't sanity checked to make sure it points within this image.
+	#[inline]
+	fn rva_to_va(&self, rva: Rva) -> Va {
+		if rva != BADRVA { self.virtual_base() + rva as Va }
+		else { BADVA }
+	}
+	/// Convert a Va to Rva.
+	///
+	/// # Parameters
+	///
+	/// * `va`
+	///
+	///   Va to convert.
+	///
+	/// # Return value
+	///
+	/// `BADRVA` if `va` is `BADVA`.
+	///
+	/// # Remarks
+	///
+	/// The `va` parameter isn't sanity checked to make sure it points within this image.
+	///
+	/// **FIXME!** This is especially problematic in PE64 images where Va is 64 bit and the resulting value doesn't fit in a 32 bit Rva...
+	#[inline]
+	fn va_to_rva(&self, va: Va) -> Rva {
+		if va != BADVA {
+			// FIXME! Overflow or underflow are very unsafe here!
+			(va - self.virtual_base()) as Rva
+		}
+		else {
+			BADRVA
+		}
+	}
+	/// Convert a Va to Rva.
+	///
+	/// # Parameters
+	///
+	/// * `va`
+	///
+	///   Va to convert.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if `va` is `BADVA`.
+	///
+	/// `PeError::Bounds` if `va` does not point within this image.
+	fn try_va_to_rva(&self, va: Va) -> Result<Rva, PeError> {
+		if va == BADVA {
+			return Err(PeError::Null);
+		}
+		let base = self.virtual_base();
+		if va < base || va - base >= self.optional_header().SizeOfImage as Va {
+			return Err(PeError::Bounds);
+		}
+		Ok((va - base) as Rva)
+	}
+}
+
+/// Reads `size` bytes at `rva` with the given alignment.
+fn read_bytes<'a, P: Pe<'a> + ?Sized>(pe: &P, rva: Rva, size: usize, align: usize) -> Result<&'a [u8], PeError> {
+	let bytes = try!(try!(pe.rva_to_slice(rva)).get(..size).ok_or(PeError::Bounds));
+	// Alignment is checked against the actual address as the image itself may not be aligned
+	if bytes.as_ptr() as usize % align != 0 {
+		return Err(PeError::Misaligned);
+	}
+	Ok(bytes)
+}
+
+/// Validates the headers so the header accessors of `Pe` can't read out of bounds.
+///
+/// # Parameters
+///
+/// * `image`
+///
+///   Bytes starting with the PE headers.
+///
+/// # Return value
+///
+/// `PeError::BadMagic` error if any of the PE magic values do not match.
+///
+/// `PeError::Bounds` error if the headers do not fit in `image`.
+///
+/// `PeError::Insanity` error if any sanity check failed.
+pub fn validate_headers(image: &[u8]) -> Result<(), PeError> {
+	//---------------- DOS header
+	if image.len() < mem::size_of::<ImageDosHeader>() {
+		return Err(PeError::Bounds);
+	}
+	// This is safe since the length was checked
+	let dos = unsafe { &*(image.as_ptr() as *const ImageDosHeader) };
+	if dos.e_magic != IMAGE_DOS_HEADER_MAGIC {
+		return Err(PeError::BadMagic);
+	}
+	//---------------- NT headers
+	let e_lfanew = dos.e_lfanew as usize;
+	if e_lfanew < mem::size_of::<ImageDosHeader>() || e_lfanew + mem::size_of::<ImageNtHeaders>() > image.len() {
+		return Err(PeError::Bounds);
+	}
+	// This is safe since the bounds were checked
+	let nt = unsafe { &*(image.as_ptr().offset(e_lfanew as isize) as *const ImageNtHeaders) };
+	if nt.Signature != IMAGE_NT_HEADERS_SIGNATURE || nt.OptionalHeader.Magic != IMAGE_NT_OPTIONAL_HDR_MAGIC {
+		return Err(PeError::BadMagic);
+	}
+	if nt.OptionalHeader.NumberOfRvaAndSizes > IMAGE_NUMBEROF_DIRECTORY_ENTRIES as u32 ||
+		nt.FileHeader.SizeOfOptionalHeader < mem::size_of::<ImageOptionalHeader>() as u16 {
+		return Err(PeError::Insanity);
+	}
+	//---------------- Section headers
+	let sec_begin = e_lfanew + (mem::size_of::<ImageNtHeaders>() - mem::size_of::<ImageOptionalHeader>()) + nt.FileHeader.SizeOfOptionalHeader as usize;
+	let sec_end = sec_begin + nt.FileHeader.NumberOfSections as usize * mem::size_of::<ImageSectionHeader>();
+	if sec_end > image.len() {
+		return Err(PeError::Bounds);
+	}
+	Ok(())
+}
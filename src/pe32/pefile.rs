@@ -3,34 +3,29 @@
 use std::path::Path;
 use std::fs::File;
 use std::{io, mem, slice};
+use std::ops::Range;
 use std::io::{Seek, Read};
 
 use super::peview::PeView;
 use super::image::*;
+use super::pe::Pe;
+use super::security::PeSecurity;
+use super::relocs::PeRelocs;
+use super::overlay::overlay_range;
+use security::Security;
+use checksum::compute_checksum_from_reader;
 
 //----------------------------------------------------------------
 
-#[derive(Debug)]
-pub enum PeError {
-	/// There was an error reading the file.
-	Io(io::Error),
-	/// Magic values didn't match.
-	BadMagic,
-	/// Sanity checks failed.
-	Insanity,
-}
-
-impl From<io::Error> for PeError {
-	fn from(err: io::Error) -> PeError {
-		PeError::Io(err)
-	}
-}
+pub use error::PeError;
 
 //----------------------------------------------------------------
 
 /// Owned version of `super::peview::PeView`.
 pub struct PeFile {
 	buf: Vec<u8>,
+	security: Vec<u8>,
+	file_size: FileOffset,
 }
 
 impl PeFile {
@@ -53,9 +48,41 @@ impl PeFile {
 	/// Otherwise the result is the correctly mapped binary.
 	pub fn open(path: &Path) -> Result<PeFile, PeError> {
 		let mut file = try!(File::open(path));
+		Self::from_reader(&mut file)
+	}
+	/// Read a PE file from a byte buffer.
+	///
+	/// # Parameters
+	///
+	/// * `bytes`
+	///
+	///   Contents of the file as it is stored on disk.
+	///
+	/// # Return value
+	///
+	/// `PeError::Io` error if the buffer is too small for the headers or sections it describes.
+	///
+	/// Otherwise the same as `open`.
+	pub fn from_bytes(bytes: &[u8]) -> Result<PeFile, PeError> {
+		Self::from_reader(&mut io::Cursor::new(bytes))
+	}
+	/// Read a PE file from any seekable reader.
+	///
+	/// # Parameters
+	///
+	/// * `file`
+	///
+	///   Reader for the contents of the file as it is stored on disk.
+	///   The file is expected to start at the beginning of the stream, it is rewound before reading.
+	///
+	/// # Return value
+	///
+	/// Same as `open`.
+	pub fn from_reader<R: Read + Seek>(file: &mut R) -> Result<PeFile, PeError> {
 		let mut buf = Vec::<u8>::with_capacity(0x1000);
 
 		//---------------- Read DOS header
+		try!(file.seek(io::SeekFrom::Start(0)));
 		let dos_bytes = mem::size_of::<ImageDosHeader>();
 		buf.resize(dos_bytes, 0);
 		try!(file.read_exact(&mut buf[..]));
@@ -90,6 +117,7 @@ impl PeFile {
 
 			// These sanity checks are arbitrary as based on experience
 			if nt.OptionalHeader.SizeOfHeaders > 0x1000 ||
+				nt.OptionalHeader.SizeOfHeaders > nt.OptionalHeader.SizeOfImage ||
 				nt.OptionalHeader.NumberOfRvaAndSizes > IMAGE_NUMBEROF_DIRECTORY_ENTRIES as u32 ||
 				nt.FileHeader.SizeOfOptionalHeader < mem::size_of::<ImageOptionalHeader>() as u16 ||
 				nt.FileHeader.NumberOfSections > 100 {
@@ -117,14 +145,33 @@ impl PeFile {
 		let sections = unsafe { slice::from_raw_parts(buf.as_ptr().offset(sec_begin as isize) as *const ImageSectionHeader, sec_num) };
 
 		//---------------- Map sections
-		try!(Self::map_sections(&mut file, &mut buf[..], hdr_bytes, sections));
+		try!(Self::map_sections(file, &mut buf[..], hdr_bytes, sections));
+
+		//---------------- Read the certificate table
+		// It is not part of any section, keep it around separately
+		let (security_offset, security_size) = {
+			let view = unsafe { PeView::module(buf.as_ptr()) };
+			view.data_directory().get(IMAGE_DIRECTORY_ENTRY_SECURITY).map(|datadir| (datadir.VirtualAddress, datadir.Size)).unwrap_or((0, 0))
+		};
+		let mut security = Vec::new();
+		if security_offset != 0 {
+			try!(file.seek(io::SeekFrom::Start(security_offset as u64)));
+			// May be cut short if the file is truncated, this is reported when accessing the certificate table
+			try!(file.by_ref().take(security_size as u64).read_to_end(&mut security));
+		}
+
+		//---------------- Locate the overlay
+		// Also not part of any section, appended data is often large so it is only read on request
+		let file_size = try!(file.seek(io::SeekFrom::End(0))) as usize;
 
 		//---------------- Done at last
 		Ok(PeFile {
 			buf: buf,
+			security: security,
+			file_size: file_size,
 		})
 	}
-	fn map_sections(file: &mut File, buf: &mut [u8], min_rva: Rva, sections: &[ImageSectionHeader]) -> Result<(), PeError> {
+	fn map_sections<R: Read + Seek>(file: &mut R, buf: &mut [u8], min_rva: Rva, sections: &[ImageSectionHeader]) -> Result<(), PeError> {
 		for it in sections {
 			// Safety: `sections` is a slice of `buf` meaning we technically violate RwLock.
 			//         This is safe however since `sections` is guaranteed to have an offset smaller than `min_rva`.
@@ -135,11 +182,15 @@ impl PeFile {
 			if it.PointerToRawData != 0 {
 				// Seek to the raw data pointer
 				try!(file.seek(io::SeekFrom::Start(it.PointerToRawData as u64)));
-				// FIXME! Validate these here so the next code can't panic!
 				let begin = it.VirtualAddress as usize;
 				let end = it.VirtualAddress as usize + it.SizeOfRawData as usize;
+				// Validate these here so the next code can't panic!
+				let dest = match buf.get_mut(begin..end) {
+					Some(dest) => dest,
+					None => return Err(PeError::Insanity),
+				};
 				// Read to the virtual address
-				try!(file.read_exact(&mut buf[begin..end]));
+				try!(file.read_exact(dest));
 			}
 		}
 		Ok(())
@@ -150,4 +201,155 @@ impl PeFile {
 		// With all the extensive error and sanity checking earlier, this better be safe...
 		unsafe { PeView::module(self.buf.as_ptr()) }
 	}
+	/// Write to the mapped image.
+	///
+	/// # Parameters
+	///
+	/// * `rva`
+	///
+	///   Where to write in the mapped image.
+	///
+	/// * `bytes`
+	///
+	///   The bytes to write.
+	///
+	/// # Return value
+	///
+	/// `PeError::Bounds` if the bytes do not fit in the image or overlap the headers.
+	///
+	/// The headers are read only, they were validated when reading the file and `view` relies on them.
+	pub fn try_write(&mut self, rva: Rva, bytes: &[u8]) -> Result<(), PeError> {
+		let size_of_headers = self.view().optional_header().SizeOfHeaders as usize;
+		let begin = rva as usize;
+		if begin < size_of_headers {
+			return Err(PeError::Bounds);
+		}
+		let dest = try!(self.buf.get_mut(begin..begin + bytes.len()).ok_or(PeError::Bounds));
+		dest.copy_from_slice(bytes);
+		Ok(())
+	}
+	/// Get the file offset range of the overlay.
+	///
+	/// # Return value
+	///
+	/// Same as `super::overlay::PeOverlay::try_overlay`.
+	///
+	/// # Remarks
+	///
+	/// The overlay is not kept in memory, use `read_overlay` to get its contents.
+	pub fn overlay_range(&self) -> Result<Range<FileOffset>, PeError> {
+		overlay_range(&self.view(), self.file_size)
+	}
+	/// Read the overlay.
+	///
+	/// # Parameters
+	///
+	/// * `file`
+	///
+	///   Reader for the file this was read from.
+	///
+	/// # Return value
+	///
+	/// `PeError::Io` if reading the file fails, eg. it was truncated since.
+	///
+	/// Otherwise the same as `overlay_range`, the contents of the overlay.
+	pub fn read_overlay<R: Read + Seek>(&self, file: &mut R) -> Result<Vec<u8>, PeError> {
+		let range = try!(self.overlay_range());
+		let mut overlay = vec![0u8; range.end - range.start];
+		try!(file.seek(io::SeekFrom::Start(range.start as u64)));
+		try!(file.read_exact(&mut overlay));
+		Ok(overlay)
+	}
+	/// Check whether the checksum stored in the optional header matches the file.
+	///
+	/// # Parameters
+	///
+	/// * `file`
+	///
+	///   Reader for the file this was read from, the checksum covers the whole file and not just the mapped sections.
+	///
+	/// # Return value
+	///
+	/// `PeError::Io` if reading the file fails.
+	///
+	/// Otherwise whether the stored checksum matches the one computed by `checksum::compute_checksum_from_reader`.
+	///
+	/// # Remarks
+	///
+	/// A stored checksum of zero means none was set by the linker, this is only acceptable for user mode images.
+	pub fn verify_checksum<R: Read + Seek>(&self, file: &mut R) -> Result<bool, PeError> {
+		let checksum = try!(compute_checksum_from_reader(file));
+		Ok(self.view().optional_header().CheckSum == checksum)
+	}
+	/// Rebase the image by applying its base relocations.
+	///
+	/// # Parameters
+	///
+	/// * `new_base`
+	///
+	///   The new image base, eg. the address of the module in a memory dump.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if the image has no relocations and the new image base differs.
+	///
+	/// `PeError::Bounds` if a relocation targets the headers or outside the image.
+	///
+	/// `PeError::Corrupt` if the relocations are corrupt or a relocation type other than
+	/// `IMAGE_REL_BASED_ABSOLUTE`, `HIGH`, `LOW`, `HIGHLOW`, `HIGHADJ` or `DIR64` is used.
+	///
+	/// All relocations are validated before any is applied, on error the image is left unmodified.
+	///
+	/// Otherwise the relocations are applied and the `ImageBase` in the optional header is updated.
+	pub fn rebase(&mut self, new_base: Va) -> Result<(), PeError> {
+		let old_base = self.view().optional_header().ImageBase;
+		if new_base == old_base {
+			return Ok(());
+		}
+		// Calculated as 64 bit to be correct for every relocation size when truncated
+		let delta = (new_base as u64).wrapping_sub(old_base as u64);
+		let size_of_headers = self.view().optional_header().SizeOfHeaders as usize;
+		let e_lfanew = self.view().dos_header().e_lfanew as isize;
+
+		//---------------- Validate the relocations
+		let fixups = {
+			let view = self.view();
+			let relocs = try!(view.try_relocs());
+			let mut fixups = Vec::new();
+			let mut it = relocs.fixups();
+			while let Some(fixup) = try!(it.try_next()) {
+				// Like `try_write` the headers are read only
+				if (fixup.rva as usize) < size_of_headers || fixup.rva as usize + fixup.size() > self.buf.len() {
+					return Err(PeError::Bounds);
+				}
+				fixups.push(fixup);
+			}
+			fixups
+		};
+
+		//---------------- Apply the relocations
+		for fixup in fixups {
+			fixup.apply(&mut self.buf[fixup.rva as usize..], delta);
+		}
+
+		//---------------- Update the image base
+		// This is safe since the headers were validated when reading the file
+		let nt = unsafe { &mut *(self.buf.as_mut_ptr().offset(e_lfanew) as *mut ImageNtHeaders) };
+		nt.OptionalHeader.ImageBase = new_base;
+		Ok(())
+	}
+}
+
+impl PeSecurity for PeFile {
+	fn try_security(&self) -> Result<Security, PeError> {
+		let datadir = try!(self.view().data_directory().get(IMAGE_DIRECTORY_ENTRY_SECURITY).ok_or(PeError::Null));
+		if datadir.VirtualAddress == 0 {
+			return Err(PeError::Null);
+		}
+		// The file was too small to read the whole certificate table
+		if self.security.len() != datadir.Size as usize {
+			return Err(PeError::Bounds);
+		}
+		Ok(Security::new(&self.security))
+	}
 }
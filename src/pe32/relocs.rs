@@ -2,36 +2,47 @@
 //!
 //! For a quick overview how relocs work, see this excellent [stackoverflow answer](https://stackoverflow.com/a/22513813).
 
-use std::{mem, fmt};
+use std::{mem, fmt, slice};
 
 use super::image::*;
+use super::pe::Pe;
 use super::peview::PeView;
+use error::{PeError, unwrap_null};
 
 //----------------------------------------------------------------
 
 /// Relocations directory.
-pub struct RelocsDirectory<'a: 'b, 'b> {
-	view_: &'b PeView<'a>,
+pub struct RelocsDirectory<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
 	datadir_: &'a ImageDataDirectory,
 }
 
-impl<'a, 'b> RelocsDirectory<'a, 'b> {
-	/// Get the associated `PeView`.
+impl<'a, 'b, P: Pe<'a>> RelocsDirectory<'a, 'b, P> {
+	/// Get the associated `Pe` image.
 	#[inline]
-	pub fn view(&self) -> &PeView {
+	pub fn view(&self) -> &'b P {
 		self.view_
 	}
 	/// Iterate over the relocations.
 	#[inline]
-	pub fn iter(&self) -> RelocsIterator {
+	pub fn iter<'c>(&'c self) -> RelocsIterator<'a, 'c, P> {
 		RelocsIterator {
 			relocs: self,
 			it: self.datadir_.VirtualAddress,
 		}
 	}
+	/// Iterate over the relocations as fixups to apply.
+	#[inline]
+	pub fn fixups<'c>(&'c self) -> FixupIterator<'a, 'c, P> {
+		FixupIterator {
+			relocs: self.iter(),
+			current: None,
+			blocks: [].iter(),
+		}
+	}
 }
 
-impl<'a, 'b> fmt::Display for RelocsDirectory<'a, 'b> {
+impl<'a, 'b, P: Pe<'a>> fmt::Display for RelocsDirectory<'a, 'b, P> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		for it in self.iter() {
 			try!(write!(f, "{}", it));
@@ -42,76 +53,88 @@ impl<'a, 'b> fmt::Display for RelocsDirectory<'a, 'b> {
 
 //----------------------------------------------------------------
 
-pub trait PeRelocs {
-	fn relocs(&self) -> Option<RelocsDirectory>;
-}
-
-impl<'a> PeRelocs for PeView<'a> {
-	fn relocs(&self) -> Option<RelocsDirectory> {
-		if let Some(datadir) = self.data_directory().get(IMAGE_DIRECTORY_ENTRY_BASERELOC) {
-			if datadir.VirtualAddress != BADRVA {
-				Some(RelocsDirectory {
-					view_: self,
-					datadir_: datadir,
-				})
-			}
-			else {
-				None
-			}
-		}
-		else {
-			None
+pub trait PeRelocs<'a>: Pe<'a> + Sized {
+	/// Get the relocations directory.
+	///
+	/// `PeError::Null` if the image has no relocations.
+	fn try_relocs<'b>(&'b self) -> Result<RelocsDirectory<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_BASERELOC).ok_or(PeError::Null));
+		if datadir.VirtualAddress == BADRVA {
+			return Err(PeError::Null);
 		}
+		Ok(RelocsDirectory {
+			view_: self,
+			datadir_: datadir,
+		})
+	}
+	/// Get the relocations directory.
+	///
+	/// Convenience wrapper around `try_relocs`.
+	fn relocs<'b>(&'b self) -> Option<RelocsDirectory<'a, 'b, Self>> {
+		unwrap_null(self.try_relocs())
 	}
 }
 
+impl<'a, P: Pe<'a>> PeRelocs<'a> for P {}
+
 //----------------------------------------------------------------
 
-pub struct RelocsIterator<'a: 'b, 'b> {
-	relocs: &'b RelocsDirectory<'a, 'b>,
+pub struct RelocsIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	relocs: &'b RelocsDirectory<'a, 'b, P>,
 	it: Rva,
 }
 
-impl<'a, 'b> Iterator for RelocsIterator<'a, 'b> {
-	type Item = BaseRelocations<'a, 'b>;
+impl<'a, 'b, P: Pe<'a>> Iterator for RelocsIterator<'a, 'b, P> {
+	type Item = BaseRelocations<'a, 'b, P>;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let end = self.relocs.datadir_.VirtualAddress + self.relocs.datadir_.Size;
-		if self.it >= end {
-			None
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> RelocsIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the relocations are corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<BaseRelocations<'a, 'b, P>>, PeError> {
+		let end = self.relocs.datadir_.VirtualAddress as u64 + self.relocs.datadir_.Size as u64;
+		if self.it as u64 >= end {
+			Ok(None)
 		}
 		else {
 			// Get the base relocation
-			let rel = self.relocs.view_.read_struct::<ImageBaseRelocation>(self.it).unwrap();
+			let rel = try!(self.relocs.view_.try_read_struct::<ImageBaseRelocation>(self.it));
 			// Sanity check, without this underflow later can be very unsafe
-			assert!(rel.SizeOfBlock as usize > mem::size_of::<ImageBaseRelocation>());
+			if rel.SizeOfBlock as usize <= mem::size_of::<ImageBaseRelocation>() || self.it as u64 + rel.SizeOfBlock as u64 > end {
+				return Err(PeError::Corrupt);
+			}
 			// Get the number of base reloc blocks
 			let block_len = (rel.SizeOfBlock as usize - mem::size_of::<ImageBaseRelocation>()) / mem::size_of::<ImageBaseRelocBlock>();
 			// Get the blocks as a slice
-			let blocks = self.relocs.view_.read_slice::<ImageBaseRelocBlock>(self.it + mem::size_of::<ImageBaseRelocation>() as Rva, block_len).unwrap();
+			let blocks = try!(self.relocs.view_.try_read_slice::<ImageBaseRelocBlock>(self.it + mem::size_of::<ImageBaseRelocation>() as Rva, block_len));
 			// Advance iterator
 			self.it += rel.SizeOfBlock;
-			Some(BaseRelocations {
+			Ok(Some(BaseRelocations {
 				view_: self.relocs.view_,
 				reloc_: rel,
 				blocks_: blocks,
-			})
+			}))
 		}
 	}
 }
 
 //----------------------------------------------------------------
 
-pub struct BaseRelocations<'a: 'b, 'b> {
-	view_: &'b PeView<'a>,
+pub struct BaseRelocations<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
 	reloc_: &'a ImageBaseRelocation,
 	blocks_: &'a [ImageBaseRelocBlock],
 }
 
-impl<'a, 'b> BaseRelocations<'a, 'b> {
-	/// Get the associated `PeView`.
+impl<'a, 'b, P: Pe<'a>> BaseRelocations<'a, 'b, P> {
+	/// Get the associated `Pe` image.
 	#[inline]
-	pub fn view(&self) -> &PeView {
+	pub fn view(&self) -> &'b P {
 		self.view_
 	}
 	/// Get the base relocation image.
@@ -125,10 +148,19 @@ impl<'a, 'b> BaseRelocations<'a, 'b> {
 		self.blocks_
 	}
 	/// Get the final Rva of a reloc block.
+	///
+	/// `PeError::Bounds` if the Rva overflows.
 	#[inline]
-	pub fn rva_of(&self, block: &ImageBaseRelocBlock) -> Rva {
+	pub fn try_rva_of(&self, block: &ImageBaseRelocBlock) -> Result<Rva, PeError> {
 		let offset = (block.TypeAndOffset & 0x0FFF) as Rva;
-		self.reloc_.VirtualAddress + offset
+		self.reloc_.VirtualAddress.checked_add(offset).ok_or(PeError::Bounds)
+	}
+	/// Get the final Rva of a reloc block.
+	///
+	/// Convenience wrapper around `try_rva_of`, panics if the Rva overflows.
+	#[inline]
+	pub fn rva_of(&self, block: &ImageBaseRelocBlock) -> Rva {
+		self.try_rva_of(block).unwrap()
 	}
 	/// Get the type of a reloc block.
 	#[inline]
@@ -137,7 +169,7 @@ impl<'a, 'b> BaseRelocations<'a, 'b> {
 	}
 }
 
-impl<'a, 'b> fmt::Display for BaseRelocations<'a, 'b> {
+impl<'a, 'b, P: Pe<'a>> fmt::Display for BaseRelocations<'a, 'b, P> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		try!(writeln!(f, "BaseRelocations"));
 		try!(writeln!(f, "  VirtualAddress: {:>08X}", self.reloc_.VirtualAddress));
@@ -148,3 +180,137 @@ impl<'a, 'b> fmt::Display for BaseRelocations<'a, 'b> {
 		Ok(())
 	}
 }
+
+//----------------------------------------------------------------
+
+/// Relocation to apply.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Fixup {
+	/// Relocation type, one of `IMAGE_REL_BASED_HIGH`, `LOW`, `HIGHLOW`, `HIGHADJ` or `DIR64`.
+	pub ty: u8,
+	/// Rva of the relocated bytes.
+	pub rva: Rva,
+	/// For `IMAGE_REL_BASED_HIGHADJ` the low half of the address, stored in the next reloc block.
+	pub param: u16,
+}
+
+impl Fixup {
+	/// Get the number of bytes modified.
+	#[inline]
+	pub fn size(&self) -> usize {
+		match self.ty {
+			IMAGE_REL_BASED_HIGHLOW => 4,
+			IMAGE_REL_BASED_DIR64 => 8,
+			_ => 2,
+		}
+	}
+	/// Apply the relocation.
+	///
+	/// # Parameters
+	///
+	/// * `bytes`
+	///
+	///   The relocated bytes, must be at least `size()` bytes long.
+	///
+	/// * `delta`
+	///
+	///   Difference between the new and the old image base, calculated as 64 bit so it can be truncated for every relocation size.
+	///
+	/// # Panics
+	///
+	/// If `bytes` is too short.
+	pub fn apply(&self, bytes: &mut [u8], delta: u64) {
+		match self.ty {
+			IMAGE_REL_BASED_HIGH => {
+				let word = read_u16(bytes, 0);
+				write_u16(bytes, 0, (((word as u32) << 16).wrapping_add(delta as u32) >> 16) as u16);
+			},
+			IMAGE_REL_BASED_LOW => {
+				let word = read_u16(bytes, 0);
+				write_u16(bytes, 0, word.wrapping_add(delta as u16));
+			},
+			IMAGE_REL_BASED_HIGHADJ => {
+				let word = read_u16(bytes, 0);
+				// Round to account for the sign extension of the low half
+				let adjusted = ((word as u32) << 16).wrapping_add(self.param as i16 as u32).wrapping_add(delta as u32).wrapping_add(0x8000);
+				write_u16(bytes, 0, (adjusted >> 16) as u16);
+			},
+			IMAGE_REL_BASED_HIGHLOW => {
+				let dword = read_u32(bytes, 0);
+				write_u32(bytes, 0, dword.wrapping_add(delta as u32));
+			},
+			_ => {
+				let qword = (read_u32(bytes, 0) as u64 | (read_u32(bytes, 4) as u64) << 32).wrapping_add(delta);
+				write_u32(bytes, 0, qword as u32);
+				write_u32(bytes, 4, (qword >> 32) as u32);
+			},
+		}
+	}
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+	bytes[offset] as u16 | (bytes[offset + 1] as u16) << 8
+}
+fn write_u16(bytes: &mut [u8], offset: usize, value: u16) {
+	bytes[offset] = value as u8;
+	bytes[offset + 1] = (value >> 8) as u8;
+}
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+	read_u16(bytes, offset) as u32 | (read_u16(bytes, offset + 2) as u32) << 16
+}
+fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+	write_u16(bytes, offset, value as u16);
+	write_u16(bytes, offset + 2, (value >> 16) as u16);
+}
+
+pub struct FixupIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	relocs: RelocsIterator<'a, 'b, P>,
+	current: Option<BaseRelocations<'a, 'b, P>>,
+	blocks: slice::Iter<'a, ImageBaseRelocBlock>,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for FixupIterator<'a, 'b, P> {
+	type Item = Fixup;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> FixupIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the relocations are corrupt.
+	///
+	/// `PeError::Corrupt` for unsupported relocation types, `IMAGE_REL_BASED_ABSOLUTE` padding is skipped.
+	///
+	/// `PeError::Bounds` if the Rva of a relocation overflows.
+	pub fn try_next(&mut self) -> Result<Option<Fixup>, PeError> {
+		loop {
+			if let Some(ref current) = self.current {
+				if let Some(block) = self.blocks.next() {
+					let ty = current.type_of(block);
+					if ty == IMAGE_REL_BASED_ABSOLUTE {
+						continue;
+					}
+					let rva = try!(current.try_rva_of(block));
+					let param = match ty {
+						IMAGE_REL_BASED_HIGH | IMAGE_REL_BASED_LOW | IMAGE_REL_BASED_HIGHLOW | IMAGE_REL_BASED_DIR64 => 0,
+						IMAGE_REL_BASED_HIGHADJ => try!(self.blocks.next().ok_or(PeError::Corrupt)).TypeAndOffset,
+						_ => return Err(PeError::Corrupt),
+					};
+					return Ok(Some(Fixup {
+						ty: ty,
+						rva: rva,
+						param: param,
+					}));
+				}
+			}
+			match try!(self.relocs.try_next()) {
+				Some(base_relocs) => {
+					self.blocks = base_relocs.blocks().iter();
+					self.current = Some(base_relocs);
+				},
+				None => return Ok(None),
+			}
+		}
+	}
+}
@@ -0,0 +1,148 @@
+//! Thread local storage directory.
+//!
+//! The TLS callbacks are called by the loader before the entry point whenever a thread attaches or detaches.
+
+use std::{fmt, mem};
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use error::{PeError, unwrap_null};
+
+//----------------------------------------------------------------
+
+/// TLS directory.
+pub struct Tls<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	image_: &'a ImageTlsDirectory,
+}
+
+impl<'a, 'b, P: Pe<'a>> Tls<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the underlying TLS directory image.
+	#[inline]
+	pub fn image(&self) -> &'a ImageTlsDirectory {
+		self.image_
+	}
+	/// Get the template used to initialize the TLS data of every new thread.
+	///
+	/// The template is followed by `SizeOfZeroFill` zero bytes which are not part of the result.
+	///
+	/// # Return value
+	///
+	/// An empty slice if there is no template.
+	///
+	/// `PeError::Corrupt` if the end address is before the start address.
+	///
+	/// `PeError::Bounds` if the template is out of range.
+	pub fn try_raw_data(&self) -> Result<&'a [u8], PeError> {
+		let (start, end) = (self.image_.StartAddressOfRawData, self.image_.EndAddressOfRawData);
+		if start == BADVA {
+			return Ok(&[]);
+		}
+		if end < start {
+			return Err(PeError::Corrupt);
+		}
+		let rva = try!(self.view_.try_va_to_rva(start));
+		self.view_.try_read_slice::<u8>(rva, (end - start) as usize)
+	}
+	/// Get the template used to initialize the TLS data of every new thread.
+	///
+	/// Convenience wrapper around `try_raw_data`, panics if the template is corrupt.
+	#[inline]
+	pub fn raw_data(&self) -> &'a [u8] {
+		self.try_raw_data().unwrap()
+	}
+	/// Iterate over the TLS callbacks.
+	///
+	/// The callbacks are yielded as Rvas converted from the Vas stored in the image.
+	#[inline]
+	pub fn callbacks<'c>(&'c self) -> TlsCallbackIterator<'a, 'c, P> {
+		TlsCallbackIterator {
+			tls: self,
+			it: self.image_.AddressOfCallBacks,
+		}
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for Tls<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let image = self.image_;
+		let (start, end, index, callbacks, zero_fill) = (image.StartAddressOfRawData, image.EndAddressOfRawData, image.AddressOfIndex, image.AddressOfCallBacks, image.SizeOfZeroFill);
+		try!(writeln!(f, "Tls"));
+		try!(writeln!(f, "  RawData:        {:>08X}..{:>08X}", start, end));
+		try!(writeln!(f, "  AddressOfIndex: {:>08X}", index));
+		try!(writeln!(f, "  SizeOfZeroFill: {}", zero_fill));
+		try!(writeln!(f, "  Callbacks:      {:>08X}", callbacks));
+		let mut it = self.callbacks();
+		while let Ok(Some(rva)) = it.try_next() {
+			try!(writeln!(f, "    {:>08X}", rva));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+pub trait PeTls<'a>: Pe<'a> + Sized {
+	/// Get the TLS directory.
+	///
+	/// `PeError::Null` if the image has no TLS directory.
+	fn try_tls<'b>(&'b self) -> Result<Tls<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_TLS).ok_or(PeError::Null));
+		let image = try!(self.try_read_struct::<ImageTlsDirectory>(datadir.VirtualAddress));
+		Ok(Tls {
+			view_: self,
+			image_: image,
+		})
+	}
+	/// Get the TLS directory.
+	///
+	/// Convenience wrapper around `try_tls`.
+	fn tls<'b>(&'b self) -> Option<Tls<'a, 'b, Self>> {
+		unwrap_null(self.try_tls())
+	}
+}
+
+impl<'a, P: Pe<'a>> PeTls<'a> for P {}
+
+//----------------------------------------------------------------
+
+pub struct TlsCallbackIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	tls: &'b Tls<'a, 'b, P>,
+	it: Va,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for TlsCallbackIterator<'a, 'b, P> {
+	type Item = Rva;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> TlsCallbackIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the callbacks are corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<Rva>, PeError> {
+		// Without callbacks the array pointer is null
+		if self.it == BADVA {
+			return Ok(None);
+		}
+		let view = self.tls.view_;
+		let va = *try!(view.try_read_struct::<Va>(try!(view.try_va_to_rva(self.it))));
+		if va == BADVA {
+			Ok(None)
+		}
+		else {
+			let rva = try!(view.try_va_to_rva(va));
+			self.it = try!(self.it.checked_add(mem::size_of::<Va>() as Va).ok_or(PeError::Bounds));
+			Ok(Some(rva))
+		}
+	}
+}
@@ -0,0 +1,427 @@
+//! Debug directory.
+//!
+//! The debug directory describes where debug information for the image can be found.
+//! Most importantly the CodeView entry identifies the matching PDB file.
+
+use std::{fmt, mem, slice, str};
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use error::{PeError, unwrap_null};
+use pod::Pod;
+
+//----------------------------------------------------------------
+
+/// Debug directory.
+pub struct DebugDirectory<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	image_: &'a [ImageDebugDirectory],
+}
+
+impl<'a, 'b, P: Pe<'a>> DebugDirectory<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the underlying debug directory image.
+	#[inline]
+	pub fn image(&self) -> &'a [ImageDebugDirectory] {
+		self.image_
+	}
+	/// Iterate over the debug directory entries.
+	#[inline]
+	pub fn iter<'c>(&'c self) -> DebugIterator<'a, 'c, P> {
+		DebugIterator {
+			view: self.view_,
+			it: self.image_.iter(),
+		}
+	}
+	/// Get the CodeView information identifying the PDB file.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if there is no CodeView entry in a supported format.
+	///
+	/// Otherwise the first supported CodeView entry or any error parsing it.
+	pub fn try_code_view(&self) -> Result<CodeView<'a>, PeError> {
+		for entry in self.iter() {
+			if entry.image().Type == IMAGE_DEBUG_TYPE_CODEVIEW {
+				if let Entry::CodeView(cv) = try!(entry.try_entry()) {
+					return Ok(cv);
+				}
+			}
+		}
+		Err(PeError::Null)
+	}
+	/// Get the CodeView information identifying the PDB file.
+	///
+	/// Convenience wrapper around `try_code_view`.
+	#[inline]
+	pub fn code_view(&self) -> Option<CodeView<'a>> {
+		unwrap_null(self.try_code_view())
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for DebugDirectory<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "Debug"));
+		for entry in self.iter() {
+			try!(write!(f, "{}", entry));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+pub trait PeDebug<'a>: Pe<'a> + Sized {
+	/// Get the debug directory.
+	///
+	/// `PeError::Null` if the image has no debug directory.
+	fn try_debug<'b>(&'b self) -> Result<DebugDirectory<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_DEBUG).ok_or(PeError::Null));
+		if datadir.VirtualAddress == BADRVA {
+			return Err(PeError::Null);
+		}
+		let len = datadir.Size as usize / mem::size_of::<ImageDebugDirectory>();
+		let image = try!(self.try_read_slice::<ImageDebugDirectory>(datadir.VirtualAddress, len));
+		Ok(DebugDirectory {
+			view_: self,
+			image_: image,
+		})
+	}
+	/// Get the debug directory.
+	///
+	/// Convenience wrapper around `try_debug`.
+	fn debug<'b>(&'b self) -> Option<DebugDirectory<'a, 'b, Self>> {
+		unwrap_null(self.try_debug())
+	}
+}
+
+impl<'a, P: Pe<'a>> PeDebug<'a> for P {}
+
+//----------------------------------------------------------------
+
+pub struct DebugIterator<'a, 'b, P: 'b = PeView<'a>> {
+	view: &'b P,
+	it: slice::Iter<'a, ImageDebugDirectory>,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for DebugIterator<'a, 'b, P> {
+	type Item = DebugEntry<'a, 'b, P>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let view = self.view;
+		self.it.next().map(|image| DebugEntry {
+			view_: view,
+			image_: image,
+		})
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.it.size_hint()
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Debug directory entry.
+pub struct DebugEntry<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	image_: &'a ImageDebugDirectory,
+}
+
+impl<'a, 'b, P: Pe<'a>> DebugEntry<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the underlying debug directory entry image.
+	#[inline]
+	pub fn image(&self) -> &'a ImageDebugDirectory {
+		self.image_
+	}
+	/// Get the raw debug data.
+	///
+	/// # Return value
+	///
+	/// An empty slice if the entry has no data.
+	///
+	/// `PeError::Null` if the data is not mapped, it is then only available in the file at `PointerToRawData`.
+	///
+	/// `PeError::Bounds` if the data is out of range.
+	pub fn try_data(&self) -> Result<&'a [u8], PeError> {
+		if self.image_.SizeOfData == 0 {
+			return Ok(&[]);
+		}
+		self.view_.try_read_slice::<u8>(self.image_.AddressOfRawData, self.image_.SizeOfData as usize)
+	}
+	/// Get the raw debug data.
+	///
+	/// Convenience wrapper around `try_data`.
+	#[inline]
+	pub fn data(&self) -> Option<&'a [u8]> {
+		unwrap_null(self.try_data())
+	}
+	/// Interpret the debug data based on its type.
+	///
+	/// # Return value
+	///
+	/// Any error from `try_data`.
+	///
+	/// `PeError::Bounds` if the data is too small for its type.
+	///
+	/// Otherwise the parsed entry, `Entry::Unknown` with the raw data for unsupported types and CodeView or POGO signatures.
+	pub fn try_entry(&self) -> Result<Entry<'a>, PeError> {
+		let data = try!(self.try_data());
+		match self.image_.Type {
+			IMAGE_DEBUG_TYPE_CODEVIEW => CodeView::parse(data),
+			IMAGE_DEBUG_TYPE_POGO => Pogo::parse(data),
+			IMAGE_DEBUG_TYPE_VC_FEATURE => read_pod::<ImageDebugVcFeature>(data).map(Entry::VcFeature),
+			IMAGE_DEBUG_TYPE_REPRO => {
+				// Deterministic builds without a hash have no data
+				if data.is_empty() {
+					Ok(Entry::Repro(data))
+				}
+				else {
+					let len = try!(read_u32(data, 0)) as usize;
+					data[4..].get(..len).map(Entry::Repro).ok_or(PeError::Bounds)
+				}
+			},
+			IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS => read_u32(data, 0).map(Entry::ExDllCharacteristics),
+			_ => Ok(Entry::Unknown(data)),
+		}
+	}
+	/// Interpret the debug data based on its type.
+	///
+	/// Convenience wrapper around `try_entry`.
+	#[inline]
+	pub fn entry(&self) -> Option<Entry<'a>> {
+		unwrap_null(self.try_entry())
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for DebugEntry<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (ty, size, rva) = (self.image_.Type, self.image_.SizeOfData, self.image_.AddressOfRawData);
+		try!(writeln!(f, "  Type {:>2} at {:>08X} size {}", ty, rva, size));
+		// Corrupt debug data is not worth failing the whole directory over
+		match self.try_entry().ok() {
+			Some(Entry::CodeView(cv)) => writeln!(f, "    {}", cv),
+			Some(Entry::Pogo(pogo)) => {
+				let mut it = pogo.iter();
+				while let Ok(Some(section)) = it.try_next() {
+					try!(writeln!(f, "    {:>08X} {:>8} {}", section.rva, section.size, section.name));
+				}
+				Ok(())
+			},
+			Some(Entry::VcFeature(vc)) => {
+				let (pre, cpp, gs, sdl, guard) = (vc.PreVcPlusPlusCount, vc.CPlusPlusCount, vc.GuardStackCount, vc.SdlCount, vc.GuardCount);
+				writeln!(f, "    Pre-VC++ {} C/C++ {} /GS {} /sdl {} guardN {}", pre, cpp, gs, sdl, guard)
+			},
+			Some(Entry::Repro(hash)) => {
+				try!(write!(f, "    Repro "));
+				for byte in hash {
+					try!(write!(f, "{:02X}", byte));
+				}
+				writeln!(f, "")
+			},
+			Some(Entry::ExDllCharacteristics(flags)) => writeln!(f, "    ExDllCharacteristics {:>08X}", flags),
+			Some(Entry::Unknown(_)) | None => Ok(()),
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Typed debug data.
+#[derive(Copy, Clone)]
+pub enum Entry<'a> {
+	/// `IMAGE_DEBUG_TYPE_CODEVIEW`, identifies the PDB file.
+	CodeView(CodeView<'a>),
+	/// `IMAGE_DEBUG_TYPE_POGO`, lists the sections of the image as seen by the linker.
+	Pogo(Pogo<'a>),
+	/// `IMAGE_DEBUG_TYPE_VC_FEATURE`, counts of objects compiled with various security features.
+	VcFeature(&'a ImageDebugVcFeature),
+	/// `IMAGE_DEBUG_TYPE_REPRO`, the image was built deterministically.
+	///
+	/// Contains the hash used in place of the timestamps, empty if there is none.
+	Repro(&'a [u8]),
+	/// `IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS`, see the `IMAGE_DLLCHARACTERISTICS_EX_*` constants.
+	ExDllCharacteristics(u32),
+	/// Any other type or a format which is not supported, eg. CodeView `NB09` or POGO `PGI\0`, the raw debug data.
+	Unknown(&'a [u8]),
+}
+
+//----------------------------------------------------------------
+
+/// CodeView debug information.
+#[derive(Copy, Clone)]
+pub enum CodeView<'a> {
+	/// PDB 7.0 format with the `RSDS` signature.
+	Cv70 { image: &'a ImageDebugCvInfoPdb70, pdb_file_name: &'a str },
+	/// PDB 2.0 format with the `NB10` signature.
+	Cv20 { image: &'a ImageDebugCvInfoPdb20, pdb_file_name: &'a str },
+}
+
+impl<'a> CodeView<'a> {
+	// Older formats such as `NB09` and `NB11` embed the debug information itself, they're left as `Entry::Unknown`.
+	fn parse(data: &'a [u8]) -> Result<Entry<'a>, PeError> {
+		let cv = match try!(read_u32(data, 0)) {
+			CV_SIGNATURE_RSDS => {
+				let image = try!(read_pod::<ImageDebugCvInfoPdb70>(data));
+				let pdb_file_name = try!(read_cstr(&data[mem::size_of::<ImageDebugCvInfoPdb70>()..]));
+				CodeView::Cv70 { image: image, pdb_file_name: pdb_file_name }
+			},
+			CV_SIGNATURE_NB10 => {
+				let image = try!(read_pod::<ImageDebugCvInfoPdb20>(data));
+				let pdb_file_name = try!(read_cstr(&data[mem::size_of::<ImageDebugCvInfoPdb20>()..]));
+				CodeView::Cv20 { image: image, pdb_file_name: pdb_file_name }
+			},
+			_ => return Ok(Entry::Unknown(data)),
+		};
+		Ok(Entry::CodeView(cv))
+	}
+	/// Get the path to the PDB file as recorded by the linker.
+	#[inline]
+	pub fn pdb_file_name(&self) -> &'a str {
+		match *self {
+			CodeView::Cv70 { pdb_file_name, .. } => pdb_file_name,
+			CodeView::Cv20 { pdb_file_name, .. } => pdb_file_name,
+		}
+	}
+	/// Get the age, incremented every time the PDB file is updated.
+	#[inline]
+	pub fn age(&self) -> u32 {
+		match *self {
+			CodeView::Cv70 { image, .. } => image.Age,
+			CodeView::Cv20 { image, .. } => image.Age,
+		}
+	}
+	/// Get the key used to look up the PDB file on a symbol server.
+	///
+	/// For PDB 7.0 this is the GUID followed by the age, for PDB 2.0 the signature followed by the age, all in uppercase hex.
+	/// The PDB file can be found on the symbol server at `<pdb name>/<key>/<pdb name>` where the pdb name is the file name part of `pdb_file_name`.
+	pub fn symbol_server_key(&self) -> String {
+		match *self {
+			CodeView::Cv70 { image, .. } => {
+				let (data1, data2, data3, data4, age) = (image.Signature.Data1, image.Signature.Data2, image.Signature.Data3, image.Signature.Data4, image.Age);
+				let mut key = format!("{:08X}{:04X}{:04X}", data1, data2, data3);
+				for byte in &data4 {
+					key.push_str(&format!("{:02X}", byte));
+				}
+				key.push_str(&format!("{:X}", age));
+				key
+			},
+			CodeView::Cv20 { image, .. } => {
+				let (signature, age) = (image.Signature, image.Age);
+				format!("{:08X}{:X}", signature, age)
+			},
+		}
+	}
+}
+
+impl<'a> fmt::Display for CodeView<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} {}", self.symbol_server_key(), self.pdb_file_name())
+	}
+}
+
+//----------------------------------------------------------------
+
+/// POGO debug information.
+#[derive(Copy, Clone)]
+pub struct Pogo<'a> {
+	signature: u32,
+	data: &'a [u8],
+}
+
+impl<'a> Pogo<'a> {
+	// Other signatures such as `PGI\0` and `PGO\0` are left as `Entry::Unknown`.
+	fn parse(data: &'a [u8]) -> Result<Entry<'a>, PeError> {
+		let signature = try!(read_u32(data, 0));
+		if signature != POGO_SIGNATURE_LTCG && signature != POGO_SIGNATURE_PGU {
+			return Ok(Entry::Unknown(data));
+		}
+		Ok(Entry::Pogo(Pogo {
+			signature: signature,
+			data: &data[4..],
+		}))
+	}
+	/// Get the signature, `POGO_SIGNATURE_LTCG` or `POGO_SIGNATURE_PGU`.
+	#[inline]
+	pub fn signature(&self) -> u32 {
+		self.signature
+	}
+	/// Iterate over the sections.
+	#[inline]
+	pub fn iter(&self) -> PogoIterator<'a> {
+		PogoIterator {
+			data: self.data,
+		}
+	}
+}
+
+/// Section described by the POGO debug information.
+#[derive(Copy, Clone, Debug)]
+pub struct PogoSection<'a> {
+	pub rva: Rva,
+	pub size: u32,
+	pub name: &'a str,
+}
+
+pub struct PogoIterator<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> Iterator for PogoIterator<'a> {
+	type Item = PogoSection<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a> PogoIterator<'a> {
+	/// Advances the iterator, returning an error instead of panicking if the POGO data is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<PogoSection<'a>>, PeError> {
+		if self.data.is_empty() {
+			return Ok(None);
+		}
+		let entry = try!(read_pod::<ImageDebugPogoEntry>(self.data));
+		let name = try!(read_cstr(&self.data[mem::size_of::<ImageDebugPogoEntry>()..]));
+		// The name is padded to a multiple of 4 bytes including its terminator
+		let next = mem::size_of::<ImageDebugPogoEntry>() + ((name.len() + 4) & !3);
+		self.data = self.data.get(next..).unwrap_or(&[]);
+		Ok(Some(PogoSection {
+			rva: entry.Rva,
+			size: entry.Size,
+			name: name,
+		}))
+	}
+}
+
+//----------------------------------------------------------------
+
+fn read_pod<'a, T: Pod>(data: &'a [u8]) -> Result<&'a T, PeError> {
+	if data.len() < mem::size_of::<T>() {
+		return Err(PeError::Bounds);
+	}
+	if data.as_ptr() as usize % mem::align_of::<T>() != 0 {
+		return Err(PeError::Misaligned);
+	}
+	// This is now safe
+	Ok(unsafe { &*(data.as_ptr() as *const T) })
+}
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, PeError> {
+	let bytes = try!(data.get(offset..offset + 4).ok_or(PeError::Bounds));
+	Ok(bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24)
+}
+fn read_cstr(data: &[u8]) -> Result<&str, PeError> {
+	let len = try!(data.iter().position(|&byte| byte == 0u8).ok_or(PeError::Bounds));
+	Ok(try!(str::from_utf8(&data[..len])))
+}
@@ -0,0 +1,433 @@
+//! Exception directory.
+//!
+//! The x64 exception directory is a table of every non-leaf function with its unwind information, sorted by address.
+//! For an overview of the format see [x64 exception handling](https://docs.microsoft.com/en-us/cpp/build/exception-handling-x64).
+
+use std::{fmt, mem, slice};
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use error::{PeError, unwrap_null};
+
+//----------------------------------------------------------------
+
+/// Exception directory.
+pub struct Exceptions<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	image_: &'a [ImageRuntimeFunctionEntry],
+}
+
+impl<'a, 'b, P: Pe<'a>> Exceptions<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the underlying function table image.
+	#[inline]
+	pub fn image(&self) -> &'a [ImageRuntimeFunctionEntry] {
+		self.image_
+	}
+	/// Iterate over the functions.
+	#[inline]
+	pub fn functions(&self) -> FunctionIterator<'a, 'b, P> {
+		FunctionIterator {
+			view: self.view_,
+			it: self.image_.iter(),
+		}
+	}
+	/// Find the function containing an Rva.
+	///
+	/// # Parameters
+	///
+	/// * `rva`
+	///
+	///   Rva somewhere inside the function.
+	///
+	/// # Return value
+	///
+	/// `None` if no function contains `rva`.
+	///
+	/// # Remarks
+	///
+	/// Uses a binary search, the function table must be sorted as the loader requires.
+	pub fn lookup_function_entry(&self, rva: Rva) -> Option<Function<'a, 'b, P>> {
+		let (mut low, mut high) = (0, self.image_.len());
+		while low < high {
+			let mid = low + (high - low) / 2;
+			let image = &self.image_[mid];
+			if rva < image.BeginAddress {
+				high = mid;
+			}
+			else if rva >= image.EndAddress {
+				low = mid + 1;
+			}
+			else {
+				return Some(Function {
+					view_: self.view_,
+					image_: image,
+				});
+			}
+		}
+		None
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for Exceptions<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "Exceptions"));
+		for function in self.functions() {
+			try!(write!(f, "{}", function));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+pub trait PeExceptions<'a>: Pe<'a> + Sized {
+	/// Get the exception directory.
+	///
+	/// `PeError::Null` if the image has no exception directory.
+	fn try_exceptions<'b>(&'b self) -> Result<Exceptions<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_EXCEPTION).ok_or(PeError::Null));
+		if datadir.VirtualAddress == BADRVA {
+			return Err(PeError::Null);
+		}
+		let len = datadir.Size as usize / mem::size_of::<ImageRuntimeFunctionEntry>();
+		let image = try!(self.try_read_slice::<ImageRuntimeFunctionEntry>(datadir.VirtualAddress, len));
+		Ok(Exceptions {
+			view_: self,
+			image_: image,
+		})
+	}
+	/// Get the exception directory.
+	///
+	/// Convenience wrapper around `try_exceptions`.
+	fn exceptions<'b>(&'b self) -> Option<Exceptions<'a, 'b, Self>> {
+		unwrap_null(self.try_exceptions())
+	}
+}
+
+impl<'a, P: Pe<'a>> PeExceptions<'a> for P {}
+
+//----------------------------------------------------------------
+
+pub struct FunctionIterator<'a, 'b, P: 'b = PeView<'a>> {
+	view: &'b P,
+	it: slice::Iter<'a, ImageRuntimeFunctionEntry>,
+}
+
+impl<'a, 'b, P: Pe<'a>> Iterator for FunctionIterator<'a, 'b, P> {
+	type Item = Function<'a, 'b, P>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let view = self.view;
+		self.it.next().map(|image| Function {
+			view_: view,
+			image_: image,
+		})
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.it.size_hint()
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Function table entry.
+pub struct Function<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	image_: &'a ImageRuntimeFunctionEntry,
+}
+
+impl<'a, 'b, P: Pe<'a>> Function<'a, 'b, P> {
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the underlying runtime function entry image.
+	#[inline]
+	pub fn image(&self) -> &'a ImageRuntimeFunctionEntry {
+		self.image_
+	}
+	/// Get the unwind information.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if the entry has no unwind information, incrementally linked images pad the table with empty entries.
+	///
+	/// `PeError::Bounds` if the unwind information is out of range.
+	///
+	/// `PeError::Corrupt` if the unwind information refers to another entry which refers to yet another entry.
+	pub fn try_unwind_info(&self) -> Result<UnwindInfo<'a, 'b, P>, PeError> {
+		let mut rva = self.image_.UnwindInfoAddress;
+		// The low bit indicates this shares the unwind information of another entry
+		if rva & 1 != 0 {
+			rva = try!(self.view_.try_read_struct::<ImageRuntimeFunctionEntry>(rva & !1)).UnwindInfoAddress;
+			if rva & 1 != 0 {
+				return Err(PeError::Corrupt);
+			}
+		}
+		UnwindInfo::new(self.view_, rva)
+	}
+	/// Get the unwind information.
+	///
+	/// Convenience wrapper around `try_unwind_info`.
+	#[inline]
+	pub fn unwind_info(&self) -> Option<UnwindInfo<'a, 'b, P>> {
+		unwrap_null(self.try_unwind_info())
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for Function<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (begin, end, unwind) = (self.image_.BeginAddress, self.image_.EndAddress, self.image_.UnwindInfoAddress);
+		try!(writeln!(f, "  Function {:>08X}..{:>08X} unwind {:>08X}", begin, end, unwind));
+		match self.try_unwind_info() {
+			Ok(unwind_info) => write!(f, "{}", unwind_info),
+			Err(_) => Ok(()),
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Unwind information.
+pub struct UnwindInfo<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	image_: &'a ImageUnwindInfo,
+	codes_: &'a [ImageUnwindCode],
+	// Rva of the chained function entry or the exception handler following the unwind codes
+	trailer_: Rva,
+}
+
+impl<'a, 'b, P: Pe<'a>> UnwindInfo<'a, 'b, P> {
+	fn new(view: &'b P, rva: Rva) -> Result<UnwindInfo<'a, 'b, P>, PeError> {
+		let image = try!(view.try_read_struct::<ImageUnwindInfo>(rva));
+		let codes_rva = rva + mem::size_of::<ImageUnwindInfo>() as Rva;
+		let codes = try!(view.try_read_slice::<ImageUnwindCode>(codes_rva, image.CountOfCodes as usize));
+		// The unwind codes are padded to an even count for alignment
+		let padded_len = (image.CountOfCodes as usize + 1) & !1;
+		Ok(UnwindInfo {
+			view_: view,
+			image_: image,
+			codes_: codes,
+			trailer_: codes_rva + (padded_len * mem::size_of::<ImageUnwindCode>()) as Rva,
+		})
+	}
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the underlying unwind information image.
+	#[inline]
+	pub fn image(&self) -> &'a ImageUnwindInfo {
+		self.image_
+	}
+	/// Get the version, either 1 or 2.
+	#[inline]
+	pub fn version(&self) -> u8 {
+		self.image_.VersionAndFlags & 0x7
+	}
+	/// Get the flags, see the `UNW_FLAG_*` constants.
+	#[inline]
+	pub fn flags(&self) -> u8 {
+		self.image_.VersionAndFlags >> 3
+	}
+	/// Get the size of the prolog in bytes.
+	#[inline]
+	pub fn size_of_prolog(&self) -> u8 {
+		self.image_.SizeOfProlog
+	}
+	/// Get the frame register, zero if the function does not use a frame pointer.
+	#[inline]
+	pub fn frame_register(&self) -> u8 {
+		self.image_.FrameRegisterAndOffset & 0xF
+	}
+	/// Get the offset from the stack pointer the frame register is set to.
+	#[inline]
+	pub fn frame_offset(&self) -> u32 {
+		(self.image_.FrameRegisterAndOffset >> 4) as u32 * 16
+	}
+	/// Get the raw unwind code slots.
+	#[inline]
+	pub fn codes(&self) -> &'a [ImageUnwindCode] {
+		self.codes_
+	}
+	/// Iterate over the decoded unwind codes.
+	#[inline]
+	pub fn unwind_codes(&self) -> UnwindCodeIterator<'a> {
+		UnwindCodeIterator {
+			version: self.version(),
+			codes: self.codes_,
+		}
+	}
+	/// Get the function entry whose unwind information is chained after this one.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if the unwind information is not chained.
+	///
+	/// `PeError::Bounds` if the chained entry is out of range.
+	pub fn try_chained(&self) -> Result<Function<'a, 'b, P>, PeError> {
+		if self.flags() & UNW_FLAG_CHAININFO == 0 {
+			return Err(PeError::Null);
+		}
+		let image = try!(self.view_.try_read_struct::<ImageRuntimeFunctionEntry>(self.trailer_));
+		Ok(Function {
+			view_: self.view_,
+			image_: image,
+		})
+	}
+	/// Get the function entry whose unwind information is chained after this one.
+	///
+	/// Convenience wrapper around `try_chained`.
+	#[inline]
+	pub fn chained(&self) -> Option<Function<'a, 'b, P>> {
+		unwrap_null(self.try_chained())
+	}
+	/// Get the Rva of the language specific exception handler.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if there is no exception handler.
+	///
+	/// `PeError::Bounds` if the handler is out of range.
+	pub fn try_handler(&self) -> Result<Rva, PeError> {
+		let flags = self.flags();
+		if flags & UNW_FLAG_CHAININFO != 0 || flags & (UNW_FLAG_EHANDLER | UNW_FLAG_UHANDLER) == 0 {
+			return Err(PeError::Null);
+		}
+		let bytes = try!(self.view_.try_read_slice::<u8>(self.trailer_, mem::size_of::<Rva>()));
+		Ok(bytes[0] as Rva | (bytes[1] as Rva) << 8 | (bytes[2] as Rva) << 16 | (bytes[3] as Rva) << 24)
+	}
+	/// Get the Rva of the language specific exception handler.
+	///
+	/// Convenience wrapper around `try_handler`.
+	#[inline]
+	pub fn handler(&self) -> Option<Rva> {
+		unwrap_null(self.try_handler())
+	}
+	/// Get the language specific handler data.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if there is no exception handler.
+	///
+	/// The format and size depend on the handler, the result extends to the end of the region containing it.
+	pub fn try_handler_data(&self) -> Result<&'a [u8], PeError> {
+		try!(self.try_handler());
+		self.view_.rva_to_slice(self.trailer_ + mem::size_of::<Rva>() as Rva)
+	}
+	/// Get the language specific handler data.
+	///
+	/// Convenience wrapper around `try_handler_data`.
+	#[inline]
+	pub fn handler_data(&self) -> Option<&'a [u8]> {
+		unwrap_null(self.try_handler_data())
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> fmt::Display for UnwindInfo<'a, 'b, P> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "    Version {} Flags {:X} Prolog {} Frame {}+{}", self.version(), self.flags(), self.size_of_prolog(), self.frame_register(), self.frame_offset()));
+		// Stop at the first corrupt unwind code, the remaining slots can't be decoded
+		let mut it = self.unwind_codes();
+		while let Ok(Some(code)) = it.try_next() {
+			try!(writeln!(f, "    {:?}", code));
+		}
+		if let Ok(chained) = self.try_chained() {
+			let (begin, end) = (chained.image().BeginAddress, chained.image().EndAddress);
+			try!(writeln!(f, "    Chained {:>08X}..{:>08X}", begin, end));
+		}
+		if let Ok(handler) = self.try_handler() {
+			try!(writeln!(f, "    Handler {:>08X}", handler));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Decoded unwind code.
+///
+/// Every variant starts with the offset in the prolog of the instruction after the one it describes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum UnwindCode {
+	/// Push a nonvolatile register.
+	PushNonVol { offset: u8, reg: u8 },
+	/// Allocate a large area on the stack.
+	AllocLarge { offset: u8, size: u32 },
+	/// Allocate a small area on the stack.
+	AllocSmall { offset: u8, size: u32 },
+	/// Establish the frame pointer register.
+	SetFPReg { offset: u8 },
+	/// Save a nonvolatile register on the stack with a mov.
+	SaveNonVol { offset: u8, reg: u8, stack_offset: u32 },
+	/// Save all 128 bits of a nonvolatile XMM register on the stack.
+	SaveXmm128 { offset: u8, reg: u8, stack_offset: u32 },
+	/// Push a machine frame, used for interrupts and exceptions.
+	PushMachFrame { offset: u8, error_code: bool },
+	/// Describes an epilog, version 2 only.
+	Epilog { offset: u8, info: u8 },
+	/// Unknown or reserved operation.
+	Unknown { offset: u8, op: u8, info: u8 },
+}
+
+pub struct UnwindCodeIterator<'a> {
+	version: u8,
+	codes: &'a [ImageUnwindCode],
+}
+
+impl<'a> Iterator for UnwindCodeIterator<'a> {
+	type Item = UnwindCode;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a> UnwindCodeIterator<'a> {
+	/// Advances the iterator, returning an error instead of panicking if the unwind codes are corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<UnwindCode>, PeError> {
+		let codes = self.codes;
+		let code = match codes.first() {
+			Some(code) => code,
+			None => return Ok(None),
+		};
+		let offset = code.CodeOffset;
+		let op = code.UnwindOpAndInfo & 0xF;
+		let info = code.UnwindOpAndInfo >> 4;
+		// Some operations use the following slots for their operand
+		let slot = |index: usize| -> Result<u32, PeError> {
+			let code = try!(codes.get(index).ok_or(PeError::Corrupt));
+			Ok(code.CodeOffset as u32 | (code.UnwindOpAndInfo as u32) << 8)
+		};
+		let (code, slots) = match op {
+			UWOP_PUSH_NONVOL => (UnwindCode::PushNonVol { offset: offset, reg: info }, 1),
+			UWOP_ALLOC_LARGE if info == 0 => (UnwindCode::AllocLarge { offset: offset, size: try!(slot(1)) * 8 }, 2),
+			UWOP_ALLOC_LARGE if info == 1 => (UnwindCode::AllocLarge { offset: offset, size: try!(slot(1)) | try!(slot(2)) << 16 }, 3),
+			UWOP_ALLOC_LARGE => return Err(PeError::Corrupt),
+			UWOP_ALLOC_SMALL => (UnwindCode::AllocSmall { offset: offset, size: info as u32 * 8 + 8 }, 1),
+			UWOP_SET_FPREG => (UnwindCode::SetFPReg { offset: offset }, 1),
+			UWOP_SAVE_NONVOL => (UnwindCode::SaveNonVol { offset: offset, reg: info, stack_offset: try!(slot(1)) * 8 }, 2),
+			UWOP_SAVE_NONVOL_FAR => (UnwindCode::SaveNonVol { offset: offset, reg: info, stack_offset: try!(slot(1)) | try!(slot(2)) << 16 }, 3),
+			UWOP_EPILOG if self.version >= 2 => (UnwindCode::Epilog { offset: offset, info: info }, 1),
+			// Formerly UWOP_SAVE_XMM and UWOP_SAVE_XMM_FAR
+			UWOP_EPILOG => (UnwindCode::Unknown { offset: offset, op: op, info: info }, 2),
+			UWOP_SPARE_CODE => (UnwindCode::Unknown { offset: offset, op: op, info: info }, 3),
+			UWOP_SAVE_XMM128 => (UnwindCode::SaveXmm128 { offset: offset, reg: info, stack_offset: try!(slot(1)) * 16 }, 2),
+			UWOP_SAVE_XMM128_FAR => (UnwindCode::SaveXmm128 { offset: offset, reg: info, stack_offset: try!(slot(1)) | try!(slot(2)) << 16 }, 3),
+			UWOP_PUSH_MACHFRAME => (UnwindCode::PushMachFrame { offset: offset, error_code: info != 0 }, 1),
+			_ => (UnwindCode::Unknown { offset: offset, op: op, info: info }, 1),
+		};
+		self.codes = try!(codes.get(slots..).ok_or(PeError::Corrupt));
+		Ok(Some(code))
+	}
+}
@@ -1,9 +1,11 @@
 //! PE exports.
 
-use std::{fmt};
+use std::{cmp, fmt};
 
 use super::image::*;
+use super::pe::Pe;
 use super::peview::PeView;
+use error::{PeError, unwrap_null};
 
 //----------------------------------------------------------------
 
@@ -20,6 +22,8 @@ pub enum Export<'a> {
 	///
 	/// Format of the string is `DllName.ExportName`.
 	/// For more information see: https://blogs.msdn.microsoft.com/oldnewthing/20060719-24/?p=30473
+	///
+	/// Use `super::resolver::Resolver` to follow forwarders to the actual symbol.
 	Forward(&'a str),
 }
 
@@ -82,16 +86,16 @@ impl<'a> fmt::Display for NamedExport<'a> {
 //----------------------------------------------------------------
 
 /// Exports directory.
-pub struct ExportDirectory<'a: 'b, 'b> {
-	view_: &'b PeView<'a>,
+pub struct ExportDirectory<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
 	datadir_: &'a ImageDataDirectory,
 	image_: &'a ImageExportDirectory,
 }
 
-impl<'a, 'b> ExportDirectory<'a, 'b> {
-	/// Get the associated `PeView`.
+impl<'a, 'b, P: Pe<'a>> ExportDirectory<'a, 'b, P> {
+	/// Get the associated `Pe` image.
 	#[inline]
-	pub fn view(&self) -> &'b PeView<'a> {
+	pub fn view(&self) -> &'b P {
 		self.view_
 	}
 	/// Get the underlying export directory image.
@@ -101,14 +105,31 @@ impl<'a, 'b> ExportDirectory<'a, 'b> {
 	}
 	/// Get the export directory's name for this library.
 	#[inline]
+	pub fn try_name(&self) -> Result<&'a str, PeError> {
+		self.view_.try_read_str(self.image_.Name)
+	}
+	/// Get the export directory's name for this library.
+	///
+	/// Convenience wrapper around `try_name`, panics if the name is corrupt.
+	#[inline]
 	pub fn name(&self) -> &'a str {
-		self.view_.read_str(self.image_.Name).unwrap()
+		self.try_name().unwrap()
+	}
+	/// Get the export address table.
+	#[inline]
+	pub fn try_functions(&self) -> Result<&'a [Rva], PeError> {
+		self.view_.try_read_slice(self.image_.AddressOfFunctions, self.image_.NumberOfFunctions as usize)
 	}
 	/// Get the export address table.  
 	#[inline]
 	pub fn functions(&self) -> Option<&'a [Rva]> {
 		self.view_.read_slice(self.image_.AddressOfFunctions, self.image_.NumberOfFunctions as usize)
 	}
+	/// Get the name address table.
+	#[inline]
+	pub fn try_names(&self) -> Result<&'a [Rva], PeError> {
+		self.view_.try_read_slice(self.image_.AddressOfNames, self.image_.NumberOfNames as usize)
+	}
 	/// Get the name address table.  
 	#[inline]
 	pub fn names(&self) -> Option<&'a [Rva]> {
@@ -118,6 +139,13 @@ impl<'a, 'b> ExportDirectory<'a, 'b> {
 	///
 	/// The value in this array is an index (not an ordinal!) into the export address table matching name in the same index as the name address table.
 	#[inline]
+	pub fn try_name_indices(&self) -> Result<&'a [u16], PeError> {
+		self.view_.try_read_slice(self.image_.AddressOfNameOrdinals, self.image_.NumberOfNames as usize)
+	}
+	/// Get the name ordinal index table.
+	///
+	/// The value in this array is an index (not an ordinal!) into the export address table matching name in the same index as the name address table.
+	#[inline]
 	pub fn name_indices(&self) -> Option<&'a [u16]> {
 		self.view_.read_slice(self.image_.AddressOfNameOrdinals, self.image_.NumberOfNames as usize)
 	}
@@ -134,7 +162,8 @@ impl<'a, 'b> ExportDirectory<'a, 'b> {
 	/// Returns if `rva` is a forwarded symbol.
 	#[inline]
 	pub fn is_forwarded(&self, rva: Rva) -> bool {
-		rva >= self.datadir_.VirtualAddress && rva < self.datadir_.VirtualAddress + self.datadir_.Size
+		// A corrupt data directory may extend past the end of the address space
+		rva >= self.datadir_.VirtualAddress && (rva as u64) < self.datadir_.VirtualAddress as u64 + self.datadir_.Size as u64
 	}
 	/// Find a symbol by its ordinal.
 	///
@@ -146,17 +175,25 @@ impl<'a, 'b> ExportDirectory<'a, 'b> {
 	///
 	/// # Return value
 	///
-	/// `Export` value.
-	pub fn symbol_by_ordinal(&self, ord: u16) -> Export<'a> {
-		if let Some(functions) = self.functions() {
-			let ord_idx = ord - self.image_.Base as u16;
-			if let Some(sym_rva) = functions.get(ord_idx as usize) {
-				if *sym_rva != BADRVA {
-					return self.symbol_from_rva(sym_rva);
-				}
+	/// `Export` value, `Export::None` if there's no symbol with this ordinal.
+	///
+	/// `PeError` if the export directory is corrupt.
+	pub fn try_symbol_by_ordinal(&self, ord: u16) -> Result<Export<'a>, PeError> {
+		let functions = try!(self.try_functions());
+		let ord_idx = (ord as u32).wrapping_sub(self.image_.Base);
+		if let Some(sym_rva) = functions.get(ord_idx as usize) {
+			if *sym_rva != BADRVA {
+				return self.try_symbol_from_rva(sym_rva);
 			}
 		}
-		Export::None
+		Ok(Export::None)
+	}
+	/// Find a symbol by its ordinal.
+	///
+	/// Convenience wrapper around `try_symbol_by_ordinal`, panics if the export directory is corrupt.
+	#[inline]
+	pub fn symbol_by_ordinal(&self, ord: u16) -> Export<'a> {
+		self.try_symbol_by_ordinal(ord).unwrap()
 	}
 	/// Find a symbol by its name.
 	///
@@ -168,25 +205,71 @@ impl<'a, 'b> ExportDirectory<'a, 'b> {
 	///
 	/// # Return value
 	///
-	/// `Export` value.
+	/// `Export` value, `Export::None` if there's no symbol with this name.
+	///
+	/// `PeError` if the export directory is corrupt.
+	///
+	/// # Remarks
+	///
+	/// The name table is sorted, it is binary searched the same way the loader does.
+	/// Symbols may not be found in an export directory whose names are not sorted.
+	pub fn try_symbol_by_name(&self, name: &str) -> Result<Export<'a>, PeError> {
+		let names = try!(self.try_names());
+		let (mut lo, mut hi) = (0, names.len());
+		while lo < hi {
+			let mid = lo + (hi - lo) / 2;
+			let name_it = try!(self.view_.try_read_str(names[mid]));
+			// Names are sorted by byte value, which is how `str` compares
+			match name_it.cmp(name) {
+				cmp::Ordering::Less => lo = mid + 1,
+				cmp::Ordering::Greater => hi = mid,
+				cmp::Ordering::Equal => return self.try_symbol_from_name_index(mid),
+			}
+		}
+		Ok(Export::None)
+	}
+	/// Find a symbol by its name.
+	///
+	/// Convenience wrapper around `try_symbol_by_name`, panics if the export directory is corrupt.
+	#[inline]
 	pub fn symbol_by_name(&self, name: &str) -> Export<'a> {
-		if let Some(functions) = self.functions() {
-		if let Some(names) = self.names() {
-		if let Some(name_indices) = self.names() {
-			for (&name_rva, &name_ord_idx) in names.iter().zip(name_indices.iter()) {
-				let name_it = self.view_.read_str(name_rva).unwrap();
-				if name_it == name {
-					if let Some(sym_rva) = functions.get(name_ord_idx as usize) {
-						if *sym_rva != BADRVA {
-							return self.symbol_from_rva(sym_rva);
-						}
-					}
-					// Export table is corrupt, shouldn't happen...
-					return Export::None;
-				}
+		self.try_symbol_by_name(name).unwrap()
+	}
+	/// Find a symbol by its name, starting with a hint.
+	///
+	/// # Parameters
+	///
+	/// * `hint`
+	///
+	///   Index into the name table where the symbol is expected, eg. the hint of `super::imports::ImportedSymbol::ByName`.
+	///
+	/// * `name`
+	///
+	///   Name of the symbol to find.
+	///
+	/// # Return value
+	///
+	/// Same as `try_symbol_by_name`.
+	///
+	/// # Remarks
+	///
+	/// The linker stores the hint when the import library matches the dll, so the name at the hint is checked first.
+	/// If it doesn't match, eg. the dll was updated since, the name is looked up with `try_symbol_by_name`.
+	pub fn try_symbol_by_hint(&self, hint: u16, name: &str) -> Result<Export<'a>, PeError> {
+		let names = try!(self.try_names());
+		if let Some(&name_rva) = names.get(hint as usize) {
+			if try!(self.view_.try_read_str(name_rva)) == name {
+				return self.try_symbol_from_name_index(hint as usize);
 			}
-		}}}
-		Export::None
+		}
+		self.try_symbol_by_name(name)
+	}
+	/// Find a symbol by its name, starting with a hint.
+	///
+	/// Convenience wrapper around `try_symbol_by_hint`, panics if the export directory is corrupt.
+	#[inline]
+	pub fn symbol_by_hint(&self, hint: u16, name: &str) -> Export<'a> {
+		self.try_symbol_by_hint(hint, name).unwrap()
 	}
 	/// Find the name for an export.
 	///
@@ -199,48 +282,63 @@ impl<'a, 'b> ExportDirectory<'a, 'b> {
 	/// # Return value
 	///
 	/// `NamedExport` value.
-	pub fn name_from_ordinal(&self, ord: u16) -> NamedExport<'a> {
-		if let Some(functions) = self.functions() {
-			let ord_idx = ord - self.image_.Base as u16;
-			if let Some(sym_rva) = functions.get(ord_idx as usize) {
-				if *sym_rva != BADRVA {
-					if let Some(name_indices) = self.name_indices() {
-					if let Some(names) = self.names() {
-						for (&name_rva, &name_ord_idx) in names.iter().zip(name_indices.iter()) {
-							if ord_idx == name_ord_idx {
-								return NamedExport {
-									ord: ord,
-									symbol: self.symbol_from_rva(sym_rva),
-									name: Some(self.view_.read_str(name_rva).unwrap()),
-								};
-							}
-						}
-					}}
-					return NamedExport {
-						ord: ord,
-						symbol: self.symbol_from_rva(sym_rva),
-						name: None,
-					};
+	///
+	/// `PeError` if the export directory is corrupt.
+	pub fn try_name_from_ordinal(&self, ord: u16) -> Result<NamedExport<'a>, PeError> {
+		let functions = try!(self.try_functions());
+		let ord_idx = (ord as u32).wrapping_sub(self.image_.Base);
+		if let Some(sym_rva) = functions.get(ord_idx as usize) {
+			if *sym_rva != BADRVA {
+				let names = try!(self.try_names());
+				let name_indices = try!(self.try_name_indices());
+				let mut name = None;
+				for (&name_rva, &name_ord_idx) in names.iter().zip(name_indices.iter()) {
+					if ord_idx == name_ord_idx as u32 {
+						name = Some(try!(self.view_.try_read_str(name_rva)));
+						break;
+					}
 				}
+				return Ok(NamedExport {
+					ord: ord,
+					symbol: try!(self.try_symbol_from_rva(sym_rva)),
+					name: name,
+				});
 			}
 		}
-		NamedExport {
+		Ok(NamedExport {
 			ord: ord,
 			symbol: Export::None,
 			name: None,
+		})
+	}
+	/// Find the name for an export.
+	///
+	/// Convenience wrapper around `try_name_from_ordinal`, panics if the export directory is corrupt.
+	#[inline]
+	pub fn name_from_ordinal(&self, ord: u16) -> NamedExport<'a> {
+		self.try_name_from_ordinal(ord).unwrap()
+	}
+	fn try_symbol_from_name_index(&self, index: usize) -> Result<Export<'a>, PeError> {
+		let functions = try!(self.try_functions());
+		let name_indices = try!(self.try_name_indices());
+		let name_ord_idx = try!(name_indices.get(index).ok_or(PeError::Corrupt));
+		match functions.get(*name_ord_idx as usize) {
+			Some(sym_rva) if *sym_rva != BADRVA => self.try_symbol_from_rva(sym_rva),
+			// Named symbol with no function, the export table is corrupt
+			_ => Err(PeError::Corrupt),
 		}
 	}
-	fn symbol_from_rva(&self, rva: &'a Rva) -> Export<'a> {
+	fn try_symbol_from_rva(&self, rva: &'a Rva) -> Result<Export<'a>, PeError> {
 		if self.is_forwarded(*rva) {
-			Export::Forward(self.view_.read_str(*rva).unwrap())
+			Ok(Export::Forward(try!(self.view_.try_read_str(*rva))))
 		}
 		else {
-			Export::Symbol(rva)
+			Ok(Export::Symbol(rva))
 		}
 	}
 	/// Iterate over the ordinals of the exports.
 	#[inline]
-	pub fn iter(&self) -> ExportIterator {
+	pub fn iter<'c>(&'c self) -> ExportIterator<'a, 'c, P> {
 		ExportIterator {
 			exp: self,
 			it: 0,
@@ -248,7 +346,7 @@ impl<'a, 'b> ExportDirectory<'a, 'b> {
 	}
 }
 
-impl<'a, 'b> fmt::Display for ExportDirectory<'a, 'b> {
+impl<'a, 'b, P: Pe<'a>> fmt::Display for ExportDirectory<'a, 'b, P> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		try!(writeln!(f, "Exports for {}", self.name()));
 		try!(writeln!(f, "  Characteristics: {:>08X}", self.image_.Characteristics));
@@ -258,7 +356,8 @@ impl<'a, 'b> fmt::Display for ExportDirectory<'a, 'b> {
 		try!(writeln!(f, "  # of Functions:  {}", self.image_.NumberOfFunctions));
 		try!(writeln!(f, "  # of Names:      {}", self.image_.NumberOfNames));
 
-		for ord in self.iter() {
+		let mut it = self.iter();
+		while let Ok(Some(ord)) = it.try_next() {
 			let name = self.name_from_ordinal(ord);
 			match name.symbol {
 				Export::None => (),
@@ -273,49 +372,60 @@ impl<'a, 'b> fmt::Display for ExportDirectory<'a, 'b> {
 
 //----------------------------------------------------------------
 
-pub trait PeExports {
-	fn exports(&self) -> Option<ExportDirectory>;
-}
-
-impl<'a> PeExports for PeView<'a> {
-	fn exports(&self) -> Option<ExportDirectory> {
-		if let Some(datadir) = self.data_directory().get(IMAGE_DIRECTORY_ENTRY_EXPORT) {
-			if datadir.VirtualAddress != BADRVA {
-				let image = self.read_struct::<ImageExportDirectory>(datadir.VirtualAddress).unwrap();
-				Some(ExportDirectory {
-					view_: self,
-					datadir_: datadir,
-					image_: image,
-				})
-			}
-			else {
-				None
-			}
-		}
-		else {
-			None
-		}
+pub trait PeExports<'a>: Pe<'a> + Sized {
+	/// Get the exports directory.
+	///
+	/// `PeError::Null` if the image has no exports.
+	fn try_exports<'b>(&'b self) -> Result<ExportDirectory<'a, 'b, Self>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_EXPORT).ok_or(PeError::Null));
+		let image = try!(self.try_read_struct::<ImageExportDirectory>(datadir.VirtualAddress));
+		Ok(ExportDirectory {
+			view_: self,
+			datadir_: datadir,
+			image_: image,
+		})
+	}
+	/// Get the exports directory.
+	///
+	/// Convenience wrapper around `try_exports`, panics if the export directory is corrupt.
+	fn exports<'b>(&'b self) -> Option<ExportDirectory<'a, 'b, Self>> {
+		unwrap_null(self.try_exports())
 	}
 }
 
+impl<'a, P: Pe<'a>> PeExports<'a> for P {}
+
 //----------------------------------------------------------------
 
-pub struct ExportIterator<'a: 'b, 'b> {
-	exp: &'b ExportDirectory<'a, 'b>,
-	it: u16,
+pub struct ExportIterator<'a: 'b, 'b, P: 'b = PeView<'a>> {
+	exp: &'b ExportDirectory<'a, 'b, P>,
+	it: u32,
 }
 
-impl<'a, 'b> Iterator for ExportIterator<'a, 'b> {
+impl<'a, 'b, P: Pe<'a>> Iterator for ExportIterator<'a, 'b, P> {
 	type Item = u16;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		if self.it as u32 >= self.exp.image_.NumberOfFunctions {
-			None
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> ExportIterator<'a, 'b, P> {
+	/// Advances the iterator, returning an error instead of panicking if the export directory is corrupt.
+	///
+	/// `PeError::Corrupt` if there are more functions than ordinals. The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<u16>, PeError> {
+		if self.it >= self.exp.image_.NumberOfFunctions {
+			Ok(None)
+		}
+		else if self.it > 0xFFFF {
+			Err(PeError::Corrupt)
 		}
 		else {
-			let ord = self.it + (self.exp.image_.Base & 0xFFFF) as u16;
+			// Ordinals are 16 bit, a large ordinal base wraps around
+			let ord = (self.it as u16).wrapping_add(self.exp.image_.Base as u16);
 			self.it += 1;
-			Some(ord)
+			Ok(Some(ord))
 		}
 	}
 }
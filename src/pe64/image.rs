@@ -6,6 +6,8 @@ pub const IMAGE_NT_OPTIONAL_HDR_MAGIC: u16 = IMAGE_NT_OPTIONAL_HDR64_MAGIC;
 
 pub type ImageOptionalHeader = ImageOptionalHeader64;
 pub type ImageNtHeaders = ImageNtHeaders64;
+pub type ImageTlsDirectory = ImageTlsDirectory64;
+pub type ImageLoadConfigDirectory = ImageLoadConfigDirectory64;
 
 pub const IMAGE_ORDINAL_FLAG: u64 = IMAGE_ORDINAL_FLAG64;
 
@@ -0,0 +1,422 @@
+//! Loader emulator.
+//!
+//! Maps a module and its dependencies into a simulated address space without any help from the system:
+//! every module is mapped at its own base, relocated and its imports are linked against the exports of the other modules.
+//!
+//! The modules are found in any number of search paths or provided as in-memory files.
+//! No code is executed, TLS callbacks and entry points are not called.
+
+use std::{error, fmt, mem};
+use std::path::{Path, PathBuf};
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use super::pefile::PeFile;
+use super::exports::PeExports;
+use super::imports::{PeImports, ImportedSymbol};
+use super::resolver::{Resolver, ResolveError, Symbol, is_api_set};
+use error::PeError;
+use apiset::ApiSetSchema;
+
+//----------------------------------------------------------------
+
+/// Errors while loading modules.
+#[derive(Debug)]
+pub enum LoaderError {
+	/// There was an error reading, relocating or linking a module.
+	Pe(PeError),
+	/// The dll was not found in the search paths nor the in-memory files.
+	DllNotFound(String),
+	/// The dll does not export the symbol, the symbol is given as `DllName.ExportName` or `DllName.#Ordinal`.
+	ImportNotFound(String),
+	/// The forwarders loop back to a symbol already visited, the symbols visited are given in order.
+	ForwarderLoop(Vec<String>),
+	/// There is no free range in the address space large enough for the dll.
+	NoFreeBase(String),
+}
+
+impl From<PeError> for LoaderError {
+	fn from(err: PeError) -> LoaderError {
+		LoaderError::Pe(err)
+	}
+}
+
+impl From<ResolveError> for LoaderError {
+	fn from(err: ResolveError) -> LoaderError {
+		match err {
+			ResolveError::Pe(err) => LoaderError::Pe(err),
+			ResolveError::DllNotFound(name) => LoaderError::DllNotFound(name),
+			ResolveError::SymbolNotFound(name) => LoaderError::ImportNotFound(name),
+			ResolveError::ForwarderLoop(chain) => LoaderError::ForwarderLoop(chain),
+		}
+	}
+}
+
+impl fmt::Display for LoaderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			LoaderError::Pe(ref err) => write!(f, "{}", err),
+			LoaderError::DllNotFound(ref name) => write!(f, "dll not found: {}", name),
+			LoaderError::ImportNotFound(ref name) => write!(f, "import not found: {}", name),
+			LoaderError::ForwarderLoop(ref chain) => write!(f, "forwarder loop: {}", chain.join(" -> ")),
+			LoaderError::NoFreeBase(ref name) => write!(f, "no free base address: {}", name),
+		}
+	}
+}
+
+impl error::Error for LoaderError {
+	fn description(&self) -> &str {
+		match *self {
+			LoaderError::Pe(_) => "pe error",
+			LoaderError::DllNotFound(_) => "dll not found",
+			LoaderError::ImportNotFound(_) => "import not found",
+			LoaderError::ForwarderLoop(_) => "forwarder loop",
+			LoaderError::NoFreeBase(_) => "no free base address",
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Module mapped by the loader.
+pub struct Module {
+	name: String,
+	base: Va,
+	file: PeFile,
+}
+
+impl Module {
+	/// Get the name the module was loaded by.
+	#[inline]
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+	/// Get the base address of the module.
+	#[inline]
+	pub fn base(&self) -> Va {
+		self.base
+	}
+	/// Get the size of the module in the address space.
+	#[inline]
+	pub fn size(&self) -> usize {
+		self.view().image().len()
+	}
+	/// Get the mapped module.
+	#[inline]
+	pub fn file(&self) -> &PeFile {
+		&self.file
+	}
+	/// Get a view into the mapped module.
+	///
+	/// Its virtual base is the address the module was loaded at.
+	#[inline]
+	pub fn view(&self) -> PeView {
+		self.file.view()
+	}
+}
+
+// Imported symbol owned so modules can be loaded while linking.
+enum Import {
+	Name(String),
+	Ordinal(u16),
+}
+
+impl Import {
+	fn symbol(&self) -> Symbol {
+		match *self {
+			Import::Name(ref name) => Symbol::Name(name),
+			Import::Ordinal(ord) => Symbol::Ordinal(ord),
+		}
+	}
+}
+
+// Forwarders name the dll without its extension, imports name the file as is, eg. `ntoskrnl.exe`.
+fn dll_file_name(dll_name: &str) -> String {
+	if dll_name.contains('.') {
+		dll_name.to_owned()
+	}
+	else {
+		format!("{}.dll", dll_name)
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Loader emulator.
+pub struct Loader {
+	search_paths: Vec<PathBuf>,
+	files: Vec<(String, Vec<u8>)>,
+	bases: Vec<(String, Va)>,
+	api_set_schema: Option<ApiSetSchema>,
+	modules: Vec<Module>,
+}
+
+impl Loader {
+	/// Create a loader with an empty address space.
+	pub fn new() -> Loader {
+		Loader {
+			search_paths: Vec::new(),
+			files: Vec::new(),
+			bases: Vec::new(),
+			api_set_schema: None,
+			modules: Vec::new(),
+		}
+	}
+	/// Add a directory to look for dlls.
+	///
+	/// Directories are searched in the order they're added, after the in-memory files.
+	pub fn add_search_path<P: AsRef<Path>>(&mut self, path: P) {
+		self.search_paths.push(path.as_ref().to_path_buf());
+	}
+	/// Add an in-memory file.
+	///
+	/// # Parameters
+	///
+	/// * `name`
+	///
+	///   The file name modules are imported by, eg. `"KERNEL32.dll"`. Names are matched case insensitively.
+	///
+	/// * `bytes`
+	///
+	///   Contents of the file as it is stored on disk.
+	pub fn add_file(&mut self, name: &str, bytes: Vec<u8>) {
+		self.files.push((name.to_owned(), bytes));
+	}
+	/// Choose the base address of a module before it is loaded.
+	///
+	/// Modules without a chosen base are loaded at their preferred image base if it is free,
+	/// otherwise they're placed after the highest loaded module.
+	pub fn set_base(&mut self, name: &str, base: Va) {
+		self.bases.push((name.to_owned(), base));
+	}
+	/// Set the API set schema redirecting api sets to their host dll.
+	///
+	/// Read it from `apisetschema.dll` with `super::apiset::PeApiSet`.
+	/// Without a schema api sets are loaded like any other dll.
+	pub fn set_api_set_schema(&mut self, schema: ApiSetSchema) {
+		self.api_set_schema = Some(schema);
+	}
+	/// Get the loaded modules in the order they were loaded.
+	#[inline]
+	pub fn modules(&self) -> &[Module] {
+		&self.modules
+	}
+	/// Find a loaded module by name, case insensitively.
+	pub fn find_module(&self, name: &str) -> Option<&Module> {
+		self.modules.iter().find(|module| module.name.eq_ignore_ascii_case(name))
+	}
+	/// Find the loaded module containing an address.
+	pub fn module_by_va(&self, va: Va) -> Option<&Module> {
+		self.modules.iter().find(|module| va >= module.base && ((va - module.base) as u64) < module.size() as u64)
+	}
+	/// Load a module and all its dependencies.
+	///
+	/// # Parameters
+	///
+	/// * `name`
+	///
+	///   File name of the module, it is found the same way as its dependencies.
+	///
+	/// # Return value
+	///
+	/// `LoaderError::DllNotFound` if the module or one of its dependencies is not found.
+	///
+	/// `LoaderError::ImportNotFound` if an imported symbol is not exported by its dll.
+	///
+	/// `LoaderError::NoFreeBase` if a module does not fit in the address space after the loaded modules.
+	///
+	/// `LoaderError::Pe` if reading or relocating a module fails, eg. `PeError::Null` if a module must be relocated but has no relocations.
+	///
+	/// Otherwise the loaded module, it is not loaded again if it already was.
+	///
+	/// # Remarks
+	///
+	/// If the module fails to load, it and the dependencies loaded for it are unloaded again.
+	pub fn load(&mut self, name: &str) -> Result<&Module, LoaderError> {
+		let index = try!(self.load_index(name));
+		Ok(&self.modules[index])
+	}
+	/// Find the address of an exported symbol by name, loading the dll and following forwarders as needed.
+	pub fn resolve_by_name(&mut self, dll_name: &str, name: &str) -> Result<Va, LoaderError> {
+		self.resolve(dll_name, Symbol::Name(name))
+	}
+	/// Find the address of an exported symbol by ordinal, loading the dll and following forwarders as needed.
+	pub fn resolve_by_ordinal(&mut self, dll_name: &str, ord: u16) -> Result<Va, LoaderError> {
+		self.resolve(dll_name, Symbol::Ordinal(ord))
+	}
+
+	fn load_index(&mut self, name: &str) -> Result<usize, LoaderError> {
+		if let Some(index) = self.modules.iter().position(|module| module.name.eq_ignore_ascii_case(name)) {
+			return Ok(index);
+		}
+		// Unload the module and the dependencies loaded for it if it fails, their imports may point into it
+		let index = self.modules.len();
+		match self.map_and_link(name) {
+			Ok(()) => Ok(index),
+			Err(err) => {
+				self.modules.truncate(index);
+				Err(err)
+			},
+		}
+	}
+	fn map_and_link(&mut self, name: &str) -> Result<(), LoaderError> {
+		//---------------- Map the module
+		let mut file = try!(self.open(name));
+		let base = try!(self.choose_base(name, &file));
+		if base != file.view().optional_header().ImageBase {
+			try!(file.rebase(base));
+		}
+		// Registered before linking so cyclic imports find it
+		let index = self.modules.len();
+		self.modules.push(Module {
+			name: name.to_owned(),
+			base: base,
+			file: file,
+		});
+
+		//---------------- Link the imports
+		let mut iats = Vec::new();
+		let mut imports = Vec::new();
+		for (mut dll_name, symbols) in try!(self.read_imports(index)) {
+			if let Some(host) = self.api_set_host(&dll_name, index) {
+				dll_name = host;
+			}
+			for (iat, import) in symbols {
+				iats.push(iat);
+				imports.push((dll_name.clone(), import));
+			}
+		}
+		let symbols: Vec<(&str, Symbol)> = imports.iter().map(|&(ref dll_name, ref import)| (&dll_name[..], import.symbol())).collect();
+		let vas = try!(self.resolve_all(&symbols));
+		for (&iat, &va) in iats.iter().zip(&vas) {
+			let bytes: Vec<u8> = (0..mem::size_of::<Va>()).map(|i| (va >> (i * 8)) as u8).collect();
+			try!(self.modules[index].file.try_write(iat, &bytes));
+		}
+		Ok(())
+	}
+	fn api_set_host(&self, dll_name: &str, importer: usize) -> Option<String> {
+		if !is_api_set(dll_name) {
+			return None;
+		}
+		let schema = match self.api_set_schema {
+			Some(ref schema) => schema,
+			None => return None,
+		};
+		schema.resolve(dll_name, &self.modules[importer].name).map(String::from)
+	}
+	fn open(&self, name: &str) -> Result<PeFile, LoaderError> {
+		if let Some(&(_, ref bytes)) = self.files.iter().find(|&&(ref file_name, _)| file_name.eq_ignore_ascii_case(name)) {
+			return Ok(try!(PeFile::from_bytes(bytes)));
+		}
+		// File systems may be case sensitive, try the lowercase name too
+		for dir in &self.search_paths {
+			for file_name in &[name.to_owned(), name.to_lowercase()] {
+				let path = dir.join(file_name);
+				if path.is_file() {
+					return Ok(try!(PeFile::open(&path)));
+				}
+			}
+		}
+		Err(LoaderError::DllNotFound(name.to_owned()))
+	}
+	fn choose_base(&self, name: &str, file: &PeFile) -> Result<Va, LoaderError> {
+		if let Some(&(_, base)) = self.bases.iter().find(|&&(ref base_name, _)| base_name.eq_ignore_ascii_case(name)) {
+			return Ok(base);
+		}
+		let view = file.view();
+		let preferred = view.optional_header().ImageBase as u64;
+		// Computed in u64, modules may end right at the top of the address space
+		let size = view.image().len() as u64;
+		let end = |module: &Module| (module.base as u64).saturating_add(module.size() as u64);
+		let fits = |base: u64| base.checked_add(size).map(|end| end - 1 <= Va::max_value() as u64).unwrap_or(false);
+		let overlaps = |base: u64| self.modules.iter().any(|module| base < end(module) && (module.base as u64) < base + size);
+		if fits(preferred) && !overlaps(preferred) {
+			return Ok(preferred as Va);
+		}
+		// Place it after the highest module, aligned to the allocation granularity
+		let highest = self.modules.iter().map(|module| end(module)).max().unwrap_or(preferred);
+		match highest.checked_add(0xFFFF).map(|end| end & !0xFFFF) {
+			Some(base) if fits(base) => Ok(base as Va),
+			_ => Err(LoaderError::NoFreeBase(name.to_owned())),
+		}
+	}
+	fn read_imports(&self, index: usize) -> Result<Vec<(String, Vec<(Rva, Import)>)>, LoaderError> {
+		let view = self.modules[index].view();
+		let mut result = Vec::new();
+		let imports = match view.try_imports() {
+			Ok(imports) => imports,
+			Err(PeError::Null) => return Ok(result),
+			Err(err) => return Err(LoaderError::Pe(err)),
+		};
+		let mut it = imports.iter();
+		while let Some(desc) = try!(it.try_next()) {
+			let mut symbols = Vec::new();
+			let mut iat = desc.image().FirstThunk;
+			let mut names = desc.int_iter();
+			while let Some(symbol) = try!(names.try_next()) {
+				symbols.push((iat, match symbol {
+					ImportedSymbol::ByName { name, .. } => Import::Name(name.to_owned()),
+					ImportedSymbol::ByOrdinal { ord } => Import::Ordinal(ord),
+				}));
+				iat = try!(iat.checked_add(mem::size_of::<Va>() as Rva).ok_or(PeError::Bounds));
+			}
+			result.push((try!(desc.try_dll_name()).to_owned(), symbols));
+		}
+		Ok(result)
+	}
+	fn resolve(&mut self, dll_name: &str, symbol: Symbol) -> Result<Va, LoaderError> {
+		let vas = try!(self.resolve_all(&[(dll_name, symbol)]));
+		Ok(vas[0])
+	}
+	fn resolve_all(&mut self, symbols: &[(&str, Symbol)]) -> Result<Vec<Va>, LoaderError> {
+		let mut vas = Vec::with_capacity(symbols.len());
+		// Dlls are loaded as the imports and forwarders lead to them, the exports are only read again after loading one
+		while vas.len() < symbols.len() {
+			let missing = {
+				let views: Vec<PeView> = self.modules.iter().map(Module::view).collect();
+				let mut resolver = Resolver::new();
+				if let Some(ref schema) = self.api_set_schema {
+					resolver.set_api_set(move |api_set, importer| schema.resolve(api_set, importer).map(String::from));
+				}
+				for (module, view) in self.modules.iter().zip(&views) {
+					match view.try_exports() {
+						Ok(exports) => resolver.insert(&module.name, exports),
+						Err(PeError::Null) => (),
+						Err(err) => return Err(LoaderError::Pe(err)),
+					}
+				}
+				let mut missing = None;
+				for &(dll_name, symbol) in &symbols[vas.len()..] {
+					match resolver.resolve(dll_name, symbol) {
+						Ok(resolved) => {
+							let module = self.find_module(&resolved.dll_name).unwrap();
+							vas.push(module.base + resolved.rva as Va);
+						},
+						Err(ResolveError::DllNotFound(name)) => {
+							missing = Some(dll_file_name(&name));
+							break;
+						},
+						Err(err) => return Err(LoaderError::from(err)),
+					}
+				}
+				match missing {
+					Some(missing) => missing,
+					None => break,
+				}
+			};
+			// Already loaded but without exports
+			if self.find_module(&missing).is_some() {
+				return Err(LoaderError::ImportNotFound(format!("{}.{}", missing, symbols[vas.len()].1)));
+			}
+			try!(self.load_index(&missing));
+		}
+		Ok(vas)
+	}
+}
+
+impl Default for Loader {
+	fn default() -> Loader {
+		Loader::new()
+	}
+}
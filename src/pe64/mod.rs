@@ -3,9 +3,24 @@
 //! [Peering Inside the PE: A Tour of the Win32 Portable Executable File Format](https://msdn.microsoft.com/en-us/library/ms809762.aspx)
 
 pub mod image;
+pub mod pe;
 pub mod peview;
 pub mod pefile;
+pub mod pefileview;
 pub mod exports;
+pub mod symbols;
+pub mod resolver;
+pub mod apiset;
 pub mod imports;
+pub mod delay_imports;
+pub mod bound_imports;
+pub mod security;
+pub mod overlay;
 pub mod relocs;
+pub mod diff;
+pub mod loader;
 pub mod resources;
+pub mod debug;
+pub mod tls;
+pub mod load_config;
+pub mod exceptions;
@@ -0,0 +1,73 @@
+//! PE overlay.
+//!
+//! See `pelite::overlay` for more info.
+
+use std::cmp;
+use std::ops::Range;
+
+use super::image::*;
+use super::pe::Pe;
+use error::{PeError, unwrap_null};
+use overlay::Overlay;
+
+/// Access to the overlay.
+///
+/// Since the overlay is located by file offset, this is only implemented for backends with access to the file on disk, eg. `super::pefileview::PeFileView` over a memory mapped file.
+/// `super::pefile::PeFile` does not keep the overlay in memory, see `PeFile::overlay_range` and `PeFile::read_overlay` instead.
+pub trait PeOverlay {
+	/// Get the overlay.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if there is no data after the last section.
+	///
+	/// `PeError::Bounds` if the sections extend past the end of the file.
+	fn try_overlay(&self) -> Result<Overlay, PeError>;
+	/// Get the overlay.
+	///
+	/// Convenience wrapper around `try_overlay`.
+	fn overlay(&self) -> Option<Overlay> {
+		unwrap_null(self.try_overlay())
+	}
+}
+
+/// Compute the file offset range of the overlay.
+///
+/// # Parameters
+///
+/// * `pe`
+///
+///   The headers of the file.
+///
+/// * `file_size`
+///
+///   Size of the file on disk.
+///
+/// # Return value
+///
+/// Same as `PeOverlay::try_overlay`.
+///
+/// # Remarks
+///
+/// The overlay starts after the raw data of the last section (or the headers if there are no sections on disk).
+/// The certificate table is appended to the file when signing, if it follows the sections the overlay ends where it starts.
+/// Any data after the certificate table is not part of the overlay.
+pub fn overlay_range<'a, P: Pe<'a>>(pe: &P, file_size: usize) -> Result<Range<FileOffset>, PeError> {
+	let mut begin = pe.optional_header().SizeOfHeaders as usize;
+	for it in pe.section_headers() {
+		if it.SizeOfRawData != 0 {
+			begin = cmp::max(begin, it.PointerToRawData as usize + it.SizeOfRawData as usize);
+		}
+	}
+	if begin > file_size {
+		return Err(PeError::Bounds);
+	}
+	let end = match pe.data_directory().get(IMAGE_DIRECTORY_ENTRY_SECURITY) {
+		Some(datadir) if datadir.VirtualAddress as usize >= begin && datadir.VirtualAddress as usize <= file_size => datadir.VirtualAddress as usize,
+		_ => file_size,
+	};
+	if begin == end {
+		return Err(PeError::Null);
+	}
+	Ok(begin..end)
+}
@@ -0,0 +1,88 @@
+//! PeFileView definitions.
+
+use super::image::*;
+use super::pe::{Pe, validate_headers};
+use super::security::PeSecurity;
+use super::overlay::{PeOverlay, overlay_range};
+use error::PeError;
+use security::Security;
+use overlay::Overlay;
+
+/// PeFileView provides interaction with a PE image as it is stored on disk.
+///
+/// Unlike `super::peview::PeView` the sections are not expected to be at their virtual addresses.
+/// Every Rva is translated to a file offset through the section table, no data is copied.
+/// Use this to inspect files without mapping them, eg. straight from a memory mapped file.
+pub struct PeFileView<'a> {
+	image: &'a [u8],
+}
+
+impl<'a> PeFileView<'a> {
+	/// Create a new instance of PeFileView of a file on disk.
+	///
+	/// # Parameters
+	///
+	/// * `image`
+	///
+	///   Contents of the file as it is stored on disk.
+	///
+	/// # Return value
+	///
+	/// Any error from `super::pe::validate_headers`.
+	///
+	/// Otherwise a view into `image`, the headers are validated so the header accessors can't fail.
+	pub fn from_bytes(image: &'a [u8]) -> Result<PeFileView<'a>, PeError> {
+		try!(validate_headers(image));
+		Ok(PeFileView {
+			image: image,
+		})
+	}
+}
+
+unsafe impl<'a> Pe<'a> for PeFileView<'a> {
+	#[inline]
+	fn image(&self) -> &'a [u8] {
+		self.image
+	}
+	/// Translates through the section table.
+	///
+	/// The result extends up to the end of the raw data of the section (or the headers) `rva` is in.
+	fn rva_to_slice(&self, rva: Rva) -> Result<&'a [u8], PeError> {
+		if rva == BADRVA {
+			return Err(PeError::Null);
+		}
+		let size_of_headers = self.optional_header().SizeOfHeaders;
+		let (begin, end) = if rva < size_of_headers {
+			(rva as usize, size_of_headers as usize)
+		}
+		else {
+			let it = try!(self.section_headers().iter().find(|it| {
+				rva >= it.VirtualAddress && rva - it.VirtualAddress < it.SizeOfRawData
+			}).ok_or(PeError::Bounds));
+			let raw = it.PointerToRawData as usize;
+			(raw + (rva - it.VirtualAddress) as usize, raw + it.SizeOfRawData as usize)
+		};
+		self.image.get(begin..end).ok_or(PeError::Bounds)
+	}
+}
+
+impl<'a> PeSecurity for PeFileView<'a> {
+	fn try_security(&self) -> Result<Security, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_SECURITY).ok_or(PeError::Null));
+		if datadir.VirtualAddress == 0 {
+			return Err(PeError::Null);
+		}
+		// The VirtualAddress is really a file offset
+		let begin = datadir.VirtualAddress as usize;
+		let end = try!(begin.checked_add(datadir.Size as usize).ok_or(PeError::Bounds));
+		let data = try!(self.image().get(begin..end).ok_or(PeError::Bounds));
+		Ok(Security::new(data))
+	}
+}
+
+impl<'a> PeOverlay for PeFileView<'a> {
+	fn try_overlay(&self) -> Result<Overlay, PeError> {
+		let range = try!(overlay_range(self, self.image.len()));
+		Ok(Overlay::new(range.start, &self.image[range]))
+	}
+}
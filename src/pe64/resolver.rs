@@ -0,0 +1,268 @@
+//! Export forwarder resolution.
+//!
+//! An exported symbol may be forwarded to a symbol in another dll, which in turn may be forwarded again.
+//! The resolver follows these chains across a set of export directories until it finds the actual code or data.
+//!
+//! Forwarders are given as `DllName.ExportName` or `DllName.#Ordinal`, the dll name is given without its extension.
+//! For more information see: https://blogs.msdn.microsoft.com/oldnewthing/20060719-24/?p=30473
+
+use std::{error, fmt};
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use super::exports::{ExportDirectory, Export};
+use error::PeError;
+
+//----------------------------------------------------------------
+
+/// Symbol to resolve, exported by name or by ordinal.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Symbol<'a> {
+	Name(&'a str),
+	Ordinal(u16),
+}
+
+impl<'a> fmt::Display for Symbol<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Symbol::Name(name) => f.write_str(name),
+			Symbol::Ordinal(ord) => write!(f, "#{}", ord),
+		}
+	}
+}
+
+/// Parse a forwarder string.
+///
+/// # Return value
+///
+/// `PeError::Corrupt` if the forwarder is not of the form `DllName.ExportName` or `DllName.#Ordinal`.
+///
+/// Otherwise the dll name, without extension, and the symbol it is forwarded to.
+pub fn parse_forward(forward: &str) -> Result<(&str, Symbol), PeError> {
+	// Dll names may contain dots, export names generally don't
+	let dot = try!(forward.rfind('.').ok_or(PeError::Corrupt));
+	let (dll_name, name) = (&forward[..dot], &forward[dot + 1..]);
+	if dll_name.is_empty() || name.is_empty() {
+		return Err(PeError::Corrupt);
+	}
+	let symbol = if name.starts_with('#') {
+		Symbol::Ordinal(try!(name[1..].parse().map_err(|_| PeError::Corrupt)))
+	}
+	else {
+		Symbol::Name(name)
+	};
+	Ok((dll_name, symbol))
+}
+
+/// Check whether a dll name refers to an api set rather than an actual dll, eg. `api-ms-win-core-synch-l1-2-0.dll`.
+pub fn is_api_set(dll_name: &str) -> bool {
+	let prefix = dll_name.get(..4).unwrap_or("");
+	prefix.eq_ignore_ascii_case("api-") || prefix.eq_ignore_ascii_case("ext-")
+}
+
+// Dll names are compared case insensitively and the `.dll` extension is optional.
+fn dll_eq(lhs: &str, rhs: &str) -> bool {
+	fn strip(name: &str) -> &str {
+		let len = name.len();
+		if len > 4 && name.get(len - 4..).map(|ext| ext.eq_ignore_ascii_case(".dll")).unwrap_or(false) { &name[..len - 4] }
+		else { name }
+	}
+	strip(lhs).eq_ignore_ascii_case(strip(rhs))
+}
+
+//----------------------------------------------------------------
+
+/// Errors while resolving a symbol.
+#[derive(Debug)]
+pub enum ResolveError {
+	/// One of the export directories or forwarders is corrupt.
+	Pe(PeError),
+	/// The dll is not known to the resolver.
+	DllNotFound(String),
+	/// The dll does not export the symbol, given as `DllName.ExportName` or `DllName.#Ordinal`.
+	SymbolNotFound(String),
+	/// The forwarders loop back to a symbol already visited, the symbols visited are given in order.
+	ForwarderLoop(Vec<String>),
+}
+
+impl From<PeError> for ResolveError {
+	fn from(err: PeError) -> ResolveError {
+		ResolveError::Pe(err)
+	}
+}
+
+impl fmt::Display for ResolveError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			ResolveError::Pe(ref err) => write!(f, "{}", err),
+			ResolveError::DllNotFound(ref name) => write!(f, "dll not found: {}", name),
+			ResolveError::SymbolNotFound(ref name) => write!(f, "symbol not found: {}", name),
+			ResolveError::ForwarderLoop(ref chain) => write!(f, "forwarder loop: {}", chain.join(" -> ")),
+		}
+	}
+}
+
+impl error::Error for ResolveError {
+	fn description(&self) -> &str {
+		match *self {
+			ResolveError::Pe(_) => "pe error",
+			ResolveError::DllNotFound(_) => "dll not found",
+			ResolveError::SymbolNotFound(_) => "symbol not found",
+			ResolveError::ForwarderLoop(_) => "forwarder loop",
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Resolved symbol.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Resolved {
+	/// Name of the dll actually exporting the symbol, as it was given to the resolver.
+	pub dll_name: String,
+	/// Rva of the symbol in that dll.
+	pub rva: Rva,
+}
+
+/// Follows export forwarders across dlls.
+///
+/// Dlls are provided up front with `insert` or on demand by a callback, see `set_callback`.
+/// Api set names are translated to their host dll by another callback, see `set_api_set`.
+pub struct Resolver<'a, 'b, P: 'b = PeView<'a>> {
+	dlls: Vec<(String, ExportDirectory<'a, 'b, P>)>,
+	callback: Option<Box<dyn FnMut(&str) -> Option<ExportDirectory<'a, 'b, P>> + 'b>>,
+	api_set: Option<Box<dyn FnMut(&str, &str) -> Option<String> + 'b>>,
+}
+
+impl<'a, 'b, P: Pe<'a>> Resolver<'a, 'b, P> {
+	/// Create a resolver without any dlls.
+	pub fn new() -> Resolver<'a, 'b, P> {
+		Resolver {
+			dlls: Vec::new(),
+			callback: None,
+			api_set: None,
+		}
+	}
+	/// Add the exports of a dll.
+	///
+	/// # Parameters
+	///
+	/// * `dll_name`
+	///
+	///   Name of the dll, matched case insensitively and with or without the `.dll` extension.
+	///
+	/// * `exports`
+	///
+	///   The export directory of the dll.
+	pub fn insert(&mut self, dll_name: &str, exports: ExportDirectory<'a, 'b, P>) {
+		self.dlls.push((dll_name.to_owned(), exports));
+	}
+	/// Set the callback providing the exports of dlls not inserted up front.
+	///
+	/// It is called at most once per dll name, the result is remembered.
+	pub fn set_callback<F: FnMut(&str) -> Option<ExportDirectory<'a, 'b, P>> + 'b>(&mut self, f: F) {
+		self.callback = Some(Box::new(f));
+	}
+	/// Set the callback translating api set names to their host dll.
+	///
+	/// It receives the api set name and the name of the dll forwarding to it, empty if the api set is resolved directly,
+	/// and returns the name of the host dll.
+	/// Without this callback api sets are looked up like any other dll.
+	pub fn set_api_set<F: FnMut(&str, &str) -> Option<String> + 'b>(&mut self, f: F) {
+		self.api_set = Some(Box::new(f));
+	}
+	/// Resolve a symbol exported by name.
+	#[inline]
+	pub fn resolve_by_name(&mut self, dll_name: &str, name: &str) -> Result<Resolved, ResolveError> {
+		self.resolve(dll_name, Symbol::Name(name))
+	}
+	/// Resolve a symbol exported by ordinal.
+	#[inline]
+	pub fn resolve_by_ordinal(&mut self, dll_name: &str, ord: u16) -> Result<Resolved, ResolveError> {
+		self.resolve(dll_name, Symbol::Ordinal(ord))
+	}
+	/// Resolve a symbol, following any forwarders.
+	///
+	/// # Parameters
+	///
+	/// * `dll_name`
+	///
+	///   Name of the dll exporting the symbol.
+	///
+	/// * `symbol`
+	///
+	///   The symbol to resolve.
+	///
+	/// # Return value
+	///
+	/// `ResolveError::DllNotFound` if the dll or the target of a forwarder is not known to the resolver.
+	///
+	/// `ResolveError::SymbolNotFound` if the dll or the target of a forwarder does not export the symbol.
+	///
+	/// `ResolveError::ForwarderLoop` if the forwarders loop back to a symbol already visited.
+	///
+	/// `ResolveError::Pe` if an export directory or a forwarder is corrupt.
+	///
+	/// Otherwise the dll and rva of the symbol at the end of the forwarder chain.
+	pub fn resolve<'s>(&mut self, dll_name: &'s str, symbol: Symbol<'s>) -> Result<Resolved, ResolveError> where 'a: 's {
+		let mut visited: Vec<(usize, Symbol)> = Vec::new();
+		let (mut dll_name, mut symbol) = (dll_name, symbol);
+		// The dll forwarding to the current symbol and the host dll of the current api set
+		let mut importer = String::new();
+		let mut host;
+		loop {
+			if is_api_set(dll_name) {
+				if let Some(ref mut api_set) = self.api_set {
+					host = try!(api_set(dll_name, &importer).ok_or_else(|| ResolveError::DllNotFound(dll_name.to_owned())));
+					dll_name = &host;
+				}
+			}
+			let index = try!(self.find(dll_name));
+			let looped = visited.contains(&(index, symbol));
+			visited.push((index, symbol));
+			if looped {
+				let chain = visited.iter().map(|&(index, symbol)| format!("{}.{}", self.dlls[index].0, symbol)).collect();
+				return Err(ResolveError::ForwarderLoop(chain));
+			}
+			let export = {
+				let exports = &self.dlls[index].1;
+				match symbol {
+					Symbol::Name(name) => try!(exports.try_symbol_by_name(name)),
+					Symbol::Ordinal(ord) => try!(exports.try_symbol_by_ordinal(ord)),
+				}
+			};
+			let forward = match export {
+				Export::Symbol(&rva) => return Ok(Resolved {
+					dll_name: self.dlls[index].0.clone(),
+					rva: rva,
+				}),
+				Export::Forward(forward) => forward,
+				Export::None => return Err(ResolveError::SymbolNotFound(format!("{}.{}", self.dlls[index].0, symbol))),
+			};
+			let (target, target_symbol) = try!(parse_forward(forward));
+			importer = self.dlls[index].0.clone();
+			dll_name = target;
+			symbol = target_symbol;
+		}
+	}
+	fn find(&mut self, dll_name: &str) -> Result<usize, ResolveError> {
+		if let Some(index) = self.dlls.iter().position(|&(ref name, _)| dll_eq(name, dll_name)) {
+			return Ok(index);
+		}
+		let exports = self.callback.as_mut().and_then(|callback| callback(dll_name));
+		match exports {
+			Some(exports) => {
+				self.dlls.push((dll_name.to_owned(), exports));
+				Ok(self.dlls.len() - 1)
+			},
+			None => Err(ResolveError::DllNotFound(dll_name.to_owned())),
+		}
+	}
+}
+
+impl<'a, 'b, P: Pe<'a>> Default for Resolver<'a, 'b, P> {
+	fn default() -> Resolver<'a, 'b, P> {
+		Resolver::new()
+	}
+}
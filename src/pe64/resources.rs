@@ -3,21 +3,28 @@
 //! See `pelite::resources` for more info.
 
 use super::image::*;
-use super::peview::PeView;
+use super::pe::Pe;
+use error::{PeError, unwrap_null};
 use resources::Resources;
 
-pub trait PeResources {
-	fn resources(&self) -> Option<Resources>;
-}
-
-impl<'a> PeResources for PeView<'a> {
-	fn resources(&self) -> Option<Resources> {
-		if let Some(datadir) = self.data_directory().get(IMAGE_DIRECTORY_ENTRY_RESOURCE) {
-			if datadir.VirtualAddress != BADRVA {
-				let resrc = self.read_slice::<u8>(datadir.VirtualAddress, datadir.Size as usize).unwrap();
-				return Some(Resources::new(resrc, datadir.VirtualAddress));
-			}
+pub trait PeResources<'a>: Pe<'a> + Sized {
+	/// Get the resources.
+	///
+	/// `PeError::Null` if the image has no resources.
+	fn try_resources(&self) -> Result<Resources<'a>, PeError> {
+		let datadir = try!(self.data_directory().get(IMAGE_DIRECTORY_ENTRY_RESOURCE).ok_or(PeError::Null));
+		if datadir.VirtualAddress == BADRVA {
+			return Err(PeError::Null);
 		}
-		return None;
+		let resrc = try!(self.try_read_slice::<u8>(datadir.VirtualAddress, datadir.Size as usize));
+		Ok(Resources::new(resrc, datadir.VirtualAddress))
+	}
+	/// Get the resources.
+	///
+	/// Convenience wrapper around `try_resources`.
+	fn resources(&self) -> Option<Resources<'a>> {
+		unwrap_null(self.try_resources())
 	}
 }
+
+impl<'a, P: Pe<'a>> PeResources<'a> for P {}
@@ -0,0 +1,108 @@
+//! PE certificate table.
+//!
+//! See `pelite::security` for more info.
+
+use std::{cmp, mem};
+
+use super::image::*;
+use super::pe::Pe;
+use super::pefileview::PeFileView;
+use error::{PeError, unwrap_null};
+use security::{Security, AuthenticodeHasher};
+
+/// Access to the certificate table.
+///
+/// Since the certificate table is pointed to by a file offset, this is only implemented for backends with access to the file on disk:
+/// `super::pefileview::PeFileView` and `super::pefile::PeFile`, which keeps the certificate table around when reading the file.
+/// Mapped images like `super::peview::PeView` do not contain it.
+pub trait PeSecurity {
+	/// Get the certificate table.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if the image is not signed.
+	///
+	/// `PeError::Bounds` if the certificate table is not inside the file.
+	fn try_security(&self) -> Result<Security, PeError>;
+	/// Get the certificate table.
+	///
+	/// Convenience wrapper around `try_security`.
+	fn security(&self) -> Option<Security> {
+		unwrap_null(self.try_security())
+	}
+}
+
+//----------------------------------------------------------------
+
+impl<'a> PeFileView<'a> {
+	/// Compute the Authenticode digest of the file.
+	///
+	/// # Parameters
+	///
+	/// * `hasher`
+	///
+	///   Receives the bytes covered by the signature in order, finalize it afterwards to get the digest.
+	///
+	/// # Return value
+	///
+	/// `PeError::Bounds` if a section or the certificate table is not inside the file.
+	///
+	/// `PeError::Corrupt` if the headers do not fit in `SizeOfHeaders`.
+	///
+	/// # Remarks
+	///
+	/// As the Authenticode specification requires, the whole file is hashed except for the checksum,
+	/// the security data directory entry and the certificate table itself.
+	/// Sections are hashed in the order of their file offsets, followed by any data between the last section and the certificate table.
+	pub fn authenticode_hash<H: AuthenticodeHasher + ?Sized>(&self, hasher: &mut H) -> Result<(), PeError> {
+		let image = self.image();
+		let size_of_headers = self.optional_header().SizeOfHeaders as usize;
+		// Offsets of the fields to skip, these are the same for PE32 and PE64
+		let opt_offset = self.dos_header().e_lfanew as usize + mem::size_of::<ImageNtHeaders>() - mem::size_of::<ImageOptionalHeader>();
+		let checksum_offset = opt_offset + 64;
+		let datadir_offset = opt_offset + mem::size_of::<ImageOptionalHeader>() - mem::size_of::<[ImageDataDirectory; IMAGE_NUMBEROF_DIRECTORY_ENTRIES]>();
+		let security_offset = datadir_offset + IMAGE_DIRECTORY_ENTRY_SECURITY * mem::size_of::<ImageDataDirectory>();
+		let security = self.data_directory().get(IMAGE_DIRECTORY_ENTRY_SECURITY);
+
+		//---------------- Headers
+		let headers = try!(image.get(..size_of_headers).ok_or(PeError::Bounds));
+		if headers.len() < datadir_offset + mem::size_of_val(self.data_directory()) {
+			return Err(PeError::Corrupt);
+		}
+		hasher.update(&headers[..checksum_offset]);
+		if security.is_some() {
+			hasher.update(&headers[checksum_offset + 4..security_offset]);
+			hasher.update(&headers[security_offset + mem::size_of::<ImageDataDirectory>()..]);
+		}
+		else {
+			hasher.update(&headers[checksum_offset + 4..]);
+		}
+
+		//---------------- Sections ordered by file offset
+		let mut sections: Vec<&ImageSectionHeader> = self.section_headers().iter().filter(|it| it.SizeOfRawData != 0).collect();
+		sections.sort_by_key(|it| it.PointerToRawData);
+		let mut end = size_of_headers;
+		for it in sections {
+			let begin = it.PointerToRawData as usize;
+			let section_end = begin + it.SizeOfRawData as usize;
+			hasher.update(try!(image.get(begin..section_end).ok_or(PeError::Bounds)));
+			end = cmp::max(end, section_end);
+		}
+
+		//---------------- Extra data up to the certificate table
+		let cert_offset = match security {
+			Some(datadir) if datadir.VirtualAddress != 0 => {
+				let cert_offset = datadir.VirtualAddress as usize;
+				if cert_offset.checked_add(datadir.Size as usize).map(|cert_end| cert_end > image.len()).unwrap_or(true) {
+					return Err(PeError::Bounds);
+				}
+				cert_offset
+			},
+			_ => image.len(),
+		};
+		if cert_offset > end {
+			hasher.update(&image[end..cert_offset]);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,184 @@
+//! Symbolizing addresses with the exports.
+//!
+//! Without debug information the export table is the only source of symbol names,
+//! an address is described by the nearest preceding export, eg. `CreateFileW+0x1A` or `#12+0x40`.
+//!
+//! The nearest preceding export is not necessarily the function containing the address, the functions in between may simply not be exported.
+//! The x64 exception directory records the bounds of every non-leaf function, when present it is used to reject exports from a different function.
+
+use std::fmt;
+
+use super::image::*;
+use super::pe::Pe;
+use super::peview::PeView;
+use super::exports::ExportDirectory;
+use super::exceptions::{PeExceptions, Exceptions};
+use error::PeError;
+
+//----------------------------------------------------------------
+
+/// Exported symbol at an address.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ExportSymbol<'a> {
+	/// Rva of the symbol.
+	pub rva: Rva,
+	/// Ordinal of the symbol.
+	pub ord: u16,
+	/// Name of the symbol, `None` if exported by ordinal only.
+	pub name: Option<&'a str>,
+}
+
+impl<'a> fmt::Display for ExportSymbol<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.name {
+			Some(name) => f.write_str(name),
+			None => write!(f, "#{}", self.ord),
+		}
+	}
+}
+
+/// Address described relative to an exported symbol.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Symbolized<'a> {
+	/// The containing or nearest preceding export.
+	pub symbol: ExportSymbol<'a>,
+	/// Offset of the address from the symbol.
+	pub offset: u32,
+}
+
+impl<'a> fmt::Display for Symbolized<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.offset == 0 {
+			write!(f, "{}", self.symbol)
+		}
+		else {
+			write!(f, "{}+{:#X}", self.symbol, self.offset)
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+// Limits following chained unwind information.
+const MAX_CHAINED: usize = 32;
+
+/// Index of the exports by address.
+pub struct SymbolIndex<'a, 'b, P: 'b = PeView<'a>> {
+	view_: &'b P,
+	symbols_: Vec<ExportSymbol<'a>>,
+	exceptions_: Option<Exceptions<'a, 'b, P>>,
+}
+
+impl<'a, 'b, P: Pe<'a>> SymbolIndex<'a, 'b, P> {
+	/// Build the index.
+	///
+	/// # Parameters
+	///
+	/// * `exports`
+	///
+	///   The export directory of the module.
+	///
+	/// # Return value
+	///
+	/// `PeError` if the export directory or the exception directory is corrupt.
+	///
+	/// # Remarks
+	///
+	/// Forwarded exports are not part of the module and are left out of the index.
+	/// Exports sharing an address are ordered so that named exports are preferred.
+	pub fn new(exports: &ExportDirectory<'a, 'b, P>) -> Result<SymbolIndex<'a, 'b, P>, PeError> {
+		let view = exports.view();
+		let functions = try!(exports.try_functions());
+		let names = try!(exports.try_names());
+		let name_indices = try!(exports.try_name_indices());
+		let ord = |index: usize| (exports.image().Base as usize).wrapping_add(index) as u16;
+		let mut symbols = Vec::with_capacity(functions.len());
+		let mut named = vec![false; functions.len()];
+		for (&name_rva, &index) in names.iter().zip(name_indices) {
+			let index = index as usize;
+			let rva = *try!(functions.get(index).ok_or(PeError::Corrupt));
+			if rva == BADRVA || exports.is_forwarded(rva) {
+				continue;
+			}
+			symbols.push(ExportSymbol { rva: rva, ord: ord(index), name: Some(try!(view.try_read_str(name_rva))) });
+			named[index] = true;
+		}
+		for (index, &rva) in functions.iter().enumerate() {
+			if rva == BADRVA || exports.is_forwarded(rva) || named[index] {
+				continue;
+			}
+			symbols.push(ExportSymbol { rva: rva, ord: ord(index), name: None });
+		}
+		symbols.sort_by_key(|symbol| (symbol.rva, symbol.name.is_none()));
+		let exceptions = match view.try_exceptions() {
+			Ok(exceptions) => Some(exceptions),
+			Err(PeError::Null) => None,
+			Err(err) => return Err(err),
+		};
+		Ok(SymbolIndex {
+			view_: view,
+			symbols_: symbols,
+			exceptions_: exceptions,
+		})
+	}
+	/// Get the associated `Pe` image.
+	#[inline]
+	pub fn view(&self) -> &'b P {
+		self.view_
+	}
+	/// Get the exported symbols ordered by address.
+	#[inline]
+	pub fn symbols(&self) -> &[ExportSymbol<'a>] {
+		&self.symbols_
+	}
+	/// Describe an Rva by the containing or nearest preceding export.
+	///
+	/// # Return value
+	///
+	/// `None` if there is no export before `rva`, or the exception directory shows that `rva` is in a different function than that export.
+	pub fn lookup(&self, rva: Rva) -> Option<Symbolized<'a>> {
+		let index = match self.symbols_.binary_search_by_key(&rva, |symbol| symbol.rva) {
+			Ok(index) => index,
+			Err(0) => return None,
+			Err(index) => index - 1,
+		};
+		// Prefer the first of the symbols sharing this address
+		let symbol_rva = self.symbols_[index].rva;
+		let index = self.symbols_[..index].iter().rposition(|symbol| symbol.rva != symbol_rva).map(|i| i + 1).unwrap_or(0);
+		let symbol = self.symbols_[index];
+		if self.function_begin(rva) != self.function_begin(symbol.rva) {
+			return None;
+		}
+		Some(Symbolized {
+			symbol: symbol,
+			offset: rva - symbol.rva,
+		})
+	}
+	/// Describe a Va by the containing or nearest preceding export.
+	///
+	/// Same as `lookup`, `None` if `va` is not inside the module.
+	pub fn lookup_va(&self, va: Va) -> Option<Symbolized<'a>> {
+		match self.view_.try_va_to_rva(va) {
+			Ok(rva) => self.lookup(rva),
+			Err(_) => None,
+		}
+	}
+	// Finds the start of the function containing the rva, following chained unwind information to the primary entry.
+	fn function_begin(&self, rva: Rva) -> Option<Rva> {
+		let exceptions = match self.exceptions_ {
+			Some(ref exceptions) => exceptions,
+			None => return None,
+		};
+		let mut function = match exceptions.lookup_function_entry(rva) {
+			Some(function) => function,
+			None => return None,
+		};
+		for _ in 0..MAX_CHAINED {
+			match function.try_unwind_info().and_then(|unwind_info| unwind_info.try_chained()) {
+				Ok(parent) => function = parent,
+				Err(_) => break,
+			}
+		}
+		Some(function.image().BeginAddress)
+	}
+}
@@ -0,0 +1,760 @@
+//! PKCS#7 SignedData as used by Authenticode.
+//!
+//! A minimal DER decoder for the signatures found in the certificate table, see `pelite::security`.
+//! It extracts the signed digest of the image, the certificates, the signers, the signing time and nested signatures.
+//!
+//! Nothing is verified! This only tells what the signature claims, check the digest against `authenticode_hash`
+//! and use a proper cryptography library to verify the signature itself.
+
+use std::{fmt, str};
+
+use error::PeError;
+
+//----------------------------------------------------------------
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_BMP_STRING: u8 = 0x1E;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_CONTEXT_0: u8 = 0xA0;
+const TAG_CONTEXT_1: u8 = 0xA1;
+
+pub const OID_SIGNED_DATA: &'static [u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x02];
+pub const OID_SPC_INDIRECT_DATA: &'static [u8] = &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x04];
+pub const OID_SPC_NESTED_SIGNATURE: &'static [u8] = &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x04, 0x01];
+pub const OID_SPC_RFC3161_TIMESTAMP: &'static [u8] = &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x03, 0x03, 0x01];
+pub const OID_SIGNING_TIME: &'static [u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x05];
+pub const OID_COUNTER_SIGNATURE: &'static [u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x06];
+pub const OID_TST_INFO: &'static [u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x01, 0x04];
+pub const OID_MD5: &'static [u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x02, 0x05];
+pub const OID_SHA1: &'static [u8] = &[0x2B, 0x0E, 0x03, 0x02, 0x1A];
+pub const OID_SHA256: &'static [u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+pub const OID_SHA384: &'static [u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+pub const OID_SHA512: &'static [u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+pub const OID_COMMON_NAME: &'static [u8] = &[0x55, 0x04, 0x03];
+
+// Short names of the well known name attributes
+const NAME_ATTRIBUTES: &'static [(&'static [u8], &'static str)] = &[
+	(&[0x55, 0x04, 0x03], "CN"),
+	(&[0x55, 0x04, 0x06], "C"),
+	(&[0x55, 0x04, 0x07], "L"),
+	(&[0x55, 0x04, 0x08], "ST"),
+	(&[0x55, 0x04, 0x0A], "O"),
+	(&[0x55, 0x04, 0x0B], "OU"),
+	(&[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x01], "E"),
+];
+
+//----------------------------------------------------------------
+
+/// Reads DER encoded values one after another.
+#[derive(Copy, Clone)]
+struct Der<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+	fn new(data: &'a [u8]) -> Der<'a> {
+		Der { data: data }
+	}
+	fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+	fn peek_tag(&self) -> Option<u8> {
+		self.data.first().cloned()
+	}
+	// Reads the next value, returning its tag, contents and the whole encoding.
+	fn read(&mut self) -> Result<(u8, &'a [u8], &'a [u8]), PeError> {
+		let data = self.data;
+		if data.len() < 2 {
+			return Err(PeError::Corrupt);
+		}
+		let tag = data[0];
+		// Multi-byte tags are not used by anything decoded here
+		if tag & 0x1F == 0x1F {
+			return Err(PeError::Corrupt);
+		}
+		let (len, header) = match data[1] {
+			len @ 0x00...0x7F => (len as usize, 2),
+			// Indefinite lengths are not allowed in DER
+			len @ 0x81...0x84 => {
+				let count = (len & 0x7F) as usize;
+				let bytes = try!(data.get(2..2 + count).ok_or(PeError::Corrupt));
+				(bytes.iter().fold(0usize, |acc, &byte| acc << 8 | byte as usize), 2 + count)
+			},
+			_ => return Err(PeError::Corrupt),
+		};
+		let end = try!(header.checked_add(len).ok_or(PeError::Corrupt));
+		let raw = try!(data.get(..end).ok_or(PeError::Corrupt));
+		self.data = &data[end..];
+		Ok((tag, &raw[header..], raw))
+	}
+	// Reads the next value which must have the given tag.
+	fn expect(&mut self, tag: u8) -> Result<&'a [u8], PeError> {
+		let (actual, contents, _) = try!(self.read());
+		if actual != tag {
+			return Err(PeError::Corrupt);
+		}
+		Ok(contents)
+	}
+	// Reads the next value only if it has the given tag.
+	fn optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, PeError> {
+		if self.peek_tag() == Some(tag) { self.expect(tag).map(Some) }
+		else { Ok(None) }
+	}
+	// Skips the next value.
+	fn skip(&mut self) -> Result<(), PeError> {
+		self.read().map(|_| ())
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Object identifier, the raw DER encoded contents.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct Oid<'a>(pub &'a [u8]);
+
+impl<'a> fmt::Display for Oid<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut value = 0u64;
+		let mut first = true;
+		for &byte in self.0 {
+			value = value << 7 | (byte & 0x7F) as u64;
+			if byte & 0x80 == 0 {
+				if first {
+					// The first two components are packed together
+					let head = if value < 80 { value / 40 } else { 2 };
+					try!(write!(f, "{}.{}", head, value - head * 40));
+					first = false;
+				}
+				else {
+					try!(write!(f, ".{}", value));
+				}
+				value = 0;
+			}
+		}
+		Ok(())
+	}
+}
+
+impl<'a> fmt::Debug for Oid<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(self, f)
+	}
+}
+
+// Reads an AlgorithmIdentifier and returns its object identifier, the parameters are ignored.
+fn read_algorithm<'a>(der: &mut Der<'a>) -> Result<Oid<'a>, PeError> {
+	let mut alg = Der::new(try!(der.expect(TAG_SEQUENCE)));
+	Ok(Oid(try!(alg.expect(TAG_OID))))
+}
+
+//----------------------------------------------------------------
+
+/// UTC time.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Time {
+	pub year: u16,
+	pub month: u8,
+	pub day: u8,
+	pub hour: u8,
+	pub minute: u8,
+	pub second: u8,
+}
+
+impl Time {
+	fn parse(tag: u8, contents: &[u8]) -> Result<Time, PeError> {
+		let text = try!(str::from_utf8(contents));
+		let digits = |range: ::std::ops::Range<usize>| -> Result<u16, PeError> {
+			let part = try!(text.get(range).ok_or(PeError::Corrupt));
+			part.parse::<u16>().map_err(|_| PeError::Corrupt)
+		};
+		// Fractional seconds and the timezone are ignored, DER requires times in UTC
+		let (year, rest) = match tag {
+			TAG_UTC_TIME => {
+				let year = try!(digits(0..2));
+				(if year >= 50 { 1900 + year } else { 2000 + year }, 2)
+			},
+			TAG_GENERALIZED_TIME => (try!(digits(0..4)), 4),
+			_ => return Err(PeError::Corrupt),
+		};
+		Ok(Time {
+			year: year,
+			month: try!(digits(rest..rest + 2)) as u8,
+			day: try!(digits(rest + 2..rest + 4)) as u8,
+			hour: try!(digits(rest + 4..rest + 6)) as u8,
+			minute: try!(digits(rest + 6..rest + 8)) as u8,
+			second: try!(digits(rest + 8..rest + 10)) as u8,
+		})
+	}
+}
+
+impl fmt::Display for Time {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", self.year, self.month, self.day, self.hour, self.minute, self.second)
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Distinguished name of a certificate issuer or subject.
+#[derive(Copy, Clone)]
+pub struct Name<'a> {
+	raw: &'a [u8],
+	contents: &'a [u8],
+}
+
+impl<'a> Name<'a> {
+	fn read(der: &mut Der<'a>) -> Result<Name<'a>, PeError> {
+		let (tag, contents, raw) = try!(der.read());
+		if tag != TAG_SEQUENCE {
+			return Err(PeError::Corrupt);
+		}
+		Ok(Name { raw: raw, contents: contents })
+	}
+	/// Get the DER encoding, suitable for comparing names.
+	#[inline]
+	pub fn raw(&self) -> &'a [u8] {
+		self.raw
+	}
+	/// Iterate over the attributes.
+	#[inline]
+	pub fn iter(&self) -> NameIterator<'a> {
+		NameIterator {
+			rdns: Der::new(self.contents),
+			rdn: Der::new(&[]),
+		}
+	}
+	/// Get the common name.
+	pub fn common_name(&self) -> Option<String> {
+		self.iter().find(|attr| attr.oid().0 == OID_COMMON_NAME).map(|attr| attr.value())
+	}
+}
+
+impl<'a> fmt::Display for Name<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, attr) in self.iter().enumerate() {
+			if i != 0 {
+				try!(f.write_str(", "));
+			}
+			match NAME_ATTRIBUTES.iter().find(|&&(oid, _)| oid == attr.oid().0) {
+				Some(&(_, short)) => try!(write!(f, "{}={}", short, attr.value())),
+				None => try!(write!(f, "{}={}", attr.oid(), attr.value())),
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Name attribute.
+#[derive(Copy, Clone)]
+pub struct NameAttribute<'a> {
+	oid: Oid<'a>,
+	tag: u8,
+	raw_value: &'a [u8],
+}
+
+impl<'a> NameAttribute<'a> {
+	/// Get the attribute type.
+	#[inline]
+	pub fn oid(&self) -> Oid<'a> {
+		self.oid
+	}
+	/// Get the DER tag and contents of the value.
+	#[inline]
+	pub fn raw_value(&self) -> (u8, &'a [u8]) {
+		(self.tag, self.raw_value)
+	}
+	/// Get the value as a string, any invalid characters are replaced.
+	pub fn value(&self) -> String {
+		match self.tag {
+			TAG_BMP_STRING => {
+				let words: Vec<u16> = self.raw_value.chunks(2).map(|word| (word[0] as u16) << 8 | word.get(1).cloned().unwrap_or(0) as u16).collect();
+				String::from_utf16_lossy(&words)
+			},
+			// Printable, IA5 and UTF8 strings are all compatible with UTF-8
+			_ => String::from_utf8_lossy(self.raw_value).into_owned(),
+		}
+	}
+}
+
+pub struct NameIterator<'a> {
+	rdns: Der<'a>,
+	rdn: Der<'a>,
+}
+
+impl<'a> Iterator for NameIterator<'a> {
+	type Item = NameAttribute<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a> NameIterator<'a> {
+	/// Advances the iterator, returning an error instead of panicking if the name is corrupt.
+	pub fn try_next(&mut self) -> Result<Option<NameAttribute<'a>>, PeError> {
+		// Names are a sequence of sets of attributes
+		while self.rdn.is_empty() {
+			if self.rdns.is_empty() {
+				return Ok(None);
+			}
+			self.rdn = Der::new(try!(self.rdns.expect(TAG_SET)));
+		}
+		let mut attr = Der::new(try!(self.rdn.expect(TAG_SEQUENCE)));
+		let oid = Oid(try!(attr.expect(TAG_OID)));
+		let (tag, value, _) = try!(attr.read());
+		Ok(Some(NameAttribute {
+			oid: oid,
+			tag: tag,
+			raw_value: value,
+		}))
+	}
+}
+
+//----------------------------------------------------------------
+
+/// X.509 certificate.
+#[derive(Copy, Clone)]
+pub struct Certificate<'a> {
+	raw: &'a [u8],
+	serial: &'a [u8],
+	issuer: Name<'a>,
+	subject: Name<'a>,
+}
+
+impl<'a> Certificate<'a> {
+	fn parse(raw: &'a [u8], contents: &'a [u8]) -> Result<Certificate<'a>, PeError> {
+		let mut cert = Der::new(contents);
+		let mut tbs = Der::new(try!(cert.expect(TAG_SEQUENCE)));
+		// Version is optional
+		try!(tbs.optional(TAG_CONTEXT_0));
+		let serial = try!(tbs.expect(TAG_INTEGER));
+		try!(tbs.skip()); // signature algorithm
+		let issuer = try!(Name::read(&mut tbs));
+		try!(tbs.skip()); // validity
+		let subject = try!(Name::read(&mut tbs));
+		Ok(Certificate {
+			raw: raw,
+			serial: serial,
+			issuer: issuer,
+			subject: subject,
+		})
+	}
+	/// Get the DER encoded certificate.
+	#[inline]
+	pub fn raw(&self) -> &'a [u8] {
+		self.raw
+	}
+	/// Get the serial number as big endian bytes.
+	#[inline]
+	pub fn serial_number(&self) -> &'a [u8] {
+		self.serial
+	}
+	/// Get the issuer.
+	#[inline]
+	pub fn issuer(&self) -> Name<'a> {
+		self.issuer
+	}
+	/// Get the subject.
+	#[inline]
+	pub fn subject(&self) -> Name<'a> {
+		self.subject
+	}
+}
+
+pub struct CertificateIterator<'a> {
+	der: Der<'a>,
+}
+
+impl<'a> Iterator for CertificateIterator<'a> {
+	type Item = Certificate<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a> CertificateIterator<'a> {
+	/// Advances the iterator, returning an error instead of panicking if a certificate is corrupt.
+	pub fn try_next(&mut self) -> Result<Option<Certificate<'a>>, PeError> {
+		while !self.der.is_empty() {
+			let (tag, contents, raw) = try!(self.der.read());
+			// Skip other kinds of certificates
+			if tag == TAG_SEQUENCE {
+				return Certificate::parse(raw, contents).map(Some);
+			}
+		}
+		Ok(None)
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Signer information.
+#[derive(Copy, Clone)]
+pub struct SignerInfo<'a> {
+	issuer: Name<'a>,
+	serial: &'a [u8],
+	digest_algorithm: Oid<'a>,
+	authenticated: &'a [u8],
+	unauthenticated: &'a [u8],
+}
+
+impl<'a> SignerInfo<'a> {
+	fn parse(contents: &'a [u8]) -> Result<SignerInfo<'a>, PeError> {
+		let mut der = Der::new(contents);
+		try!(der.expect(TAG_INTEGER)); // version
+		let mut issuer_and_serial = Der::new(try!(der.expect(TAG_SEQUENCE)));
+		let issuer = try!(Name::read(&mut issuer_and_serial));
+		let serial = try!(issuer_and_serial.expect(TAG_INTEGER));
+		let digest_algorithm = try!(read_algorithm(&mut der));
+		let authenticated = try!(der.optional(TAG_CONTEXT_0)).unwrap_or(&[]);
+		try!(der.skip()); // digest encryption algorithm
+		try!(der.expect(TAG_OCTET_STRING)); // encrypted digest
+		let unauthenticated = try!(der.optional(TAG_CONTEXT_1)).unwrap_or(&[]);
+		Ok(SignerInfo {
+			issuer: issuer,
+			serial: serial,
+			digest_algorithm: digest_algorithm,
+			authenticated: authenticated,
+			unauthenticated: unauthenticated,
+		})
+	}
+	/// Get the issuer of the signer certificate.
+	#[inline]
+	pub fn issuer(&self) -> Name<'a> {
+		self.issuer
+	}
+	/// Get the serial number of the signer certificate.
+	#[inline]
+	pub fn serial_number(&self) -> &'a [u8] {
+		self.serial
+	}
+	/// Get the digest algorithm used for the signed attributes.
+	#[inline]
+	pub fn digest_algorithm(&self) -> Oid<'a> {
+		self.digest_algorithm
+	}
+	/// Iterate over the authenticated attributes.
+	#[inline]
+	pub fn authenticated_attributes(&self) -> AttributeIterator<'a> {
+		AttributeIterator { der: Der::new(self.authenticated) }
+	}
+	/// Iterate over the unauthenticated attributes.
+	#[inline]
+	pub fn unauthenticated_attributes(&self) -> AttributeIterator<'a> {
+		AttributeIterator { der: Der::new(self.unauthenticated) }
+	}
+	/// Get the signing time.
+	///
+	/// Looks at the signing time attribute, then the countersignature and finally the RFC 3161 timestamp.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if the signature has no signing time.
+	pub fn try_signing_time(&self) -> Result<Time, PeError> {
+		// Signing time claimed by the signer itself
+		let mut it = self.authenticated_attributes();
+		while let Some(attr) = try!(it.try_next()) {
+			if attr.oid().0 == OID_SIGNING_TIME {
+				let (tag, contents, _) = try!(Der::new(attr.values()).read());
+				return Time::parse(tag, contents);
+			}
+		}
+		// Timestamped by a time stamping authority
+		let mut it = self.unauthenticated_attributes();
+		while let Some(attr) = try!(it.try_next()) {
+			if attr.oid().0 == OID_COUNTER_SIGNATURE {
+				let counter = try!(SignerInfo::parse(try!(Der::new(attr.values()).expect(TAG_SEQUENCE))));
+				return counter.try_signing_time();
+			}
+			if attr.oid().0 == OID_SPC_RFC3161_TIMESTAMP {
+				let token = try!(Content::parse(try!(Der::new(attr.values()).expect(TAG_SEQUENCE))));
+				if token.content_type.0 != OID_TST_INFO {
+					return Err(PeError::BadMagic);
+				}
+				// The TSTInfo is wrapped in an octet string
+				let mut tst_info = Der::new(try!(Der::new(try!(Der::new(token.content).expect(TAG_OCTET_STRING))).expect(TAG_SEQUENCE)));
+				try!(tst_info.expect(TAG_INTEGER)); // version
+				try!(tst_info.expect(TAG_OID)); // policy
+				try!(tst_info.expect(TAG_SEQUENCE)); // message imprint
+				try!(tst_info.expect(TAG_INTEGER)); // serial number
+				return Time::parse(TAG_GENERALIZED_TIME, try!(tst_info.expect(TAG_GENERALIZED_TIME)));
+			}
+		}
+		Err(PeError::Null)
+	}
+}
+
+/// Signed attribute.
+#[derive(Copy, Clone)]
+pub struct Attribute<'a> {
+	oid: Oid<'a>,
+	values: &'a [u8],
+}
+
+impl<'a> Attribute<'a> {
+	/// Get the attribute type.
+	#[inline]
+	pub fn oid(&self) -> Oid<'a> {
+		self.oid
+	}
+	/// Get the DER encoded values, the contents of a set.
+	#[inline]
+	pub fn values(&self) -> &'a [u8] {
+		self.values
+	}
+}
+
+pub struct AttributeIterator<'a> {
+	der: Der<'a>,
+}
+
+impl<'a> Iterator for AttributeIterator<'a> {
+	type Item = Attribute<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a> AttributeIterator<'a> {
+	/// Advances the iterator, returning an error instead of panicking if an attribute is corrupt.
+	pub fn try_next(&mut self) -> Result<Option<Attribute<'a>>, PeError> {
+		if self.der.is_empty() {
+			return Ok(None);
+		}
+		let mut attr = Der::new(try!(self.der.expect(TAG_SEQUENCE)));
+		let oid = Oid(try!(attr.expect(TAG_OID)));
+		let values = try!(attr.expect(TAG_SET));
+		Ok(Some(Attribute {
+			oid: oid,
+			values: values,
+		}))
+	}
+}
+
+pub struct SignerIterator<'a> {
+	der: Der<'a>,
+}
+
+impl<'a> Iterator for SignerIterator<'a> {
+	type Item = SignerInfo<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a> SignerIterator<'a> {
+	/// Advances the iterator, returning an error instead of panicking if a signer is corrupt.
+	pub fn try_next(&mut self) -> Result<Option<SignerInfo<'a>>, PeError> {
+		if self.der.is_empty() {
+			return Ok(None);
+		}
+		SignerInfo::parse(try!(self.der.expect(TAG_SEQUENCE))).map(Some)
+	}
+}
+
+//----------------------------------------------------------------
+
+// ContentInfo wrapping a SignedData.
+struct Content<'a> {
+	content_type: Oid<'a>,
+	content: &'a [u8],
+	certificates: &'a [u8],
+	signer_infos: &'a [u8],
+}
+
+impl<'a> Content<'a> {
+	fn parse(contents: &'a [u8]) -> Result<Content<'a>, PeError> {
+		let mut content_info = Der::new(contents);
+		if try!(content_info.expect(TAG_OID)) != OID_SIGNED_DATA {
+			return Err(PeError::BadMagic);
+		}
+		let mut signed_data = Der::new(try!(Der::new(try!(content_info.expect(TAG_CONTEXT_0))).expect(TAG_SEQUENCE)));
+		try!(signed_data.expect(TAG_INTEGER)); // version
+		try!(signed_data.expect(TAG_SET)); // digest algorithms
+		let mut encap = Der::new(try!(signed_data.expect(TAG_SEQUENCE)));
+		let content_type = Oid(try!(encap.expect(TAG_OID)));
+		let content = try!(encap.expect(TAG_CONTEXT_0));
+		let certificates = try!(signed_data.optional(TAG_CONTEXT_0)).unwrap_or(&[]);
+		try!(signed_data.optional(TAG_CONTEXT_1)); // crls
+		let signer_infos = try!(signed_data.expect(TAG_SET));
+		Ok(Content {
+			content_type: content_type,
+			content: content,
+			certificates: certificates,
+			signer_infos: signer_infos,
+		})
+	}
+}
+
+/// Authenticode signature.
+pub struct SignedData<'a> {
+	raw: &'a [u8],
+	content: Content<'a>,
+	digest_algorithm: Oid<'a>,
+	digest: &'a [u8],
+}
+
+impl<'a> SignedData<'a> {
+	/// Decode an Authenticode signature.
+	///
+	/// # Parameters
+	///
+	/// * `der`
+	///
+	///   DER encoded PKCS#7 ContentInfo, eg. the data of a `WIN_CERT_TYPE_PKCS_SIGNED_DATA` certificate.
+	///   Any trailing padding is ignored.
+	///
+	/// # Return value
+	///
+	/// `PeError::BadMagic` if this is not SignedData with SpcIndirectDataContent.
+	///
+	/// `PeError::Corrupt` if the DER encoding is invalid.
+	pub fn parse(der: &'a [u8]) -> Result<SignedData<'a>, PeError> {
+		let (tag, contents, raw) = try!(Der::new(der).read());
+		if tag != TAG_SEQUENCE {
+			return Err(PeError::Corrupt);
+		}
+		let content = try!(Content::parse(contents));
+		if content.content_type.0 != OID_SPC_INDIRECT_DATA {
+			return Err(PeError::BadMagic);
+		}
+		let mut spc = Der::new(try!(Der::new(content.content).expect(TAG_SEQUENCE)));
+		try!(spc.expect(TAG_SEQUENCE)); // data
+		let mut digest_info = Der::new(try!(spc.expect(TAG_SEQUENCE)));
+		let digest_algorithm = try!(read_algorithm(&mut digest_info));
+		let digest = try!(digest_info.expect(TAG_OCTET_STRING));
+		Ok(SignedData {
+			raw: raw,
+			content: content,
+			digest_algorithm: digest_algorithm,
+			digest: digest,
+		})
+	}
+	/// Get the DER encoding without trailing padding.
+	#[inline]
+	pub fn raw(&self) -> &'a [u8] {
+		self.raw
+	}
+	/// Get the algorithm of the Authenticode digest, eg. `OID_SHA1` or `OID_SHA256`.
+	#[inline]
+	pub fn digest_algorithm(&self) -> Oid<'a> {
+		self.digest_algorithm
+	}
+	/// Get the expected Authenticode digest of the image.
+	#[inline]
+	pub fn digest(&self) -> &'a [u8] {
+		self.digest
+	}
+	/// Iterate over the included certificates.
+	#[inline]
+	pub fn certificates(&self) -> CertificateIterator<'a> {
+		CertificateIterator { der: Der::new(self.content.certificates) }
+	}
+	/// Iterate over the signers, Authenticode requires exactly one.
+	#[inline]
+	pub fn signers(&self) -> SignerIterator<'a> {
+		SignerIterator { der: Der::new(self.content.signer_infos) }
+	}
+	/// Get the signer.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if there are no signers.
+	#[inline]
+	pub fn try_signer(&self) -> Result<SignerInfo<'a>, PeError> {
+		try!(self.signers().try_next()).ok_or(PeError::Null)
+	}
+	/// Get the certificate of the signer.
+	///
+	/// # Return value
+	///
+	/// `PeError::Null` if there are no signers or the certificate is not included.
+	pub fn try_signer_certificate(&self) -> Result<Certificate<'a>, PeError> {
+		let signer = try!(self.try_signer());
+		let mut it = self.certificates();
+		while let Some(cert) = try!(it.try_next()) {
+			if cert.serial_number() == signer.serial_number() && cert.issuer().raw() == signer.issuer().raw() {
+				return Ok(cert);
+			}
+		}
+		Err(PeError::Null)
+	}
+	/// Get the signing time of the signer.
+	///
+	/// See `SignerInfo::try_signing_time` for more information.
+	#[inline]
+	pub fn try_signing_time(&self) -> Result<Time, PeError> {
+		try!(self.try_signer()).try_signing_time()
+	}
+	/// Iterate over the nested signatures.
+	///
+	/// Images with multiple signatures store the extra ones as unauthenticated attributes of the first.
+	pub fn nested(&self) -> NestedIterator<'a> {
+		NestedIterator {
+			attributes: match self.try_signer() {
+				Ok(signer) => signer.unauthenticated_attributes(),
+				Err(_) => AttributeIterator { der: Der::new(&[]) },
+			},
+			values: Der::new(&[]),
+		}
+	}
+}
+
+impl<'a> fmt::Display for SignedData<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(write!(f, "SignedData {} ", self.digest_algorithm));
+		for byte in self.digest {
+			try!(write!(f, "{:02X}", byte));
+		}
+		try!(writeln!(f, ""));
+		if let Ok(cert) = self.try_signer_certificate() {
+			try!(writeln!(f, "  Signer:  {}", cert.subject()));
+			try!(writeln!(f, "  Issuer:  {}", cert.issuer()));
+		}
+		if let Ok(time) = self.try_signing_time() {
+			try!(writeln!(f, "  Signed:  {}", time));
+		}
+		for nested in self.nested() {
+			try!(write!(f, "{}", nested));
+		}
+		Ok(())
+	}
+}
+
+pub struct NestedIterator<'a> {
+	attributes: AttributeIterator<'a>,
+	values: Der<'a>,
+}
+
+impl<'a> Iterator for NestedIterator<'a> {
+	type Item = SignedData<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a> NestedIterator<'a> {
+	/// Advances the iterator, returning an error instead of panicking if a nested signature is corrupt.
+	pub fn try_next(&mut self) -> Result<Option<SignedData<'a>>, PeError> {
+		// Every nested signature attribute may contain multiple signatures
+		while self.values.is_empty() {
+			match try!(self.attributes.try_next()) {
+				Some(attr) => if attr.oid().0 == OID_SPC_NESTED_SIGNATURE {
+					self.values = Der::new(attr.values());
+				},
+				None => return Ok(None),
+			}
+		}
+		let (_, _, raw) = try!(self.values.read());
+		SignedData::parse(raw).map(Some)
+	}
+}
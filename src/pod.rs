@@ -0,0 +1,39 @@
+//! Plain old data.
+
+/// Types that can be safely reinterpreted from the bytes of an image.
+///
+/// Reading a structure from an image casts a pointer to the underlying bytes, no data is copied or validated.
+/// This is only sound for types without references, pointers or invalid bit patterns (eg. `bool`, `char` or enums).
+///
+/// Implemented for the primitive integers, arrays thereof and every structure in the `image` modules.
+///
+/// # Safety
+///
+/// Implement this for your own `#[repr(C)]` structures with care, every bit pattern must be a valid instance:
+///
+/// ```
+/// # #![allow(non_snake_case)]
+/// #[repr(C)]
+/// struct MyStruct {
+/// 	pub Magic: u32,
+/// 	pub Data: [u8; 12],
+/// }
+/// unsafe impl pelite::Pod for MyStruct {}
+/// ```
+pub unsafe trait Pod: 'static {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+
+macro_rules! impl_pod_arrays {
+	($($len:expr)*) => {
+		$(unsafe impl<T: Pod> Pod for [T; $len] {})*
+	};
+}
+impl_pod_arrays!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 64 128 256);
@@ -7,6 +7,8 @@ use std::{slice, fmt, mem};
 use std::fmt::Write;
 
 use super::image::*;
+use error::PeError;
+use pod::Pod;
 
 //----------------------------------------------------------------
 
@@ -50,18 +52,25 @@ impl<'a> Resources<'a> {
 			image_: ROOT_ENTRY,
 		}
 	}
-	fn read_slice(&self, off: usize, len: usize) -> &[u8] {
-		// Panics on invalid input; this is desired behaviour as it indicates corruption
-		&self.data[off .. off + len]
+	fn try_read_slice(&self, off: usize, len: usize) -> Result<&[u8], PeError> {
+		let end = try!(off.checked_add(len).ok_or(PeError::Bounds));
+		self.data.get(off..end).ok_or(PeError::Bounds)
 	}
-	fn read_str(&self, off: usize) -> &[u16] {
+	fn try_read_str(&self, off: usize) -> Result<&[u16], PeError> {
 		// Reads the resource names which are utf16
-		let words = *self.read::<u16>(off) as usize;
-		let nameptr = self.read_slice(off + 2, words * 2).as_ptr() as *const u16;
-		unsafe { slice::from_raw_parts(nameptr, words) }
+		let words = *try!(self.try_read::<u16>(off)) as usize;
+		let nameptr = try!(self.try_read_slice(off + 2, words * 2)).as_ptr() as *const u16;
+		if nameptr as usize % mem::align_of::<u16>() != 0 {
+			return Err(PeError::Misaligned);
+		}
+		Ok(unsafe { slice::from_raw_parts(nameptr, words) })
 	}
-	fn read<T>(&self, off: usize) -> &T {
-		unsafe { &*(self.read_slice(off, mem::size_of::<T>()).as_ptr() as *const _) }
+	fn try_read<T: Pod>(&self, off: usize) -> Result<&T, PeError> {
+		let ptr = try!(self.try_read_slice(off, mem::size_of::<T>())).as_ptr();
+		if ptr as usize % mem::align_of::<T>() != 0 {
+			return Err(PeError::Misaligned);
+		}
+		Ok(unsafe { &*(ptr as *const T) })
 	}
 }
 
@@ -120,64 +129,96 @@ impl<'a> ResourceDirectoryEntry<'a> {
 		&self.image_
 	}
 	/// Get the name for this entry.
-	pub fn name(&self) -> ResourceName {
+	pub fn try_name(&self) -> Result<ResourceName, PeError> {
 		if self.image_.Name & 0x80000000 != 0 {
 			let offset = (self.image_.Name & !0x80000000) as usize;
-			let name = self.resrc_.read_str(offset);
-			ResourceName::Name(name)
+			let name = try!(self.resrc_.try_read_str(offset));
+			Ok(ResourceName::Name(name))
 		}
 		else {
-			ResourceName::Id((self.image_.Name & 0xFFFF) as u16)
+			Ok(ResourceName::Id((self.image_.Name & 0xFFFF) as u16))
 		}
 	}
+	/// Get the name for this entry.
+	///
+	/// Convenience wrapper around `try_name`, panics if the name is corrupt.
+	#[inline]
+	pub fn name(&self) -> ResourceName {
+		self.try_name().unwrap()
+	}
 	/// Is this entry a subdirectory?
 	#[inline]
 	pub fn is_dir(&self) -> bool {
 		self.image_.Offset & 0x80000000 != 0
 	}
 	/// Interpret this entry as a subdirectory.
-	pub fn as_dir(&self) -> Option<ResourceDirectory> {
+	///
+	/// `Ok(None)` if this entry isn't a subdirectory.
+	pub fn try_as_dir(&self) -> Result<Option<ResourceDirectory>, PeError> {
 		if self.is_dir() {
 			let offset = (self.image_.Offset & !0x80000000) as usize;
 			// Ensures there's at least enough to read the directory image
-			let image = self.resrc_.read::<ImageResourceDirectory>(offset);
+			let image = try!(self.resrc_.try_read::<ImageResourceDirectory>(offset));
 			// Ensures the entire directory image and its entries can be read
 			let bytes = mem::size_of::<ImageResourceDirectory>() + (image.NumberOfNamedEntries as usize + image.NumberOfIdEntries as usize) * mem::size_of::<ImageResourceDirectoryEntry>();
-			let image = unsafe { &*(self.resrc_.read_slice(offset, bytes).as_ptr() as *const ImageResourceDirectory) };
+			try!(self.resrc_.try_read_slice(offset, bytes));
 			// This is a valid directory contained within the resources
-			Some(ResourceDirectory {
+			Ok(Some(ResourceDirectory {
 				entry_: self,
 				image_: image,
-			})
+			}))
 		}
 		else {
-			None
+			Ok(None)
 		}
 	}
+	/// Interpret this entry as a subdirectory.
+	///
+	/// Convenience wrapper around `try_as_dir`, panics if the directory is corrupt.
+	#[inline]
+	pub fn as_dir(&self) -> Option<ResourceDirectory> {
+		self.try_as_dir().unwrap()
+	}
 	/// Interpret this entry as a data entry.
-	pub fn as_data(&self) -> Option<ResourceDataEntry> {
+	///
+	/// `Ok(None)` if this entry isn't a data entry.
+	pub fn try_as_data(&self) -> Result<Option<ResourceDataEntry>, PeError> {
 		if !self.is_dir() {
 			let offset = self.image_.Offset as usize;
-			let image = self.resrc_.read::<ImageResourceDataEntry>(offset);
-			Some(ResourceDataEntry {
+			let image = try!(self.resrc_.try_read::<ImageResourceDataEntry>(offset));
+			Ok(Some(ResourceDataEntry {
 				entry_: self,
 				image_: image,
-			})
+			}))
 		}
 		else {
-			None
+			Ok(None)
 		}
 	}
+	/// Interpret this entry as a data entry.
+	///
+	/// Convenience wrapper around `try_as_data`, panics if the data entry is corrupt.
+	#[inline]
+	pub fn as_data(&self) -> Option<ResourceDataEntry> {
+		self.try_as_data().unwrap()
+	}
 	/// Get the entry as either subdirectory or data entry.
-	pub fn entry(&self) -> ResourceEntry {
+	pub fn try_entry(&self) -> Result<ResourceEntry, PeError> {
 		// These unwrap()s should get optimized out.
 		if self.is_dir() {
-			ResourceEntry::Directory(self.as_dir().unwrap())
+			Ok(ResourceEntry::Directory(try!(self.try_as_dir()).unwrap()))
 		}
 		else {
-			ResourceEntry::DataEntry(self.as_data().unwrap())
+			Ok(ResourceEntry::DataEntry(try!(self.try_as_data()).unwrap()))
 		}
 	}
+	/// Get the entry as either subdirectory or data entry.
+	///
+	/// Convenience wrapper around `try_entry`, panics if the entry is corrupt.
+	#[inline]
+	pub fn entry(&self) -> ResourceEntry {
+		self.try_entry().unwrap()
+	}
 }
 
 impl<'a> fmt::Display for ResourceDirectoryEntry<'a> {
@@ -238,6 +279,18 @@ impl<'a> ResourceDirectory<'a> {
 		self.image_
 	}
 	/// Find a child entry by name. Not very efficient due to String conversions...
+	///
+	/// `PeError` if the name of any child entry before the match is corrupt.
+	pub fn try_find(&self, name: &str) -> Result<Option<ResourceDirectoryEntry>, PeError> {
+		for e in self.iter() {
+			// Allocates a new String for every compare :(
+			if format!("{}", try!(e.try_name())) == name {
+				return Ok(Some(e));
+			}
+		}
+		Ok(None)
+	}
+	/// Find a child entry by name. Not very efficient due to String conversions...
 	pub fn find(&self, name: &str) -> Option<ResourceDirectoryEntry> {
 		self.iter().find(|e| {
 			// Allocates a new String for every compare :(
@@ -318,9 +371,16 @@ impl<'a> ResourceDataEntry<'a> {
 		self.image_
 	}
 	/// Get the resource data as a byte slice.
+	pub fn try_data(&self) -> Result<&[u8], PeError> {
+		let offset = try!((self.image_.OffsetToData as usize).checked_sub(self.entry_.resrc_.vbase as usize).ok_or(PeError::Bounds));
+		self.entry_.resrc_.try_read_slice(offset, self.image_.Size as usize)
+	}
+	/// Get the resource data as a byte slice.
+	///
+	/// Convenience wrapper around `try_data`, panics if the data is out of bounds.
+	#[inline]
 	pub fn data(&self) -> &[u8] {
-		let offset = self.image_.OffsetToData as usize - self.entry_.resrc_.vbase as usize;
-		self.entry_.resrc_.read_slice(offset, self.image_.Size as usize)
+		self.try_data().unwrap()
 	}
 }
 
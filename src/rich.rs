@@ -0,0 +1,225 @@
+//! Rich header.
+//!
+//! The Microsoft linker hides this undocumented structure in the DOS stub, between the DOS header and the NT headers.
+//! It records every tool (compiler, assembler, linker, ...) that produced the object files linked into the image,
+//! identified by a product id and build number along with how many objects it produced.
+//!
+//! The structure is XOR masked with a checksum of the DOS header, the DOS stub and the entries themselves:
+//!
+//! ```text
+//! "DanS" ^ key, 0 ^ key, 0 ^ key, 0 ^ key
+//! (prod_id << 16 | build) ^ key, count ^ key
+//! ...
+//! "Rich", key
+//! ```
+
+use std::{cmp, fmt, mem};
+
+use image::*;
+use error::PeError;
+
+//----------------------------------------------------------------
+
+const DANS_MAGIC: u32 = 0x536E6144;
+const RICH_MAGIC: u32 = 0x68636952;
+
+/// Product id of the entry counting the functions imported through old import libraries without their own Rich information.
+pub const PROD_ID_IMPORT0: u16 = 0x0001;
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+	bytes[offset] as u32 | (bytes[offset + 1] as u32) << 8 | (bytes[offset + 2] as u32) << 16 | (bytes[offset + 3] as u32) << 24
+}
+
+//----------------------------------------------------------------
+
+/// Rich header.
+#[derive(Copy, Clone)]
+pub struct RichHeader<'a> {
+	image: &'a [u8],
+	offset: usize,
+	entries: &'a [u8],
+	key: u32,
+}
+
+impl<'a> RichHeader<'a> {
+	/// Find and decode the Rich header.
+	///
+	/// # Parameters
+	///
+	/// * `image`
+	///
+	///   The bytes starting at the DOS header, eg. the `image()` of any PE backend.
+	///
+	/// # Return value
+	///
+	/// `PeError::Bounds` if the image is too small for the DOS header.
+	///
+	/// `PeError::BadMagic` if the DOS header magic does not match.
+	///
+	/// `PeError::Null` if there is no Rich header, eg. the image was not linked by a Microsoft linker.
+	///
+	/// `PeError::Corrupt` if the start of the Rich header could not be found.
+	///
+	/// The stored checksum is not verified, see `verify_checksum`.
+	pub fn parse(image: &'a [u8]) -> Result<RichHeader<'a>, PeError> {
+		if image.len() < mem::size_of::<ImageDosHeader>() {
+			return Err(PeError::Bounds);
+		}
+		// This is safe since the length was checked and the struct is packed
+		let dos = unsafe { &*(image.as_ptr() as *const ImageDosHeader) };
+		if dos.e_magic != IMAGE_DOS_HEADER_MAGIC {
+			return Err(PeError::BadMagic);
+		}
+		// Only the DOS stub is searched
+		let stub_begin = mem::size_of::<ImageDosHeader>();
+		let stub_end = cmp::min(dos.e_lfanew as usize, image.len());
+		// The Rich marker ends the structure, followed by the key
+		let mut rich = stub_begin;
+		loop {
+			if rich + 8 > stub_end {
+				return Err(PeError::Null);
+			}
+			if read_u32(image, rich) == RICH_MAGIC {
+				break;
+			}
+			rich += 4;
+		}
+		let key = read_u32(image, rich + 4);
+		// Walk backwards to find the masked DanS marker
+		let mut offset = rich;
+		loop {
+			if offset < stub_begin + 4 {
+				return Err(PeError::Corrupt);
+			}
+			offset -= 4;
+			if read_u32(image, offset) ^ key == DANS_MAGIC {
+				break;
+			}
+		}
+		// The DanS marker is followed by three padding dwords, then the entries
+		let entries = try!(image.get(offset + 16..rich).ok_or(PeError::Corrupt));
+		if entries.len() % 8 != 0 {
+			return Err(PeError::Corrupt);
+		}
+		Ok(RichHeader {
+			image: image,
+			offset: offset,
+			entries: entries,
+			key: key,
+		})
+	}
+	/// Get the file offset of the start of the Rich header.
+	#[inline]
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+	/// Get the XOR key, which is also the stored checksum.
+	#[inline]
+	pub fn key(&self) -> u32 {
+		self.key
+	}
+	/// Compute the checksum of the DOS header, the DOS stub and the entries.
+	///
+	/// The `e_lfanew` field is excluded as it is filled in after the Rich header is generated.
+	pub fn checksum(&self) -> u32 {
+		let mut checksum = self.offset as u32;
+		for (i, &byte) in self.image[..self.offset].iter().enumerate() {
+			if i >= 0x3C && i < 0x40 {
+				continue;
+			}
+			checksum = checksum.wrapping_add((byte as u32).rotate_left(i as u32));
+		}
+		for entry in self.iter() {
+			let comp_id = (entry.prod_id as u32) << 16 | entry.build as u32;
+			checksum = checksum.wrapping_add(comp_id.rotate_left(entry.count));
+		}
+		checksum
+	}
+	/// Check whether the stored checksum matches.
+	///
+	/// A mismatch means the DOS stub or the Rich header was tampered with after linking.
+	#[inline]
+	pub fn verify_checksum(&self) -> bool {
+		self.checksum() == self.key
+	}
+	/// Iterate over the entries.
+	#[inline]
+	pub fn iter(&self) -> RichIterator<'a> {
+		RichIterator {
+			entries: self.entries,
+			key: self.key,
+		}
+	}
+}
+
+impl<'a> fmt::Display for RichHeader<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "RichHeader Key {:08X} Checksum {:08X}", self.key, self.checksum()));
+		for entry in self.iter() {
+			try!(writeln!(f, "  ProdId {:>04X} Build {:>5} Count {:>4} {}", entry.prod_id, entry.build, entry.count, entry.vs_version().unwrap_or("")));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+pub struct RichIterator<'a> {
+	entries: &'a [u8],
+	key: u32,
+}
+
+impl<'a> Iterator for RichIterator<'a> {
+	type Item = RichEntry;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.entries.len() < 8 {
+			return None;
+		}
+		let comp_id = read_u32(self.entries, 0) ^ self.key;
+		let count = read_u32(self.entries, 4) ^ self.key;
+		self.entries = &self.entries[8..];
+		Some(RichEntry {
+			prod_id: (comp_id >> 16) as u16,
+			build: comp_id as u16,
+			count: count,
+		})
+	}
+}
+
+/// Rich header entry.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RichEntry {
+	/// Identifies the tool and the language, eg. C or C++ compiler, linker, assembler.
+	pub prod_id: u16,
+	/// Build number of the tool.
+	pub build: u16,
+	/// Number of objects produced by this tool, or the number of imports for `PROD_ID_IMPORT0`.
+	pub count: u32,
+}
+
+impl RichEntry {
+	/// Get the Visual Studio release the tool shipped with.
+	///
+	/// Product ids are assigned in blocks for every toolset, the releases sharing the v14x toolset (2015 and later) are told apart by build number.
+	pub fn vs_version(&self) -> Option<&'static str> {
+		let version = match self.prod_id {
+			0x0002...0x0018 => "Visual Studio 97 / 6.0",
+			0x0019...0x0045 => "Visual Studio .NET 2002",
+			0x0046...0x006C => "Visual Studio .NET 2003",
+			0x006D...0x0082 => "Visual Studio 2005",
+			0x0083...0x0097 => "Visual Studio 2008",
+			0x0098...0x00B4 => "Visual Studio 2010",
+			0x00B5...0x00C6 => "Visual Studio 2012",
+			0x00C7...0x00FC => "Visual Studio 2013",
+			0x00FD...0x01FF => match self.build {
+				0...24999 => "Visual Studio 2015",
+				25000...27499 => "Visual Studio 2017",
+				27500...30699 => "Visual Studio 2019",
+				_ => "Visual Studio 2022",
+			},
+			_ => return None,
+		};
+		Some(version)
+	}
+}
@@ -0,0 +1,155 @@
+//! Authenticode certificate table.
+//!
+//! The security directory is the only directory pointed to by a file offset instead of an Rva.
+//! It is not part of any section and never mapped by the loader, only backends with access to the file on disk can find it.
+//!
+//! For more information see [Windows Authenticode Portable Executable Signature Format](http://download.microsoft.com/download/9/c/5/9c5b2167-8017-4bae-9fde-d599bac8184a/Authenticode_PE.docx).
+
+use std::{fmt, mem};
+
+use super::image::*;
+use error::PeError;
+
+//----------------------------------------------------------------
+
+/// Certificate table.
+pub struct Security<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> Security<'a> {
+	/// Interpret memory as a certificate table.
+	///
+	/// # Parameters
+	///
+	/// * `data`
+	///
+	///   The bytes pointed to by the security data directory.
+	///
+	/// # Remarks
+	///
+	/// No validation is done ahead of time.
+	#[inline]
+	pub fn new(data: &'a [u8]) -> Security<'a> {
+		Security {
+			data: data,
+		}
+	}
+	/// Get the raw certificate table.
+	#[inline]
+	pub fn data(&self) -> &'a [u8] {
+		self.data
+	}
+	/// Iterate over the certificates.
+	#[inline]
+	pub fn iter(&self) -> CertificateIterator<'a> {
+		CertificateIterator {
+			data: self.data,
+		}
+	}
+}
+
+impl<'a> fmt::Display for Security<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		try!(writeln!(f, "Security"));
+		for cert in self.iter() {
+			try!(writeln!(f, "  Revision {:>04X} Type {} Length {}", cert.revision(), cert.certificate_type(), cert.data().len()));
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Receives the bytes covered by the Authenticode signature.
+///
+/// Implement this for the hash algorithm of the signature, typically SHA-1 or SHA-256.
+/// This keeps this library free of any cryptography dependencies.
+///
+/// Implemented for closures taking the bytes to hash.
+pub trait AuthenticodeHasher {
+	/// Hash the next chunk of bytes.
+	fn update(&mut self, bytes: &[u8]);
+}
+
+impl<F: FnMut(&[u8])> AuthenticodeHasher for F {
+	fn update(&mut self, bytes: &[u8]) {
+		self(bytes)
+	}
+}
+
+//----------------------------------------------------------------
+
+pub struct CertificateIterator<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> Iterator for CertificateIterator<'a> {
+	type Item = Certificate<'a>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.try_next().unwrap()
+	}
+}
+
+impl<'a> CertificateIterator<'a> {
+	/// Advances the iterator, returning an error instead of panicking if the certificate table is corrupt.
+	///
+	/// The iterator is not advanced on error.
+	pub fn try_next(&mut self) -> Result<Option<Certificate<'a>>, PeError> {
+		if self.data.is_empty() {
+			return Ok(None);
+		}
+		if self.data.len() < mem::size_of::<WinCertificate>() {
+			return Err(PeError::Bounds);
+		}
+		// This is safe since the length was checked and the struct is packed
+		let image = unsafe { &*(self.data.as_ptr() as *const WinCertificate) };
+		let len = image.Length as usize;
+		if len < mem::size_of::<WinCertificate>() {
+			return Err(PeError::Corrupt);
+		}
+		let data = try!(self.data.get(mem::size_of::<WinCertificate>()..len).ok_or(PeError::Bounds));
+		// Entries are aligned to 8 bytes, the last one may omit its padding
+		let next = (len + 7) & !7;
+		self.data = self.data.get(next..).unwrap_or(&[]);
+		Ok(Some(Certificate {
+			image_: image,
+			data_: data,
+		}))
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Certificate table entry.
+#[derive(Copy, Clone)]
+pub struct Certificate<'a> {
+	image_: &'a WinCertificate,
+	data_: &'a [u8],
+}
+
+impl<'a> Certificate<'a> {
+	/// Get the underlying certificate header image.
+	#[inline]
+	pub fn image(&self) -> &'a WinCertificate {
+		self.image_
+	}
+	/// Get the revision, see the `WIN_CERT_REVISION_*` constants.
+	#[inline]
+	pub fn revision(&self) -> u16 {
+		self.image_.Revision
+	}
+	/// Get the certificate type, see the `WIN_CERT_TYPE_*` constants.
+	#[inline]
+	pub fn certificate_type(&self) -> u16 {
+		self.image_.CertificateType
+	}
+	/// Get the certificate data.
+	///
+	/// For `WIN_CERT_TYPE_PKCS_SIGNED_DATA` this is a DER encoded PKCS#7 SignedData blob.
+	#[inline]
+	pub fn data(&self) -> &'a [u8] {
+		self.data_
+	}
+}
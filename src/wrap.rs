@@ -0,0 +1,437 @@
+//! Bitness agnostic wrappers.
+//!
+//! When you don't know ahead of time whether a binary is PE32 or PE64, open it through `PeFile` here.
+//! The optional header magic is inspected and the appropriate `pe32` or `pe64` implementation is selected.
+//!
+//! The wrappers forward to their respective implementations, results that depend on the bitness are themselves wrapped.
+
+use std::path::Path;
+use std::fs::File;
+use std::{io, fmt, mem};
+use std::io::{Seek, SeekFrom, Read};
+use std::ops::Range;
+
+use image::*;
+use error::{PeError, unwrap_null};
+use resources::Resources;
+use security::Security;
+use pe32;
+use pe64;
+use pe32::pe::Pe as Pe32;
+use pe32::exports::PeExports as PeExports32;
+use pe32::imports::PeImports as PeImports32;
+use pe32::delay_imports::PeDelayImports as PeDelayImports32;
+use pe32::bound_imports::PeBoundImports as PeBoundImports32;
+use pe32::security::PeSecurity as PeSecurity32;
+use pe32::relocs::PeRelocs as PeRelocs32;
+use pe32::resources::PeResources as PeResources32;
+use pe32::debug::PeDebug as PeDebug32;
+use pe32::tls::PeTls as PeTls32;
+use pe32::load_config::PeLoadConfig as PeLoadConfig32;
+use pe64::pe::Pe as Pe64;
+use pe64::exports::PeExports as PeExports64;
+use pe64::imports::PeImports as PeImports64;
+use pe64::delay_imports::PeDelayImports as PeDelayImports64;
+use pe64::bound_imports::PeBoundImports as PeBoundImports64;
+use pe64::security::PeSecurity as PeSecurity64;
+use pe64::relocs::PeRelocs as PeRelocs64;
+use pe64::resources::PeResources as PeResources64;
+use pe64::debug::PeDebug as PeDebug64;
+use pe64::tls::PeTls as PeTls64;
+use pe64::load_config::PeLoadConfig as PeLoadConfig64;
+use pe64::image::{Rva, Va, FileOffset};
+
+//----------------------------------------------------------------
+
+/// Wraps either a PE32 or a PE64 variant of something.
+pub enum Wrap<T32, T64> {
+	T32(T32),
+	T64(T64),
+}
+
+impl<T32, T64> Wrap<T32, T64> {
+	/// Is this the PE32 variant?
+	#[inline]
+	pub fn is_32(&self) -> bool {
+		match *self {
+			Wrap::T32(_) => true,
+			Wrap::T64(_) => false,
+		}
+	}
+	/// Is this the PE64 variant?
+	#[inline]
+	pub fn is_64(&self) -> bool {
+		!self.is_32()
+	}
+	/// Get the PE32 variant, if it is one.
+	#[inline]
+	pub fn as_32(&self) -> Option<&T32> {
+		match *self {
+			Wrap::T32(ref t) => Some(t),
+			Wrap::T64(_) => None,
+		}
+	}
+	/// Get the PE64 variant, if it is one.
+	#[inline]
+	pub fn as_64(&self) -> Option<&T64> {
+		match *self {
+			Wrap::T32(_) => None,
+			Wrap::T64(ref t) => Some(t),
+		}
+	}
+}
+
+impl<T32: fmt::Display, T64: fmt::Display> fmt::Display for Wrap<T32, T64> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Wrap::T32(ref t) => t.fmt(f),
+			Wrap::T64(ref t) => t.fmt(f),
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Either a `pe32::peview::PeView` or a `pe64::peview::PeView`.
+pub type PeView<'a> = Wrap<pe32::peview::PeView<'a>, pe64::peview::PeView<'a>>;
+
+impl<'a> PeView<'a> {
+	/// Create a new instance of PeView of a mapped module.
+	///
+	/// # Parameters
+	///
+	/// * `base`
+	///
+	///   Pointer to the mapped module in memory.
+	///
+	/// # Return value
+	///
+	/// View into memory pointed at by `base` interpreted as a PE32 or PE64 module depending on its optional header magic.
+	///
+	/// `None` if the magic is neither.
+	///
+	/// # Safety
+	///
+	/// The underlying memory is not taken ownership of. Make sure it outlives this PeView instance!
+	///
+	/// No sanity or safety checks are done other than inspecting the optional header magic.
+	pub unsafe fn module(base: *const u8) -> Option<PeView<'a>> {
+		let dos = &*(base as *const ImageDosHeader);
+		// Signature and file header are shared between PE32 and PE64, the magic follows them
+		let magic_offset = dos.e_lfanew as usize + mem::size_of::<u32>() + mem::size_of::<ImageFileHeader>();
+		let magic = *(base.offset(magic_offset as isize) as *const u16);
+		match magic {
+			IMAGE_NT_OPTIONAL_HDR32_MAGIC => Some(Wrap::T32(pe32::peview::PeView::module(base))),
+			IMAGE_NT_OPTIONAL_HDR64_MAGIC => Some(Wrap::T64(pe64::peview::PeView::module(base))),
+			_ => None,
+		}
+	}
+	/// Get the mapped image as a byte slice.
+	#[inline]
+	pub fn image(&self) -> &'a [u8] {
+		match *self {
+			Wrap::T32(ref view) => view.image(),
+			Wrap::T64(ref view) => view.image(),
+		}
+	}
+	/// Get the virtual base address.
+	#[inline]
+	pub fn virtual_base(&self) -> Va {
+		match *self {
+			Wrap::T32(ref view) => view.virtual_base() as Va,
+			Wrap::T64(ref view) => view.virtual_base(),
+		}
+	}
+	/// Get the dos header image.
+	#[inline]
+	pub fn dos_header(&self) -> &'a ImageDosHeader {
+		match *self {
+			Wrap::T32(ref view) => view.dos_header(),
+			Wrap::T64(ref view) => view.dos_header(),
+		}
+	}
+	/// Get the NT headers image.
+	#[inline]
+	pub fn nt_headers(&self) -> Wrap<&'a ImageNtHeaders32, &'a ImageNtHeaders64> {
+		match *self {
+			Wrap::T32(ref view) => Wrap::T32(view.nt_headers()),
+			Wrap::T64(ref view) => Wrap::T64(view.nt_headers()),
+		}
+	}
+	/// Get the file header image.
+	#[inline]
+	pub fn file_header(&self) -> &'a ImageFileHeader {
+		match *self {
+			Wrap::T32(ref view) => view.file_header(),
+			Wrap::T64(ref view) => view.file_header(),
+		}
+	}
+	/// Get the optional header image.
+	#[inline]
+	pub fn optional_header(&self) -> Wrap<&'a ImageOptionalHeader32, &'a ImageOptionalHeader64> {
+		match *self {
+			Wrap::T32(ref view) => Wrap::T32(view.optional_header()),
+			Wrap::T64(ref view) => Wrap::T64(view.optional_header()),
+		}
+	}
+	/// Get the section image headers.
+	#[inline]
+	pub fn section_headers(&self) -> &'a [ImageSectionHeader] {
+		match *self {
+			Wrap::T32(ref view) => view.section_headers(),
+			Wrap::T64(ref view) => view.section_headers(),
+		}
+	}
+	/// Get the data directory.
+	#[inline]
+	pub fn data_directory(&self) -> &'a [ImageDataDirectory] {
+		match *self {
+			Wrap::T32(ref view) => view.data_directory(),
+			Wrap::T64(ref view) => view.data_directory(),
+		}
+	}
+	/// Convert an Rva to FileOffset.
+	///
+	/// See `pe64::pe::Pe::rva_to_file_offset` for more information.
+	#[inline]
+	pub fn rva_to_file_offset(&self, rva: Rva) -> Option<FileOffset> {
+		match *self {
+			Wrap::T32(ref view) => view.rva_to_file_offset(rva),
+			Wrap::T64(ref view) => view.rva_to_file_offset(rva),
+		}
+	}
+	/// Convert a FileOffset to Rva.
+	///
+	/// See `pe64::pe::Pe::file_offset_to_rva` for more information.
+	#[inline]
+	pub fn file_offset_to_rva(&self, file_offset: FileOffset) -> Rva {
+		match *self {
+			Wrap::T32(ref view) => view.file_offset_to_rva(file_offset),
+			Wrap::T64(ref view) => view.file_offset_to_rva(file_offset),
+		}
+	}
+	/// Get the exports directory.
+	pub fn try_exports(&self) -> Result<Wrap<pe32::exports::ExportDirectory, pe64::exports::ExportDirectory>, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_exports().map(Wrap::T32),
+			Wrap::T64(ref view) => view.try_exports().map(Wrap::T64),
+		}
+	}
+	/// Get the exports directory.
+	#[inline]
+	pub fn exports(&self) -> Option<Wrap<pe32::exports::ExportDirectory, pe64::exports::ExportDirectory>> {
+		unwrap_null(self.try_exports())
+	}
+	/// Get the imports directory.
+	pub fn try_imports(&self) -> Result<Wrap<pe32::imports::ImportDirectory, pe64::imports::ImportDirectory>, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_imports().map(Wrap::T32),
+			Wrap::T64(ref view) => view.try_imports().map(Wrap::T64),
+		}
+	}
+	/// Get the imports directory.
+	#[inline]
+	pub fn imports(&self) -> Option<Wrap<pe32::imports::ImportDirectory, pe64::imports::ImportDirectory>> {
+		unwrap_null(self.try_imports())
+	}
+	/// Get the delay-load imports directory.
+	pub fn try_delay_imports(&self) -> Result<Wrap<pe32::delay_imports::DelayImportDirectory, pe64::delay_imports::DelayImportDirectory>, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_delay_imports().map(Wrap::T32),
+			Wrap::T64(ref view) => view.try_delay_imports().map(Wrap::T64),
+		}
+	}
+	/// Get the delay-load imports directory.
+	#[inline]
+	pub fn delay_imports(&self) -> Option<Wrap<pe32::delay_imports::DelayImportDirectory, pe64::delay_imports::DelayImportDirectory>> {
+		unwrap_null(self.try_delay_imports())
+	}
+	/// Get the bound imports directory.
+	pub fn try_bound_imports(&self) -> Result<Wrap<pe32::bound_imports::BoundImportDirectory, pe64::bound_imports::BoundImportDirectory>, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_bound_imports().map(Wrap::T32),
+			Wrap::T64(ref view) => view.try_bound_imports().map(Wrap::T64),
+		}
+	}
+	/// Get the bound imports directory.
+	#[inline]
+	pub fn bound_imports(&self) -> Option<Wrap<pe32::bound_imports::BoundImportDirectory, pe64::bound_imports::BoundImportDirectory>> {
+		unwrap_null(self.try_bound_imports())
+	}
+	/// Get the relocations directory.
+	pub fn try_relocs(&self) -> Result<Wrap<pe32::relocs::RelocsDirectory, pe64::relocs::RelocsDirectory>, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_relocs().map(Wrap::T32),
+			Wrap::T64(ref view) => view.try_relocs().map(Wrap::T64),
+		}
+	}
+	/// Get the relocations directory.
+	#[inline]
+	pub fn relocs(&self) -> Option<Wrap<pe32::relocs::RelocsDirectory, pe64::relocs::RelocsDirectory>> {
+		unwrap_null(self.try_relocs())
+	}
+	/// Get the resources.
+	///
+	/// Resources are the same for PE32 and PE64 and are not wrapped.
+	pub fn try_resources(&self) -> Result<Resources, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_resources(),
+			Wrap::T64(ref view) => view.try_resources(),
+		}
+	}
+	/// Get the resources.
+	#[inline]
+	pub fn resources(&self) -> Option<Resources> {
+		unwrap_null(self.try_resources())
+	}
+	/// Get the debug directory.
+	pub fn try_debug(&self) -> Result<Wrap<pe32::debug::DebugDirectory, pe64::debug::DebugDirectory>, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_debug().map(Wrap::T32),
+			Wrap::T64(ref view) => view.try_debug().map(Wrap::T64),
+		}
+	}
+	/// Get the debug directory.
+	#[inline]
+	pub fn debug(&self) -> Option<Wrap<pe32::debug::DebugDirectory, pe64::debug::DebugDirectory>> {
+		unwrap_null(self.try_debug())
+	}
+	/// Get the TLS directory.
+	pub fn try_tls(&self) -> Result<Wrap<pe32::tls::Tls, pe64::tls::Tls>, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_tls().map(Wrap::T32),
+			Wrap::T64(ref view) => view.try_tls().map(Wrap::T64),
+		}
+	}
+	/// Get the TLS directory.
+	#[inline]
+	pub fn tls(&self) -> Option<Wrap<pe32::tls::Tls, pe64::tls::Tls>> {
+		unwrap_null(self.try_tls())
+	}
+	/// Get the load config directory.
+	pub fn try_load_config(&self) -> Result<Wrap<pe32::load_config::LoadConfig, pe64::load_config::LoadConfig>, PeError> {
+		match *self {
+			Wrap::T32(ref view) => view.try_load_config().map(Wrap::T32),
+			Wrap::T64(ref view) => view.try_load_config().map(Wrap::T64),
+		}
+	}
+	/// Get the load config directory.
+	#[inline]
+	pub fn load_config(&self) -> Option<Wrap<pe32::load_config::LoadConfig, pe64::load_config::LoadConfig>> {
+		unwrap_null(self.try_load_config())
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Either a `pe32::pefile::PeFile` or a `pe64::pefile::PeFile`.
+pub type PeFile = Wrap<pe32::pefile::PeFile, pe64::pefile::PeFile>;
+
+impl PeFile {
+	/// Read a PE32 or PE64 file from disk.
+	///
+	/// # Parameters
+	///
+	/// * `path`
+	///
+	///   Path to the file on disk.
+	///
+	/// # Return value
+	///
+	/// `PeError::BadMagic` error if the optional header magic is neither PE32 nor PE64.
+	///
+	/// Otherwise the same as `pe64::pefile::PeFile::open`.
+	pub fn open(path: &Path) -> Result<PeFile, PeError> {
+		let mut file = try!(File::open(path));
+		Self::from_reader(&mut file)
+	}
+	/// Read a PE32 or PE64 file from a byte buffer.
+	///
+	/// See `pe64::pefile::PeFile::from_bytes` for more information.
+	pub fn from_bytes(bytes: &[u8]) -> Result<PeFile, PeError> {
+		Self::from_reader(&mut io::Cursor::new(bytes))
+	}
+	/// Read a PE32 or PE64 file from any seekable reader.
+	///
+	/// See `pe64::pefile::PeFile::from_reader` for more information.
+	pub fn from_reader<R: Read + Seek>(file: &mut R) -> Result<PeFile, PeError> {
+		match try!(read_magic(file)) {
+			IMAGE_NT_OPTIONAL_HDR32_MAGIC => pe32::pefile::PeFile::from_reader(file).map(Wrap::T32),
+			IMAGE_NT_OPTIONAL_HDR64_MAGIC => pe64::pefile::PeFile::from_reader(file).map(Wrap::T64),
+			_ => Err(PeError::BadMagic),
+		}
+	}
+	/// Get a view into the mapped image.
+	#[inline]
+	pub fn view(&self) -> PeView {
+		match *self {
+			Wrap::T32(ref file) => Wrap::T32(file.view()),
+			Wrap::T64(ref file) => Wrap::T64(file.view()),
+		}
+	}
+	/// Get the certificate table.
+	///
+	/// The certificate table is the same for PE32 and PE64 and is not wrapped.
+	pub fn try_security(&self) -> Result<Security, PeError> {
+		match *self {
+			Wrap::T32(ref file) => file.try_security(),
+			Wrap::T64(ref file) => file.try_security(),
+		}
+	}
+	/// Get the certificate table.
+	#[inline]
+	pub fn security(&self) -> Option<Security> {
+		unwrap_null(self.try_security())
+	}
+	/// Get the file offset range of the overlay.
+	///
+	/// See `pe64::pefile::PeFile::overlay_range` for more information.
+	pub fn overlay_range(&self) -> Result<Range<FileOffset>, PeError> {
+		match *self {
+			Wrap::T32(ref file) => file.overlay_range(),
+			Wrap::T64(ref file) => file.overlay_range(),
+		}
+	}
+	/// Read the overlay.
+	///
+	/// See `pe64::pefile::PeFile::read_overlay` for more information.
+	pub fn read_overlay<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<u8>, PeError> {
+		match *self {
+			Wrap::T32(ref file) => file.read_overlay(reader),
+			Wrap::T64(ref file) => file.read_overlay(reader),
+		}
+	}
+	/// Check whether the stored checksum matches the file.
+	///
+	/// See `pe64::pefile::PeFile::verify_checksum` for more information.
+	pub fn verify_checksum<R: Read + Seek>(&self, reader: &mut R) -> Result<bool, PeError> {
+		match *self {
+			Wrap::T32(ref file) => file.verify_checksum(reader),
+			Wrap::T64(ref file) => file.verify_checksum(reader),
+		}
+	}
+}
+
+/// Read the optional header magic without committing to either PE32 or PE64.
+fn read_magic<R: Read + Seek>(file: &mut R) -> Result<u16, PeError> {
+	let mut buf = vec![0u8; mem::size_of::<ImageDosHeader>()];
+	try!(file.seek(SeekFrom::Start(0)));
+	try!(file.read_exact(&mut buf[..]));
+	let e_lfanew = {
+		// This is safe since we read as many bytes earlier
+		let dos = unsafe { &*(buf.as_ptr() as *const ImageDosHeader) };
+		if dos.e_magic != IMAGE_DOS_HEADER_MAGIC {
+			return Err(PeError::BadMagic);
+		}
+		dos.e_lfanew
+	};
+	// Signature and file header are shared between PE32 and PE64, the magic follows them
+	let nt_bytes = mem::size_of::<u32>() + mem::size_of::<ImageFileHeader>();
+	buf.resize(nt_bytes + mem::size_of::<u16>(), 0);
+	try!(file.seek(SeekFrom::Start(e_lfanew as u64)));
+	try!(file.read_exact(&mut buf[..]));
+	if buf[0..4] != [b'P', b'E', 0, 0] {
+		return Err(PeError::BadMagic);
+	}
+	Ok(buf[nt_bytes] as u16 | (buf[nt_bytes + 1] as u16) << 8)
+}
@@ -0,0 +1,98 @@
+extern crate pelite;
+use pelite::PeError;
+use pelite::apiset::*;
+
+// There is no apisetschema.dll among the test binaries, build the schemas by hand.
+fn put(bytes: &mut Vec<u8>, offset: usize, values: &[usize]) {
+	for (i, &value) in values.iter().enumerate() {
+		for j in 0..4 {
+			bytes[offset + i * 4 + j] = (value >> (j * 8)) as u8;
+		}
+	}
+}
+fn push(bytes: &mut Vec<u8>, values: &[usize]) -> usize {
+	let offset = bytes.len();
+	bytes.resize(offset + values.len() * 4, 0);
+	put(bytes, offset, values);
+	offset
+}
+fn push_str(bytes: &mut Vec<u8>, s: &str) -> (usize, usize) {
+	let offset = bytes.len();
+	for word in s.encode_utf16() {
+		bytes.extend_from_slice(&[word as u8, (word >> 8) as u8]);
+	}
+	let len = bytes.len() - offset;
+	while bytes.len() % 4 != 0 {
+		bytes.push(0);
+	}
+	(offset, len)
+}
+fn schema(version: u32, entries: &[(&str, &[(&str, &str)])]) -> Vec<u8> {
+	let (header, entry_size) = match version { 2 => (8, 12), 4 => (16, 24), _ => (28, 24) };
+	let mut bytes = vec![0u8; header + entries.len() * entry_size];
+	for (i, &(name, hosts)) in entries.iter().enumerate() {
+		// Older versions store the names without the api prefix
+		let stored = if version < 6 && name.starts_with("api-") { &name[4..] } else { name };
+		let (name_offset, name_len) = push_str(&mut bytes, stored);
+		let values: Vec<_> = hosts.iter().map(|&(importer, host)| (push_str(&mut bytes, importer), push_str(&mut bytes, host))).collect();
+		let data = match version {
+			2 => push(&mut bytes, &[values.len()]),
+			4 => push(&mut bytes, &[0, values.len()]),
+			_ => bytes.len(),
+		};
+		for &((importer_offset, importer_len), (host_offset, host_len)) in &values {
+			if version > 2 {
+				push(&mut bytes, &[0]);
+			}
+			push(&mut bytes, &[if importer_len != 0 { importer_offset } else { 0 }, importer_len, host_offset, host_len]);
+		}
+		let entry = header + i * entry_size;
+		match version {
+			2 => put(&mut bytes, entry, &[name_offset, name_len, data]),
+			4 => put(&mut bytes, entry, &[0, name_offset, name_len, name_offset, name_len, data]),
+			_ => put(&mut bytes, entry, &[0, name_offset, name_len, stored.rfind('-').unwrap() * 2, data, values.len()]),
+		}
+	}
+	let size = bytes.len();
+	match version {
+		2 => put(&mut bytes, 0, &[2, entries.len()]),
+		4 => put(&mut bytes, 0, &[4, size, 0, entries.len()]),
+		_ => put(&mut bytes, 0, &[version as usize, size, 0, entries.len(), header, 0, 0]),
+	}
+	bytes
+}
+
+const ENTRIES: &'static [(&'static str, &'static [(&'static str, &'static str)])] = &[
+	("api-ms-win-core-synch-l1-2-0", &[("", "kernel32.dll"), ("kernel32.dll", "kernelbase.dll")]),
+	("api-ms-win-crt-runtime-l1-1-0", &[("", "ucrtbase.dll")]),
+	("ext-ms-win-missing-l1-1-0", &[]),
+];
+
+#[test]
+fn test_apiset() {
+	for &version in &[2, 4, 6] {
+		let bytes = schema(version, ENTRIES);
+		let schema = ApiSetSchema::parse(&bytes).unwrap();
+		print!("{}", schema);
+		assert_eq!(schema.version(), version);
+		assert_eq!(schema.entries().len(), 3);
+		let names: Vec<&str> = schema.entries().iter().map(|entry| entry.name()).collect();
+		assert_eq!(names, ["api-ms-win-core-synch-l1-2-0", "api-ms-win-crt-runtime-l1-1-0", "ext-ms-win-missing-l1-1-0"]);
+		// Hosts specific to the importer take precedence over the default host
+		assert_eq!(schema.resolve("API-MS-WIN-CORE-SYNCH-L1-2-0.DLL", ""), Some("kernel32.dll"));
+		assert_eq!(schema.resolve("api-ms-win-core-synch-l1-2-0", "user32.dll"), Some("kernel32.dll"));
+		assert_eq!(schema.resolve("api-ms-win-core-synch-l1-2-0.dll", "KERNEL32.DLL"), Some("kernelbase.dll"));
+		assert_eq!(schema.resolve("api-ms-win-crt-runtime-l1-1-0.dll", "dummy64.dll"), Some("ucrtbase.dll"));
+		assert_eq!(schema.resolve("ext-ms-win-missing-l1-1-0.dll", ""), None);
+		assert_eq!(schema.resolve("api-ms-win-core-unknown-l1-1-0.dll", ""), None);
+		// Only version 6 ignores the last number of the name
+		assert_eq!(schema.find("api-ms-win-core-synch-l1-2-1.dll").is_some(), version == 6);
+		assert!(schema.find("api-ms-win-core-synch-l1-3-0.dll").is_none());
+		// Offsets out of bounds are reported
+		assert!(ApiSetSchema::parse(&bytes[..bytes.len() - 4]).is_err());
+	}
+	match ApiSetSchema::parse(&schema(5, ENTRIES)) {
+		Err(PeError::BadMagic) => (),
+		_ => panic!(),
+	}
+}
@@ -1,11 +1,25 @@
 extern crate pelite;
 use std::path::Path;
-use pelite::pe32::peview::PeView;
+use std::fs::File;
+use std::io::Read;
+use pelite::rich::RichHeader;
+use pelite::PeError;
+use pelite::pe32::image::*;
+use pelite::pe32::pe::Pe;
 use pelite::pe32::pefile::PeFile;
+use pelite::pe32::pefileview::PeFileView;
 use pelite::pe32::exports::PeExports;
-use pelite::pe32::imports::PeImports;
-use pelite::pe32::relocs::PeRelocs;
+use pelite::pe32::symbols::SymbolIndex;
+use pelite::pe32::imports::{PeImports, ImportedSymbol};
+use pelite::pe32::delay_imports::PeDelayImports;
+use pelite::pe32::bound_imports::PeBoundImports;
+use pelite::pe32::security::PeSecurity;
+use pelite::pe32::relocs::{PeRelocs, Fixup};
 use pelite::pe32::resources::PeResources;
+use pelite::pe32::debug::PeDebug;
+use pelite::pe32::tls::PeTls;
+use pelite::pe32::load_config::PeLoadConfig;
+use pelite::pe32::loader::{Loader, LoaderError};
 
 #[test]
 fn test_dummyd_dll() {
@@ -17,10 +31,286 @@ fn test_dummy_dll() {
 	let file = PeFile::open(Path::new("tests\\bin\\dummy.dll")).unwrap();
 	run_tests(&file.view());
 }
+#[test]
+fn test_dummyd_dll_fileview() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummyd.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	assert!(view.security().is_none());
+	run_tests(&view);
+}
+
+#[test]
+fn test_rebase() {
+	let mut file = PeFile::open(Path::new("tests\\bin\\dummy.dll")).unwrap();
+	let original = file.view().image().to_vec();
+	let old_base = file.view().optional_header().ImageBase;
+	file.rebase(0x0040_0000).unwrap();
+	let new_base = file.view().optional_header().ImageBase;
+	assert_eq!(new_base, 0x0040_0000);
+	// Every relocated address moved by the same amount, nothing else changed
+	let mut changed = vec![false; original.len()];
+	let view = file.view();
+	let relocs = view.relocs().unwrap();
+	for base_relocs in relocs.iter() {
+		for block in base_relocs.blocks() {
+			let ty = base_relocs.type_of(block);
+			assert!(ty == IMAGE_REL_BASED_HIGHLOW || ty == IMAGE_REL_BASED_ABSOLUTE);
+			if ty == IMAGE_REL_BASED_ABSOLUTE {
+				continue;
+			}
+			let rva = base_relocs.rva_of(block) as usize;
+			let read = |image: &[u8]| image[rva..rva + 4].iter().rev().fold(0u64, |acc, &byte| acc << 8 | byte as u64);
+			assert_eq!(read(view.image()).wrapping_sub(read(&original)) as Va, (0x0040_0000 as Va).wrapping_sub(old_base));
+			for i in 0..4 {
+				changed[rva + i] = true;
+			}
+		}
+	}
+	let image_base = view.dos_header().e_lfanew as usize + 24 + 28;
+	for i in 0..4 {
+		changed[image_base + i] = true;
+	}
+	for (i, (&a, &b)) in original.iter().zip(view.image()).enumerate() {
+		assert!(a == b || changed[i]);
+	}
+	// Rebasing back restores the original
+	file.rebase(old_base).unwrap();
+	assert_eq!(file.view().image(), &original[..]);
+}
+
+// Store the low `size` bytes of the value in little endian.
+fn put(bytes: &mut [u8], offset: usize, value: u64, size: usize) {
+	for i in 0..size {
+		bytes[offset + i] = (value >> (i * 8)) as u8;
+	}
+}
+// Point a data directory to a hand-written directory, the headers of dummy.dll are unused from 0x2E0 up to 0x400.
+fn set_data_directory(bytes: &mut [u8], index: usize, rva: Rva, size: u32) {
+	let e_lfanew = bytes[0x3C] as usize | (bytes[0x3D] as usize) << 8;
+	let datadir = e_lfanew + 24 + 96 + index * 8;
+	put(bytes, datadir, rva as u64, 4);
+	put(bytes, datadir + 4, size as u64, 4);
+}
+#[test]
+fn test_tls() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let image_base = PeFileView::from_bytes(&bytes).unwrap().optional_header().ImageBase as u64;
+	// TLS directory at 0x340, the template at 0x380 and the callbacks at 0x3A0
+	put(&mut bytes, 0x340, image_base + 0x380, 4);
+	put(&mut bytes, 0x344, image_base + 0x390, 4);
+	put(&mut bytes, 0x348, image_base + 0x3C0, 4);
+	put(&mut bytes, 0x34C, image_base + 0x3A0, 4);
+	put(&mut bytes, 0x350, 0x20, 4);
+	bytes[0x380..0x390].copy_from_slice(b"thread local\0\0\0\0");
+	put(&mut bytes, 0x3A0, image_base + 0x1000, 4);
+	put(&mut bytes, 0x3A4, image_base + 0x1010, 4);
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_TLS, 0x340, 24);
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let tls = view.tls().unwrap();
+	println!("{}", tls);
+	assert_eq!(tls.raw_data(), b"thread local\0\0\0\0");
+	assert_eq!(tls.callbacks().collect::<Vec<_>>(), [0x1000, 0x1010]);
+}
+
+// Delay-load `delayed.dll` in either the current Rva based format or the legacy Va based format.
+fn delay_import_dll(rva_based: bool) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let image_base = PeFileView::from_bytes(&bytes).unwrap().optional_header().ImageBase as u64;
+	let address = |rva: u64| if rva_based { rva } else { image_base + rva };
+	// The descriptors at 0x300 with the names and thunks following them
+	put(&mut bytes, 0x300, if rva_based { DLATTR_RVA as u64 } else { 0 }, 4);
+	put(&mut bytes, 0x304, address(0x340), 4);
+	put(&mut bytes, 0x308, address(0x3C0), 4);
+	put(&mut bytes, 0x30C, address(0x3A0), 4);
+	put(&mut bytes, 0x310, address(0x380), 4);
+	bytes[0x340..0x34C].copy_from_slice(b"delayed.dll\0");
+	put(&mut bytes, 0x350, 7, 2);
+	bytes[0x352..0x35E].copy_from_slice(b"DelayedFunc\0");
+	put(&mut bytes, 0x380, address(0x350), 4);
+	put(&mut bytes, 0x384, IMAGE_ORDINAL_FLAG as u64 | 5, 4);
+	put(&mut bytes, 0x3A0, image_base + 0x1000, 4);
+	put(&mut bytes, 0x3A4, image_base + 0x1010, 4);
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT, 0x300, 64);
+	bytes
+}
+#[test]
+fn test_delay_imports() {
+	for &rva_based in &[true, false] {
+		let bytes = delay_import_dll(rva_based);
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		let delay_imports = view.delay_imports().unwrap();
+		println!("{}", delay_imports);
+		let descs: Vec<_> = delay_imports.iter().collect();
+		assert_eq!(descs.len(), 1);
+		let desc = &descs[0];
+		assert_eq!(desc.is_rva_based(), rva_based);
+		assert_eq!(desc.dll_name(), "delayed.dll");
+		assert_eq!(desc.try_to_rva(desc.image().ModuleHandleRVA as Va).unwrap(), 0x3C0);
+		let symbols: Vec<_> = desc.int_iter().map(|symbol| match symbol {
+			ImportedSymbol::ByName { hint, name } => (hint, name.to_owned()),
+			ImportedSymbol::ByOrdinal { ord } => (ord, String::new()),
+		}).collect();
+		assert_eq!(symbols, [(7, "DelayedFunc".to_owned()), (5, String::new())]);
+		let image_base = view.optional_header().ImageBase;
+		assert_eq!(desc.iat_iter().cloned().collect::<Vec<_>>(), [image_base + 0x1000, image_base + 0x1010]);
+		match desc.try_to_rva(0) {
+			Err(PeError::Null) => (),
+			_ => panic!(),
+		}
+	}
+	// Legacy Vas must point within the image
+	let mut bytes = delay_import_dll(false);
+	put(&mut bytes, 0x304, 0x340, 4);
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let desc = view.delay_imports().unwrap().iter().next().unwrap();
+	assert!(desc.try_dll_name().is_err());
+}
+
+#[test]
+fn test_bound_imports() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	// Two descriptors, the first forwarding to ntdll.dll, the module names are relative to the directory
+	put(&mut bytes, 0x340, 0x11111111, 4);
+	put(&mut bytes, 0x344, 0x20, 2);
+	put(&mut bytes, 0x346, 1, 2);
+	put(&mut bytes, 0x348, 0x22222222, 4);
+	put(&mut bytes, 0x34C, 0x2D, 2);
+	put(&mut bytes, 0x350, 0x33333333, 4);
+	put(&mut bytes, 0x354, 0x37, 2);
+	bytes[0x360..0x382].copy_from_slice(b"KERNEL32.dll\0ntdll.dll\0USER32.dll\0");
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT, 0x340, 0x42);
+	{
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		let bound_imports = view.bound_imports().unwrap();
+		println!("{}", bound_imports);
+		let descs: Vec<_> = bound_imports.iter().map(|desc| {
+			let forwarders: Vec<_> = desc.forwarders().map(|fwd| (fwd.module_name(), fwd.time_date_stamp())).collect();
+			(desc.module_name(), desc.time_date_stamp(), forwarders)
+		}).collect();
+		assert_eq!(descs, [
+			("KERNEL32.dll", 0x11111111, vec![("ntdll.dll", 0x22222222)]),
+			("USER32.dll", 0x33333333, vec![]),
+		]);
+		assert_eq!(bound_imports.find("kernel32.dll").unwrap().forwarders_image().len(), 1);
+		assert_eq!(bound_imports.find("user32.DLL").unwrap().time_date_stamp(), 0x33333333);
+		assert!(bound_imports.find("ntdll.dll").is_none());
+	}
+	// The directory size ends the iteration as well, module names may not be out of bounds
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT, 0x340, 0x10);
+	put(&mut bytes, 0x344, 0xFFFF, 2);
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let bound_imports = view.bound_imports().unwrap();
+	assert_eq!(bound_imports.iter().count(), 1);
+	assert!(bound_imports.iter().next().unwrap().try_module_name().is_err());
+	assert!(bound_imports.try_find("USER32.dll").is_err());
+}
+
+#[test]
+fn test_fixups() {
+	// The 16-bit types on known values, HIGHADJ rounds for the sign extension of the low half in `param`
+	let apply = |ty: u8, param: u16, word: u16, delta: u64| -> u16 {
+		let mut bytes = [word as u8, (word >> 8) as u8];
+		Fixup { ty: ty, rva: 0, param: param }.apply(&mut bytes, delta);
+		bytes[0] as u16 | (bytes[1] as u16) << 8
+	};
+	assert_eq!(apply(IMAGE_REL_BASED_HIGH, 0, 0x1234, 0x18000), 0x1235);
+	assert_eq!(apply(IMAGE_REL_BASED_LOW, 0, 0x1234, 0x18000), 0x9234);
+	assert_eq!(apply(IMAGE_REL_BASED_HIGHADJ, 0x8000, 0x1235, 0x10000), 0x1236);
+	assert_eq!(apply(IMAGE_REL_BASED_HIGHADJ, 0x7FFF, 0x1235, 0x1), 0x1236);
+	assert_eq!(apply(IMAGE_REL_BASED_HIGHADJ, 0x7FFE, 0x1235, 0x1), 0x1235);
+	assert_eq!(apply(IMAGE_REL_BASED_HIGHADJ, 0x1000, 0x1235, 0xFFFF_F000), 0x1235);
+	// Hand-written relocations at 0x300, the HIGHADJ param is stored in the next block
+	let relocs_dll = |base: u32, blocks: &[u16]| {
+		let mut bytes = Vec::new();
+		File::open("tests\\bin\\dummy.dll").unwrap().read_to_end(&mut bytes).unwrap();
+		put(&mut bytes, 0x300, base as u64, 4);
+		put(&mut bytes, 0x304, 8 + blocks.len() as u64 * 2, 4);
+		for (i, &block) in blocks.iter().enumerate() {
+			put(&mut bytes, 0x308 + i * 2, block as u64, 2);
+		}
+		set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_BASERELOC, 0x300, 8 + blocks.len() as u32 * 2);
+		bytes
+	};
+	let bytes = relocs_dll(0x1000, &[0x1010, 0x2012, 0x4014, 0x8000, 0x0000]);
+	let mut file = PeFile::from_bytes(&bytes).unwrap();
+	assert_eq!(file.view().relocs().unwrap().fixups().collect::<Vec<_>>(), [
+		Fixup { ty: IMAGE_REL_BASED_HIGH, rva: 0x1010, param: 0 },
+		Fixup { ty: IMAGE_REL_BASED_LOW, rva: 0x1012, param: 0 },
+		Fixup { ty: IMAGE_REL_BASED_HIGHADJ, rva: 0x1014, param: 0x8000 },
+	]);
+	let read = |file: &PeFile, rva: usize| file.view().image()[rva] as u16 | (file.view().image()[rva + 1] as u16) << 8;
+	let original = [read(&file, 0x1010), read(&file, 0x1012), read(&file, 0x1014)];
+	let old_base = file.view().optional_header().ImageBase;
+	file.rebase(old_base + 0x10000).unwrap();
+	assert_eq!([read(&file, 0x1010), read(&file, 0x1012), read(&file, 0x1014)], [original[0].wrapping_add(1), original[1], original[2].wrapping_add(1)]);
+	// Unsupported types are corrupt, the headers and anything past the image are out of bounds
+	let size_of_image = file.view().optional_header().SizeOfImage;
+	for &(base, block, corrupt) in &[(0x1000, 0x5010, true), (0x0000, 0x3100, false), (size_of_image - 2, 0x3000, false), (0xFFFF_F800, 0x3FFF, false)] {
+		let bytes = relocs_dll(base, &[block]);
+		let mut file = PeFile::from_bytes(&bytes).unwrap();
+		let original = file.view().image().to_vec();
+		match file.rebase(old_base + 0x10000) {
+			Err(PeError::Corrupt) => assert!(corrupt),
+			Err(PeError::Bounds) => assert!(!corrupt),
+			_ => panic!(),
+		}
+		assert_eq!(file.view().image(), &original[..]);
+	}
+}
+
+#[test]
+fn test_loader_no_free_base() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	// Preferred base so close to 4 GiB the image doesn't fit
+	let e_lfanew = PeFileView::from_bytes(&bytes).unwrap().dos_header().e_lfanew as usize;
+	for (i, &byte) in [0x00, 0xF0, 0xFF, 0xFF].iter().enumerate() {
+		bytes[e_lfanew + 24 + 28 + i] = byte;
+	}
+	let mut loader = Loader::new();
+	loader.add_file("dummy.dll", bytes);
+	match loader.load("dummy.dll") {
+		Err(LoaderError::NoFreeBase(name)) => assert_eq!(name, "dummy.dll"),
+		_ => panic!(),
+	}
+	assert!(loader.modules().is_empty());
+}
 
-fn run_tests(view: &PeView) {
+fn run_tests<'a, P: Pe<'a>>(view: &P) {
 	println!("{}", view.imports().unwrap());
-	println!("{}", view.exports().unwrap());
+	let exports = view.exports().unwrap();
+	println!("{}", exports);
+	// Every named export is found by name and by its hint, even a wrong hint
+	for (hint, &name_rva) in exports.names().unwrap().iter().enumerate() {
+		let name = view.read_str(name_rva).unwrap();
+		let symbol = format!("{}", exports.symbol_by_name(name));
+		assert!(symbol != "None");
+		assert_eq!(format!("{}", exports.symbol_by_hint(hint as u16, name)), symbol);
+		assert_eq!(format!("{}", exports.symbol_by_hint(hint as u16 + 1, name)), symbol);
+	}
+	assert_eq!(format!("{}", exports.symbol_by_name("missing")), "None");
+	// Every exported address symbolizes to itself
+	let index = SymbolIndex::new(&exports).unwrap();
+	for symbol in index.symbols() {
+		assert_eq!(index.lookup(symbol.rva).unwrap().offset, 0);
+	}
 	println!("{}", view.resources().unwrap());
 	println!("{}", view.relocs().unwrap());
+	let debug = view.debug().unwrap();
+	println!("{}", debug);
+	assert!(debug.code_view().unwrap().pdb_file_name().ends_with(".pdb"));
+	// The dummy dlls do not use thread local storage
+	assert!(view.tls().is_none());
+	assert!(view.delay_imports().is_none());
+	assert!(view.bound_imports().is_none());
+	let load_config = view.load_config().unwrap();
+	println!("{}", load_config);
+	assert!(load_config.security_cookie() != 0);
+	let rich = RichHeader::parse(view.image()).unwrap();
+	println!("{}", rich);
+	assert!(rich.verify_checksum());
 }
@@ -1,11 +1,32 @@
 extern crate pelite;
 use std::path::Path;
-use pelite::pe64::peview::PeView;
+use std::fs::File;
+use std::io::{Cursor, Read};
+use pelite::PeError;
+use pelite::pe64::image::*;
+use pelite::rich::{RichHeader, RichEntry};
+use pelite::pe64::pe::{Pe, validate_headers};
 use pelite::pe64::pefile::PeFile;
+use pelite::pe64::pefileview::PeFileView;
+use pelite::pe64::peview::PeView;
+use pelite::pe64::diff::diff;
+use pelite::pe64::loader::{Loader, LoaderError};
+use pelite::pe64::symbols::SymbolIndex;
+use pelite::pe64::resolver::{Resolver, ResolveError, Symbol, parse_forward};
 use pelite::pe64::exports::PeExports;
 use pelite::pe64::imports::PeImports;
+use pelite::pe64::delay_imports::PeDelayImports;
+use pelite::pe64::bound_imports::PeBoundImports;
+use pelite::pe64::security::PeSecurity;
+use pelite::pe64::overlay::PeOverlay;
 use pelite::pe64::relocs::PeRelocs;
 use pelite::pe64::resources::PeResources;
+use pelite::pe64::debug::{PeDebug, Entry};
+use pelite::pe64::tls::PeTls;
+use pelite::pe64::load_config::{PeLoadConfig, GuardTable};
+use pelite::pe64::exceptions::{PeExceptions, UnwindCode};
+use pelite::checksum::{Checksum, compute_checksum, compute_checksum_from_reader, checksum_offset};
+use pelite::apiset::ApiSetSchema;
 
 #[test]
 fn test_dummy64d_dll() {
@@ -17,10 +38,923 @@ fn test_dummy64_dll() {
 	let file = PeFile::open(Path::new("tests\\bin\\dummy64.dll")).unwrap();
 	run_tests(&file.view());
 }
+#[test]
+fn test_dummy64d_dll_fileview() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64d.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	run_tests(&PeFileView::from_bytes(&bytes).unwrap());
+}
+
+// Sign a copy of the dll with a dummy certificate table.
+fn append_certificate(bytes: &mut Vec<u8>, blob: &[u8]) {
+	while bytes.len() % 8 != 0 {
+		bytes.push(0);
+	}
+	let offset = bytes.len() as u32;
+	let length = 8 + blob.len() as u32;
+	bytes.extend_from_slice(&[length as u8, (length >> 8) as u8, (length >> 16) as u8, (length >> 24) as u8, 0x00, 0x02, 0x02, 0x00]);
+	bytes.extend_from_slice(blob);
+	// Point the security data directory to it
+	let e_lfanew = bytes[0x3C] as usize | (bytes[0x3D] as usize) << 8;
+	let datadir = e_lfanew + 24 + 112 + 4 * 8;
+	let size = bytes.len() as u32 - offset;
+	for i in 0..4 {
+		bytes[datadir + i] = (offset >> (i * 8)) as u8;
+		bytes[datadir + 4 + i] = (size >> (i * 8)) as u8;
+	}
+}
+#[test]
+fn test_security() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64d.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	assert!(PeFileView::from_bytes(&bytes).unwrap().security().is_none());
+	assert!(PeFile::from_bytes(&bytes).unwrap().security().is_none());
+	append_certificate(&mut bytes, b"signature");
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let file = PeFile::from_bytes(&bytes).unwrap();
+	for security in vec![view.security().unwrap(), file.security().unwrap()] {
+		let certs: Vec<_> = security.iter().collect();
+		assert_eq!(certs.len(), 1);
+		assert_eq!(certs[0].revision(), 0x0200);
+		assert_eq!(certs[0].certificate_type(), 0x0002);
+		assert_eq!(certs[0].data(), b"signature");
+	}
+}
+
+#[test]
+fn test_authenticode_hash() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64d.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let hash = |bytes: &[u8]| {
+		let mut hashed = Vec::new();
+		PeFileView::from_bytes(bytes).unwrap().authenticode_hash(&mut |chunk: &[u8]| hashed.extend_from_slice(chunk)).unwrap();
+		hashed
+	};
+	// Everything but the checksum and the security data directory entry
+	let unsigned = hash(&bytes);
+	assert_eq!(unsigned.len(), bytes.len() - 4 - 8);
+	// Neither the checksum nor the certificate table are covered
+	let checksum = (bytes[0x3C] as usize | (bytes[0x3D] as usize) << 8) + 24 + 64;
+	bytes[checksum] ^= 0xFF;
+	append_certificate(&mut bytes, b"signature");
+	let signed = hash(&bytes);
+	assert_eq!(&signed[..unsigned.len()], &unsigned[..]);
+	assert!(signed[unsigned.len()..].iter().all(|&byte| byte == 0));
+}
+#[test]
+fn test_checksum() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let checksum = compute_checksum(&bytes).unwrap();
+	assert_eq!(checksum, 0x9BF2);
+	assert_eq!(compute_checksum_from_reader(&mut Cursor::new(&bytes)).unwrap(), checksum);
+	// Chunks of any length give the same result, words may be split between them
+	for &len in &[1, 3, 0x3FF] {
+		let mut incremental = Checksum::new(checksum_offset(&bytes).unwrap());
+		for chunk in bytes.chunks(len) {
+			incremental.update(chunk);
+		}
+		assert_eq!(incremental.finish(), checksum);
+	}
+	// The linker did not set the checksum
+	let file = PeFile::from_bytes(&bytes).unwrap();
+	assert!(!file.verify_checksum(&mut Cursor::new(&bytes)).unwrap());
+	// Storing the checksum does not change it
+	let offset = (bytes[0x3C] as usize | (bytes[0x3D] as usize) << 8) + 24 + 64;
+	for i in 0..4 {
+		bytes[offset + i] = (checksum >> (i * 8)) as u8;
+	}
+	assert_eq!(compute_checksum(&bytes).unwrap(), checksum);
+	assert!(PeFile::from_bytes(&bytes).unwrap().verify_checksum(&mut Cursor::new(&bytes)).unwrap());
+	// But any other change does
+	let last = bytes.len() - 1;
+	bytes[last] ^= 0x55;
+	assert!(!PeFile::from_bytes(&bytes).unwrap().verify_checksum(&mut Cursor::new(&bytes)).unwrap());
+	assert!(compute_checksum(&bytes[..0x40]).is_err());
+	assert!(compute_checksum_from_reader(&mut Cursor::new(&bytes[..0x40])).is_err());
+}
+#[test]
+fn test_rich_header() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let rich = RichHeader::parse(&bytes).unwrap();
+	assert_eq!(rich.offset(), 0x80);
+	assert_eq!(rich.key(), 0xB647AC60);
+	assert!(rich.verify_checksum());
+	let entries: Vec<_> = rich.iter().collect();
+	assert_eq!(entries.len(), 11);
+	assert_eq!((entries[0].prod_id, entries[0].build, entries[0].count), (0x93, 30729, 2));
+	assert_eq!(entries[0].vs_version(), Some("Visual Studio 2008"));
+	// Import0 is not a tool
+	assert_eq!((entries[6].prod_id, entries[6].count), (0x1, 32));
+	assert_eq!(entries[6].vs_version(), None);
+	// The linker comes last
+	assert_eq!((entries[10].prod_id, entries[10].build), (0x102, 23506));
+	assert_eq!(entries[10].vs_version(), Some("Visual Studio 2015"));
+	// Any change to the DOS stub breaks the checksum
+	bytes[0x50] ^= 0x01;
+	assert!(!RichHeader::parse(&bytes).unwrap().verify_checksum());
+	// Without the Rich marker there is no Rich header
+	bytes[0xE8] = 0;
+	assert!(RichHeader::parse(&bytes).is_err());
+}
+#[test]
+fn test_rich_vs_version() {
+	// The product ids at both ends of every block, the v14x toolsets by build number
+	let cases: &[(u16, u16, Option<&str>)] = &[
+		(0x0001, 0, None),
+		(0x0002, 0, Some("Visual Studio 97 / 6.0")),
+		(0x0018, 0, Some("Visual Studio 97 / 6.0")),
+		(0x0019, 0, Some("Visual Studio .NET 2002")),
+		(0x0045, 0, Some("Visual Studio .NET 2002")),
+		(0x0046, 0, Some("Visual Studio .NET 2003")),
+		(0x006C, 0, Some("Visual Studio .NET 2003")),
+		(0x006D, 0, Some("Visual Studio 2005")),
+		(0x0082, 0, Some("Visual Studio 2005")),
+		(0x0083, 0, Some("Visual Studio 2008")),
+		(0x0097, 0, Some("Visual Studio 2008")),
+		(0x0098, 0, Some("Visual Studio 2010")),
+		(0x00B4, 0, Some("Visual Studio 2010")),
+		(0x00B5, 0, Some("Visual Studio 2012")),
+		(0x00C6, 0, Some("Visual Studio 2012")),
+		(0x00C7, 0, Some("Visual Studio 2013")),
+		(0x00FC, 0, Some("Visual Studio 2013")),
+		(0x00FD, 24999, Some("Visual Studio 2015")),
+		(0x0100, 25000, Some("Visual Studio 2017")),
+		(0x0100, 27499, Some("Visual Studio 2017")),
+		(0x0100, 27500, Some("Visual Studio 2019")),
+		(0x0100, 30699, Some("Visual Studio 2019")),
+		(0x01FF, 30700, Some("Visual Studio 2022")),
+		(0x0200, 30700, None),
+	];
+	for &(prod_id, build, version) in cases {
+		let entry = RichEntry { prod_id: prod_id, build: build, count: 1 };
+		assert_eq!(entry.vs_version(), version, "prod_id {:#06X} build {}", prod_id, build);
+	}
+}
+#[test]
+fn test_debug_unknown() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let patches: Vec<(usize, &[u8; 4])> = PeFileView::from_bytes(&bytes).unwrap().debug().unwrap().image().iter().filter_map(|dir| {
+		match dir.Type {
+			IMAGE_DEBUG_TYPE_CODEVIEW => Some((dir.PointerToRawData as usize, b"NB09")),
+			IMAGE_DEBUG_TYPE_POGO => Some((dir.PointerToRawData as usize, b"PGI\0")),
+			_ => None,
+		}
+	}).collect();
+	assert_eq!(patches.len(), 2);
+	// Older CodeView and POGO formats are not decoded but are not an error either
+	for &(offset, signature) in &patches {
+		bytes[offset..offset + 4].copy_from_slice(signature);
+	}
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let debug = view.debug().unwrap();
+	println!("{}", debug);
+	for entry in debug.iter().filter(|entry| patches.iter().any(|&(offset, _)| entry.image().PointerToRawData as usize == offset)) {
+		match entry.try_entry() {
+			Ok(Entry::Unknown(data)) => assert!(data.starts_with(b"NB09") || data.starts_with(b"PGI\0")),
+			_ => panic!(),
+		}
+	}
+	assert!(debug.code_view().is_none());
+}
+// Store the low `size` bytes of the value in little endian.
+fn put(bytes: &mut [u8], offset: usize, value: u64, size: usize) {
+	for i in 0..size {
+		bytes[offset + i] = (value >> (i * 8)) as u8;
+	}
+}
+// Point a data directory to a hand-written directory, the headers of dummy64.dll are unused from 0x320 up to 0x400.
+fn set_data_directory(bytes: &mut [u8], index: usize, rva: Rva, size: u32) {
+	let e_lfanew = bytes[0x3C] as usize | (bytes[0x3D] as usize) << 8;
+	let datadir = e_lfanew + 24 + 112 + index * 8;
+	put(bytes, datadir, rva as u64, 4);
+	put(bytes, datadir + 4, size as u64, 4);
+}
+#[test]
+fn test_tls() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let image_base = PeFileView::from_bytes(&bytes).unwrap().optional_header().ImageBase;
+	// TLS directory at 0x340, the template at 0x380 and the callbacks at 0x3A0
+	put(&mut bytes, 0x340, image_base + 0x380, 8);
+	put(&mut bytes, 0x348, image_base + 0x390, 8);
+	put(&mut bytes, 0x350, image_base + 0x3C0, 8);
+	put(&mut bytes, 0x358, image_base + 0x3A0, 8);
+	put(&mut bytes, 0x360, 0x20, 4);
+	bytes[0x380..0x390].copy_from_slice(b"thread local\0\0\0\0");
+	put(&mut bytes, 0x3A0, image_base + 0x1000, 8);
+	put(&mut bytes, 0x3A8, image_base + 0x1010, 8);
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_TLS, 0x340, 40);
+	{
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		let tls = view.tls().unwrap();
+		println!("{}", tls);
+		assert_eq!(tls.raw_data(), b"thread local\0\0\0\0");
+		assert_eq!(tls.callbacks().collect::<Vec<_>>(), [0x1000, 0x1010]);
+	}
+	// Callbacks outside the image and templates ending before they start are errors
+	put(&mut bytes, 0x3A8, image_base + 0x1000_0000, 8);
+	put(&mut bytes, 0x348, image_base + 0x370, 8);
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let tls = view.tls().unwrap();
+	println!("{}", tls);
+	let mut callbacks = tls.callbacks();
+	assert_eq!(callbacks.try_next().unwrap(), Some(0x1000));
+	assert!(callbacks.try_next().is_err());
+	match tls.try_raw_data() {
+		Err(PeError::Corrupt) => (),
+		_ => panic!(),
+	}
+}
+#[test]
+fn test_load_config() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let image_base = PeFileView::from_bytes(&bytes).unwrap().optional_header().ImageBase;
+	// The tables at 0x320, guard table entries have a byte of flags
+	put(&mut bytes, 0x320, 0x1000, 4);
+	put(&mut bytes, 0x324, 0x1010, 4);
+	put(&mut bytes, 0x328, 0x1000, 4);
+	put(&mut bytes, 0x32C, IMAGE_GUARD_FLAG_FID_SUPPRESSED as u64, 1);
+	put(&mut bytes, 0x32D, 0x1020, 4);
+	put(&mut bytes, 0x332, 0x2000, 4);
+	put(&mut bytes, 0x337, 0x1030, 4);
+	// Load config directory at 0x340 up to the longjmp targets
+	let guard_flags = IMAGE_GUARD_CF_INSTRUMENTED | IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT | IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT | 1 << IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT;
+	put(&mut bytes, 0x340, 192, 4);
+	put(&mut bytes, 0x340 + 88, image_base + 0x3000, 8);
+	put(&mut bytes, 0x340 + 96, image_base + 0x320, 8);
+	put(&mut bytes, 0x340 + 104, 2, 8);
+	put(&mut bytes, 0x340 + 128, image_base + 0x328, 8);
+	put(&mut bytes, 0x340 + 136, 2, 8);
+	put(&mut bytes, 0x340 + 144, guard_flags as u64, 4);
+	put(&mut bytes, 0x340 + 148, 0x1, 2);
+	put(&mut bytes, 0x340 + 160, image_base + 0x332, 8);
+	put(&mut bytes, 0x340 + 168, 1, 8);
+	put(&mut bytes, 0x340 + 176, image_base + 0x337, 8);
+	put(&mut bytes, 0x340 + 184, 1, 8);
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_LOAD_CONFIG, 0x340, 192);
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let load_config = view.load_config().unwrap();
+	println!("{}", load_config);
+	assert_eq!(load_config.size(), 192);
+	assert_eq!(load_config.bytes().len(), 192);
+	assert_eq!(load_config.security_cookie(), image_base + 0x3000);
+	assert_eq!(load_config.guard_flags(), guard_flags);
+	let code_integrity = load_config.image().CodeIntegrity;
+	let flags = code_integrity.Flags;
+	assert_eq!(flags, 0x1);
+	assert_eq!(load_config.se_handlers(), [0x1000, 0x1010]);
+	let entries = |table: GuardTable| table.iter().map(|entry| (entry.rva, entry.flags)).collect::<Vec<_>>();
+	assert_eq!(entries(load_config.guard_cf_functions()), [(0x1000, IMAGE_GUARD_FLAG_FID_SUPPRESSED), (0x1020, 0)]);
+	assert_eq!(entries(load_config.guard_address_taken_iat_entries()), [(0x2000, 0)]);
+	assert_eq!(entries(load_config.guard_long_jump_targets()), [(0x1030, 0)]);
+	// Fields beyond the size are zero
+	assert!(load_config.guard_eh_continuations().is_empty());
+	assert_eq!(load_config.dynamic_value_reloc_table(), (0, 0));
+	// Tables reaching outside the image are reported when printed
+	let mut corrupt = bytes.clone();
+	put(&mut corrupt, 0x340 + 104, 0x1000_0000, 8);
+	put(&mut corrupt, 0x340 + 136, 0x1000_0000, 8);
+	let view = PeFileView::from_bytes(&corrupt).unwrap();
+	let load_config = view.load_config().unwrap();
+	assert!(load_config.try_se_handlers().is_err());
+	assert!(load_config.try_guard_cf_functions().is_err());
+	assert!(format!("{}", load_config).contains(&format!("  SEHandlers:     {}", PeError::Bounds)));
+}
+#[test]
+fn test_unwind_codes() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64d.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	{
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		let exceptions = view.exceptions().unwrap();
+		// Saves and allocates beyond the reach of the small forms, with an exception handler
+		let unwind_info = exceptions.lookup_function_entry(0x13340).unwrap().unwind_info().unwrap();
+		assert_eq!((unwind_info.version(), unwind_info.flags(), unwind_info.size_of_prolog()), (1, UNW_FLAG_EHANDLER | UNW_FLAG_UHANDLER, 106));
+		assert_eq!((unwind_info.frame_register(), unwind_info.frame_offset()), (0, 0));
+		assert_eq!(unwind_info.unwind_codes().collect::<Vec<_>>(), [
+			UnwindCode::SaveNonVol { offset: 0x6A, reg: 6, stack_offset: 0xEA8 },
+			UnwindCode::AllocLarge { offset: 0x13, size: 0xEB0 },
+			UnwindCode::PushNonVol { offset: 0x0C, reg: 15 },
+			UnwindCode::PushNonVol { offset: 0x0A, reg: 14 },
+			UnwindCode::PushNonVol { offset: 0x08, reg: 13 },
+			UnwindCode::PushNonVol { offset: 0x06, reg: 12 },
+			UnwindCode::PushNonVol { offset: 0x04, reg: 7 },
+			UnwindCode::PushNonVol { offset: 0x03, reg: 5 },
+			UnwindCode::PushNonVol { offset: 0x02, reg: 3 },
+		]);
+		assert_eq!(unwind_info.handler(), Some(0x111AE));
+		assert_eq!(&unwind_info.handler_data().unwrap()[..4], &[0x90, 0x0E, 0x00, 0x00]);
+		assert!(unwind_info.chained().is_none());
+		println!("{}", unwind_info);
+		// A function split in parts chains the unwind information of its first part
+		let unwind_info = exceptions.lookup_function_entry(0x12CBD).unwrap().unwind_info().unwrap();
+		assert_eq!(unwind_info.flags(), UNW_FLAG_CHAININFO);
+		assert_eq!(unwind_info.unwind_codes().collect::<Vec<_>>(), [UnwindCode::SaveNonVol { offset: 0x05, reg: 7, stack_offset: 0x30 }]);
+		let chained = unwind_info.chained().unwrap();
+		let (begin, end) = (chained.image().BeginAddress, chained.image().EndAddress);
+		assert_eq!((begin, end), (0x12CA0, 0x12CBD));
+		assert_eq!(chained.unwind_info().unwrap().unwind_codes().count(), 3);
+		assert!(unwind_info.handler().is_none());
+	}
+	// Operands missing from the end of the array and unknown ALLOC_LARGE forms are errors
+	let unwind_info_offset = PeFileView::from_bytes(&bytes).unwrap().rva_to_file_offset(0x1AF2C).unwrap();
+	bytes[unwind_info_offset + 2] = 1;
+	{
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		let unwind_info = view.exceptions().unwrap().lookup_function_entry(0x12CA0).unwrap().unwind_info().unwrap();
+		println!("{}", unwind_info);
+		assert!(unwind_info.unwind_codes().try_next().is_err());
+	}
+	bytes[unwind_info_offset + 2] = 4;
+	bytes[unwind_info_offset + 5] = 0x21;
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let unwind_info = view.exceptions().unwrap().lookup_function_entry(0x12CA0).unwrap().unwind_info().unwrap();
+	println!("{}", unwind_info);
+	assert!(unwind_info.unwind_codes().try_next().is_err());
+}
+#[test]
+fn test_delay_imports() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let image_base = PeFileView::from_bytes(&bytes).unwrap().optional_header().ImageBase;
+	// The descriptors at 0x320 with the names and thunks following them
+	put(&mut bytes, 0x320, DLATTR_RVA as u64, 4);
+	put(&mut bytes, 0x324, 0x360, 4);
+	put(&mut bytes, 0x328, 0x3C0, 4);
+	put(&mut bytes, 0x32C, 0x3A0, 4);
+	put(&mut bytes, 0x330, 0x380, 4);
+	bytes[0x360..0x36C].copy_from_slice(b"delayed.dll\0");
+	put(&mut bytes, 0x370, 7, 2);
+	bytes[0x372..0x37E].copy_from_slice(b"DelayedFunc\0");
+	put(&mut bytes, 0x380, 0x370, 8);
+	put(&mut bytes, 0x388, IMAGE_ORDINAL_FLAG | 5, 8);
+	put(&mut bytes, 0x3A0, image_base + 0x1000, 8);
+	put(&mut bytes, 0x3A8, image_base + 0x1010, 8);
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT, 0x320, 64);
+	{
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		let delay_imports = view.delay_imports().unwrap();
+		println!("{}", delay_imports);
+		let descs: Vec<_> = delay_imports.iter().collect();
+		assert_eq!(descs.len(), 1);
+		let desc = &descs[0];
+		assert!(desc.is_rva_based());
+		assert_eq!(desc.dll_name(), "delayed.dll");
+		let symbols: Vec<_> = desc.int_iter().map(|symbol| format!("{}", symbol)).collect();
+		assert_eq!(symbols, ["DelayedFunc", "#5"]);
+		assert_eq!(desc.iat_iter().cloned().collect::<Vec<_>>(), [image_base + 0x1000, image_base + 0x1010]);
+	}
+	// The legacy Va based format can't address a 64-bit image above 4 GiB
+	put(&mut bytes, 0x320, 0, 4);
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let desc = view.delay_imports().unwrap().iter().next().unwrap();
+	assert!(!desc.is_rva_based());
+	match desc.try_dll_name() {
+		Err(PeError::Bounds) => (),
+		_ => panic!(),
+	}
+}
+#[test]
+fn test_bound_imports() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	// Two descriptors, the first forwarding to ntdll.dll, the module names are relative to the directory
+	put(&mut bytes, 0x340, 0x11111111, 4);
+	put(&mut bytes, 0x344, 0x20, 2);
+	put(&mut bytes, 0x346, 1, 2);
+	put(&mut bytes, 0x348, 0x22222222, 4);
+	put(&mut bytes, 0x34C, 0x2D, 2);
+	put(&mut bytes, 0x350, 0x33333333, 4);
+	put(&mut bytes, 0x354, 0x37, 2);
+	bytes[0x360..0x382].copy_from_slice(b"KERNEL32.dll\0ntdll.dll\0USER32.dll\0");
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT, 0x340, 0x42);
+	{
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		let bound_imports = view.bound_imports().unwrap();
+		println!("{}", bound_imports);
+		let descs: Vec<_> = bound_imports.iter().map(|desc| {
+			let forwarders: Vec<_> = desc.forwarders().map(|fwd| (fwd.module_name(), fwd.time_date_stamp())).collect();
+			(desc.module_name(), desc.time_date_stamp(), forwarders)
+		}).collect();
+		assert_eq!(descs, [
+			("KERNEL32.dll", 0x11111111, vec![("ntdll.dll", 0x22222222)]),
+			("USER32.dll", 0x33333333, vec![]),
+		]);
+		assert_eq!(bound_imports.find("kernel32.dll").unwrap().forwarders_image().len(), 1);
+		assert_eq!(bound_imports.find("user32.DLL").unwrap().time_date_stamp(), 0x33333333);
+		assert!(bound_imports.find("ntdll.dll").is_none());
+	}
+	// The directory size ends the iteration as well, module names may not be out of bounds
+	set_data_directory(&mut bytes, IMAGE_DIRECTORY_ENTRY_BOUND_IMPORT, 0x340, 0x10);
+	put(&mut bytes, 0x344, 0xFFFF, 2);
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	let bound_imports = view.bound_imports().unwrap();
+	assert_eq!(bound_imports.iter().count(), 1);
+	assert!(bound_imports.iter().next().unwrap().try_module_name().is_err());
+	assert!(bound_imports.try_find("USER32.dll").is_err());
+}
+#[test]
+fn test_overlay() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64d.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	assert!(PeFileView::from_bytes(&bytes).unwrap().overlay().is_none());
+	match PeFile::from_bytes(&bytes).unwrap().overlay_range() {
+		Err(PeError::Null) => (),
+		_ => panic!(),
+	}
+	// Append a payload
+	let end = bytes.len();
+	bytes.extend_from_slice(b"payload");
+	{
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		let overlay = view.overlay().unwrap();
+		assert_eq!(overlay.range(), end..end + 7);
+		assert_eq!(overlay.data(), b"payload");
+		let file = PeFile::from_bytes(&bytes).unwrap();
+		assert_eq!(file.overlay_range().unwrap(), end..end + 7);
+		assert_eq!(file.read_overlay(&mut Cursor::new(&bytes)).unwrap(), b"payload");
+		// The file changed since it was read
+		assert!(file.read_overlay(&mut Cursor::new(&bytes[..end + 3])).is_err());
+	}
+	// The certificate table is not part of the overlay, only its alignment padding
+	append_certificate(&mut bytes, b"signature");
+	let view = PeFileView::from_bytes(&bytes).unwrap();
+	assert_eq!(view.overlay().unwrap().data(), b"payload\0");
+	assert_eq!(PeFile::from_bytes(&bytes).unwrap().read_overlay(&mut Cursor::new(&bytes)).unwrap(), b"payload\0");
+	// Truncated files are reported
+	let truncated = PeFileView::from_bytes(&bytes[..end - 1]).unwrap();
+	match truncated.try_overlay() {
+		Err(PeError::Bounds) => (),
+		_ => panic!(),
+	}
+}
+
+#[test]
+fn test_rebase() {
+	let mut file = PeFile::open(Path::new("tests\\bin\\dummy64.dll")).unwrap();
+	let original = file.view().image().to_vec();
+	let old_base = file.view().optional_header().ImageBase;
+	file.rebase(0x7FF6_0000_0000).unwrap();
+	let new_base = file.view().optional_header().ImageBase;
+	assert_eq!(new_base, 0x7FF6_0000_0000);
+	// Every relocated address moved by the same amount, nothing else changed
+	let mut changed = vec![false; original.len()];
+	let view = file.view();
+	let relocs = view.relocs().unwrap();
+	for base_relocs in relocs.iter() {
+		for block in base_relocs.blocks() {
+			let ty = base_relocs.type_of(block);
+			assert!(ty == IMAGE_REL_BASED_DIR64 || ty == IMAGE_REL_BASED_ABSOLUTE);
+			if ty == IMAGE_REL_BASED_ABSOLUTE {
+				continue;
+			}
+			let rva = base_relocs.rva_of(block) as usize;
+			let read = |image: &[u8]| image[rva..rva + 8].iter().rev().fold(0u64, |acc, &byte| acc << 8 | byte as u64);
+			assert_eq!(read(view.image()).wrapping_sub(read(&original)) as Va, (0x7FF6_0000_0000 as Va).wrapping_sub(old_base));
+			for i in 0..8 {
+				changed[rva + i] = true;
+			}
+		}
+	}
+	let image_base = view.dos_header().e_lfanew as usize + 24 + 24;
+	for i in 0..8 {
+		changed[image_base + i] = true;
+	}
+	for (i, (&a, &b)) in original.iter().zip(view.image()).enumerate() {
+		assert!(a == b || changed[i]);
+	}
+	// Rebasing back restores the original
+	file.rebase(old_base).unwrap();
+	assert_eq!(file.view().image(), &original[..]);
+}
+#[test]
+fn test_diff() {
+	let file = PeFile::open(Path::new("tests\\bin\\dummy64.dll")).unwrap();
+	// Simulate a module loaded at a different base
+	let mut loaded = PeFile::open(Path::new("tests\\bin\\dummy64.dll")).unwrap();
+	loaded.rebase(0x7FF6_0000_0000).unwrap();
+	let mut dump = loaded.view().image().to_vec();
+	assert!(diff(&file.view(), &unsafe { PeView::module(dump.as_ptr()) }).unwrap().is_empty());
+	// Filled in import address tables are not patches
+	let view = file.view();
+	for desc in view.imports().unwrap().iter() {
+		let iat = desc.image().FirstThunk as usize;
+		for byte in &mut dump[iat..iat + 8 * desc.iat_iter().count()] {
+			*byte = 0x41;
+		}
+	}
+	assert!(diff(&view, &unsafe { PeView::module(dump.as_ptr()) }).unwrap().is_empty());
+	// Hook the entry point and redirect a relocated pointer
+	let entry = view.optional_header().AddressOfEntryPoint as usize;
+	for byte in &mut dump[entry..entry + 5] {
+		*byte = 0xCC;
+	}
+	let relocs = view.relocs().unwrap();
+	let fixup = relocs.fixups().next().unwrap();
+	dump[fixup.rva as usize + 2] ^= 0x10;
+	let patches = diff(&view, &unsafe { PeView::module(dump.as_ptr()) }).unwrap();
+	for patch in &patches {
+		print!("{}", patch);
+	}
+	assert_eq!(patches.len(), 2);
+	let hook = patches.iter().find(|patch| patch.begin as usize == entry).unwrap();
+	assert_eq!(hook.end as usize, entry + 5);
+	assert_eq!(hook.section_name(), ".text");
+	assert!(patches.iter().any(|patch| patch.begin == fixup.rva + 2 && patch.end == fixup.rva + 3));
+	// Sections without a virtual size are compared over their raw size, VirtualSize is at offset 8 of the 40 byte section header
+	let mut mapped = view.image().to_vec();
+	let text = view.section_headers().iter().position(|section| section.Name.starts_with(b".text")).unwrap();
+	let headers = view.section_headers().as_ptr() as usize - view.image().as_ptr() as usize;
+	put(&mut mapped, headers + text * 40 + 8, 0, 4);
+	let mut patched = mapped.clone();
+	patched[entry] = 0xCC;
+	let patches = diff(&unsafe { PeView::module(mapped.as_ptr()) }, &unsafe { PeView::module(patched.as_ptr()) }).unwrap();
+	assert_eq!(patches.len(), 1);
+	assert_eq!((patches[0].begin as usize, patches[0].end as usize), (entry, entry + 1));
+	// Not the same module
+	let other = PeFile::open(Path::new("tests\\bin\\dummy64d.dll")).unwrap();
+	assert!(diff(&view, &other.view()).is_err());
+}
+
+// Build a minimal dll exporting the given symbols, either at a distinct rva or forwarded.
+fn export_dll(image_base: Va, dll_name: &str, exports: &[(&str, Option<&str>)]) -> Vec<u8> {
+	let mut exports = exports.to_vec();
+	exports.sort_by_key(|&(name, _)| name);
+	let mut file = vec![0u8; 0x1200];
+	// DOS header, NT headers and one section mapping the file at rva 0x1000
+	put(&mut file, 0x00, 0x5A4D, 2);
+	put(&mut file, 0x3C, 0x40, 4);
+	put(&mut file, 0x40, 0x4550, 4);
+	put(&mut file, 0x44, 0x8664, 2);
+	put(&mut file, 0x46, 1, 2);
+	put(&mut file, 0x54, 240, 2);
+	put(&mut file, 0x56, 0x2022, 2);
+	put(&mut file, 0x58, 0x20B, 2);
+	put(&mut file, 0x58 + 24, image_base, 8);
+	put(&mut file, 0x58 + 32, 0x1000, 4);
+	put(&mut file, 0x58 + 36, 0x200, 4);
+	put(&mut file, 0x58 + 56, 0x2000, 4);
+	put(&mut file, 0x58 + 60, 0x200, 4);
+	put(&mut file, 0x58 + 108, 16, 4);
+	put(&mut file, 0x58 + 112, 0x1000, 4);
+	put(&mut file, 0x58 + 116, 0x800, 4);
+	file[0x148..0x150].copy_from_slice(b".edata\0\0");
+	put(&mut file, 0x148 + 8, 0x1000, 4);
+	put(&mut file, 0x148 + 12, 0x1000, 4);
+	put(&mut file, 0x148 + 16, 0x1000, 4);
+	put(&mut file, 0x148 + 20, 0x200, 4);
+	// Export directory followed by its tables and strings, the exported functions live at 0x1800
+	let n = exports.len();
+	let (functions, names, ordinals) = (0x1028, 0x1028 + n * 4, 0x1028 + n * 8);
+	let mut strings = ordinals + n * 2;
+	let edata = |rva: usize| rva - 0x1000 + 0x200;
+	put(&mut file, edata(0x1000) + 16, 1, 4);
+	put(&mut file, edata(0x1000) + 20, n as u64, 4);
+	put(&mut file, edata(0x1000) + 24, n as u64, 4);
+	put(&mut file, edata(0x1000) + 28, functions as u64, 4);
+	put(&mut file, edata(0x1000) + 32, names as u64, 4);
+	put(&mut file, edata(0x1000) + 36, ordinals as u64, 4);
+	let mut add_string = |file: &mut Vec<u8>, s: &str| -> usize {
+		let rva = strings;
+		file[edata(rva)..edata(rva) + s.len()].copy_from_slice(s.as_bytes());
+		strings += s.len() + 1;
+		rva
+	};
+	let name_rva = add_string(&mut file, dll_name);
+	put(&mut file, edata(0x1000) + 12, name_rva as u64, 4);
+	for (i, &(name, forward)) in exports.iter().enumerate() {
+		let function = match forward {
+			Some(forward) => add_string(&mut file, forward),
+			None => 0x1800 + i * 0x10,
+		};
+		put(&mut file, edata(functions) + i * 4, function as u64, 4);
+		let name_rva = add_string(&mut file, name);
+		put(&mut file, edata(names) + i * 4, name_rva as u64, 4);
+		put(&mut file, edata(ordinals) + i * 2, i as u64, 2);
+	}
+	file
+}
+// Provide fake dependencies of dummy64.dll, the names are looked up case insensitively.
+fn add_dummy64_dependencies(loader: &mut Loader, dummy64: &[u8], crt_name: &str) {
+	let view = PeFileView::from_bytes(dummy64).unwrap();
+	for (i, desc) in view.imports().unwrap().iter().enumerate() {
+		let names: Vec<String> = desc.int_iter().map(|symbol| format!("{}", symbol)).collect();
+		let mut exports: Vec<(&str, Option<&str>)> = names.iter().map(|name| (&name[..], None)).collect();
+		// Without relocations they must be loaded at their preferred base
+		let base = 0x7FF8_0000_0000 + 0x100_0000 * i as Va;
+		if desc.dll_name() == "KERNEL32.dll" {
+			exports.push(("GetProcAddress", None));
+		}
+		let dll_name = if desc.dll_name().starts_with("api-ms-win-crt") { crt_name } else { desc.dll_name() };
+		let bytes = export_dll(base, &dll_name.to_lowercase(), &exports);
+		loader.add_file(&dll_name.to_uppercase(), bytes);
+	}
+}
+#[test]
+fn test_loader() {
+	// A module failing to link is unloaded again rather than left half linked
+	let mut loader = Loader::new();
+	loader.add_search_path(Path::new("tests").join("bin"));
+	match loader.load("dummy64.dll") {
+		Err(LoaderError::DllNotFound(_)) => (),
+		_ => panic!(),
+	}
+	assert!(loader.modules().is_empty());
+	assert!(loader.load("dummy64.dll").is_err());
+
+	let mut loader = Loader::new();
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	add_dummy64_dependencies(&mut loader, &bytes, "api-ms-win-crt-runtime-l1-1-0.dll");
+	loader.add_search_path(Path::new("tests").join("bin"));
+	loader.set_base("dummy64.dll", 0x1_8000_0000);
+	assert_eq!(loader.load("dummy64.dll").unwrap().base(), 0x1_8000_0000);
+	assert_eq!(loader.modules().len(), 4);
+	// Every import address table slot points at the export in its dll
+	let dummy = loader.find_module("DUMMY64.DLL").unwrap();
+	let dummy_view = dummy.view();
+	let image_base = dummy_view.optional_header().ImageBase;
+	assert_eq!(image_base, 0x1_8000_0000);
+	for desc in dummy_view.imports().unwrap().iter() {
+		let dll = loader.find_module(desc.dll_name()).unwrap();
+		let dll_view = dll.view();
+		let exports = dll_view.exports().unwrap();
+		for (symbol, &va) in desc.int_iter().zip(desc.iat_iter()) {
+			match exports.symbol_by_name(&format!("{}", symbol)) {
+				pelite::pe64::exports::Export::Symbol(&rva) => assert_eq!(va, dll.base() + rva as Va),
+				_ => panic!("{} not exported", symbol),
+			}
+			assert_eq!(loader.module_by_va(va).unwrap().name(), dll.name());
+		}
+	}
+	// Forwarded exports are followed to their target
+	let kernel32 = loader.find_module("kernel32.dll").unwrap().base();
+	let get_proc_address = loader.resolve_by_name("dummy64.dll", "?fnDummy@@YAHXZ").unwrap();
+	assert_eq!(get_proc_address, loader.resolve_by_name("KERNEL32.dll", "GetProcAddress").unwrap());
+	assert!(get_proc_address > kernel32 && get_proc_address < kernel32 + 0x2000);
+	assert_eq!(loader.resolve_by_ordinal("dummy64.dll", 6).unwrap(), 0x1_8000_3638);
+	match loader.resolve_by_name("dummy64.dll", "missing") {
+		Err(LoaderError::ImportNotFound(name)) => assert_eq!(name, "dummy64.dll.missing"),
+		_ => panic!(),
+	}
+	// Forwarder loops are reported
+	loader.add_file("loop.dll", export_dll(0x7FF9_0000_0000, "loop.dll", &[("Ping", Some("loop.Pong")), ("Pong", Some("loop.Ping"))]));
+	match loader.resolve_by_name("loop.dll", "Ping") {
+		Err(LoaderError::ForwarderLoop(_)) => (),
+		_ => panic!(),
+	}
+	match loader.load("missing.dll") {
+		Err(LoaderError::DllNotFound(name)) => assert_eq!(name, "missing.dll"),
+		_ => panic!(),
+	}
+}
+
+#[test]
+fn test_loader_imports() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	// Import from KERNEL32.exe instead, and read the names of the other imports from the import address table
+	let mut patches = Vec::new();
+	{
+		let view = PeFileView::from_bytes(&bytes).unwrap();
+		for desc in view.imports().unwrap().iter() {
+			let offset = desc.image() as *const _ as usize - bytes.as_ptr() as usize;
+			if desc.dll_name() == "KERNEL32.dll" {
+				let name = view.rva_to_file_offset(desc.image().Name).unwrap();
+				patches.push((name + 9, 0x657865, 3));
+			}
+			else {
+				patches.push((offset, 0, 4));
+			}
+		}
+	}
+	for &(offset, value, size) in &patches {
+		put(&mut bytes, offset, value, size);
+	}
+	let mut loader = Loader::new();
+	add_dummy64_dependencies(&mut loader, &bytes, "api-ms-win-crt-runtime-l1-1-0.dll");
+	loader.add_file("dummy64.dll", bytes.clone());
+	let dummy = loader.load("dummy64.dll").unwrap().base();
+	assert!(loader.find_module("KERNEL32.exe").is_some());
+	assert!(loader.find_module("KERNEL32.dll").is_none());
+	let dummy_view = loader.find_module("dummy64.dll").unwrap().view();
+	assert_eq!(dummy_view.imports().unwrap().iter().count(), 3);
+	for desc in dummy_view.imports().unwrap().iter() {
+		assert!(desc.iat_iter().all(|&va| loader.module_by_va(va).unwrap().name() == desc.dll_name()));
+	}
+	assert_eq!(loader.module_by_va(dummy).unwrap().name(), "dummy64.dll");
+}
+
+#[test]
+fn test_loader_api_set() {
+	// Schema version 2 redirecting the C runtime api set: header and entry, the name without prefix, then the host
+	let words = |s: &str| -> Vec<u8> { s.encode_utf16().flat_map(|word| vec![word as u8, (word >> 8) as u8]).collect() };
+	let dwords = |values: &[usize]| -> Vec<u8> { values.iter().flat_map(|&value| vec![value as u8, (value >> 8) as u8, 0, 0]).collect() };
+	let (name, host) = (words("ms-win-crt-runtime-l1-1-0"), words("ucrtbase.dll"));
+	let data = 20 + name.len();
+	let schema = [dwords(&[2, 1, 20, name.len(), data]), name, dwords(&[1, 0, 0, data + 20, host.len()]), host].concat();
+	let schema = ApiSetSchema::parse(&schema).unwrap();
+	assert_eq!(schema.resolve("api-ms-win-crt-runtime-l1-1-0.dll", "dummy64.dll"), Some("ucrtbase.dll"));
+
+	let mut loader = Loader::new();
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	add_dummy64_dependencies(&mut loader, &bytes, "ucrtbase.dll");
+	loader.set_api_set_schema(schema);
+	loader.add_search_path(Path::new("tests").join("bin"));
+	loader.load("dummy64.dll").unwrap();
+	assert!(loader.find_module("api-ms-win-crt-runtime-l1-1-0.dll").is_none());
+	assert!(loader.find_module("ucrtbase.dll").is_some());
+	let initterm = loader.resolve_by_name("api-ms-win-crt-runtime-l1-1-0.dll", "_initterm").unwrap();
+	assert_eq!(loader.module_by_va(initterm).unwrap().name(), "ucrtbase.dll");
+}
+
+#[test]
+fn test_resolver() {
+	assert_eq!(parse_forward("KERNEL32.GetProcAddress").unwrap(), ("KERNEL32", Symbol::Name("GetProcAddress")));
+	assert_eq!(parse_forward("api-ms-win-core-synch-l1-2-0.#12").unwrap(), ("api-ms-win-core-synch-l1-2-0", Symbol::Ordinal(12)));
+	assert!(parse_forward("NoDot").is_err());
+	assert!(parse_forward("Dll.#x").is_err());
+	let a = PeFile::from_bytes(&export_dll(0x1000_0000, "a.dll", &[
+		("Api", Some("api-ms-win-test-l1-1-0.Target")),
+		("Chain", Some("a.Func")),
+		("Func", Some("b.#4")),
+		("Gone", Some("c.Func")),
+		("Missing", Some("b.Nope")),
+		("Ping", Some("b.Pong")),
+	])).unwrap();
+	let b = PeFile::from_bytes(&export_dll(0x2000_0000, "b.dll", &[
+		("One", None),
+		("Pong", Some("a.Ping")),
+		("Target", None),
+		("Two", None),
+	])).unwrap();
+	let (a_view, b_view) = (a.view(), b.view());
+	let mut resolver = Resolver::new();
+	resolver.insert("A.DLL", a_view.exports().unwrap());
+	// Dlls may also be provided on demand
+	resolver.set_callback(|dll_name| if dll_name.eq_ignore_ascii_case("b") { b_view.exports() } else { None });
+	// Forwarders by ordinal, through other forwarders
+	let resolved = resolver.resolve_by_name("a.dll", "Chain").unwrap();
+	assert_eq!(resolved.dll_name, "b");
+	assert_eq!(resolved.rva, 0x1830);
+	assert_eq!(resolver.resolve_by_ordinal("A", 3).unwrap().rva, 0x1830);
+	match resolver.resolve_by_name("a.dll", "Missing") {
+		Err(ResolveError::SymbolNotFound(name)) => assert_eq!(name, "b.Nope"),
+		_ => panic!(),
+	}
+	match resolver.resolve_by_name("a.dll", "Gone") {
+		Err(ResolveError::DllNotFound(name)) => assert_eq!(name, "c"),
+		_ => panic!(),
+	}
+	match resolver.resolve_by_name("a.dll", "Ping") {
+		Err(ResolveError::ForwarderLoop(chain)) => assert_eq!(chain, ["A.DLL.Ping", "b.Pong", "A.DLL.Ping"]),
+		_ => panic!(),
+	}
+	// Api sets are looked up like any other dll unless they're translated to their host
+	assert!(resolver.resolve_by_name("a.dll", "Api").is_err());
+	resolver.set_api_set(|api_set, importer| {
+		assert_eq!(importer, "A.DLL");
+		if api_set == "api-ms-win-test-l1-1-0" { Some(String::from("b.dll")) } else { None }
+	});
+	let resolved = resolver.resolve_by_name("a.dll", "Api").unwrap();
+	assert_eq!(resolved.dll_name, "b");
+	assert_eq!(resolved.rva, 0x1820);
+}
+
+#[test]
+fn test_symbol_by_name() {
+	let names: Vec<String> = (0..100).map(|i| format!("Function{}", i * 7 % 100)).collect();
+	let exports: Vec<(&str, Option<&str>)> = names.iter().map(|name| (&name[..], None)).collect();
+	let file = PeFile::from_bytes(&export_dll(0x1000_0000, "many.dll", &exports)).unwrap();
+	let view = file.view();
+	let exports = view.exports().unwrap();
+	// Exported in sorted order at increasing rvas
+	let mut sorted = names.clone();
+	sorted.sort();
+	for (i, name) in sorted.iter().enumerate() {
+		match exports.symbol_by_name(name) {
+			pelite::pe64::exports::Export::Symbol(&rva) => assert_eq!(rva as usize, 0x1800 + i * 0x10),
+			_ => panic!("{} not found", name),
+		}
+		assert_eq!(format!("{}", exports.symbol_by_hint(i as u16, name)), format!("{:>08X}", 0x1800 + i * 0x10));
+		assert_eq!(format!("{}", exports.symbol_by_hint(0, name)), format!("{:>08X}", 0x1800 + i * 0x10));
+	}
+	for name in &["", "Function", "Function100", "Function00", "function1", "Zzz"] {
+		assert_eq!(format!("{}", exports.symbol_by_name(name)), "None");
+	}
+}
+
+#[test]
+fn test_corrupt_exports() {
+	let mut bytes = export_dll(0x1000_0000, "corrupt.dll", &[("First", None), ("Second", None)]);
+	// Ordinal base at the end of the 16 bit range and an export directory size reaching past 4 GiB
+	put(&mut bytes, 0x200 + 16, 0xFFFF, 4);
+	put(&mut bytes, 0x58 + 116, 0xFFFF_FFFF, 4);
+	{
+		let file = PeFile::from_bytes(&bytes).unwrap();
+		let view = file.view();
+		let exports = view.exports().unwrap();
+		assert_eq!(exports.iter().collect::<Vec<u16>>(), [0xFFFF, 0]);
+		assert!(exports.is_forwarded(0x1800));
+		assert!(!exports.is_forwarded(0xFFF));
+	}
+	// More functions than there are ordinals
+	put(&mut bytes, 0x200 + 20, 0x1_0001, 4);
+	let file = PeFile::from_bytes(&bytes).unwrap();
+	let view = file.view();
+	let exports = view.exports().unwrap();
+	let mut it = exports.iter();
+	let mut count = 0;
+	loop {
+		match it.try_next() {
+			Ok(Some(_)) => count += 1,
+			Err(PeError::Corrupt) => break,
+			_ => panic!(),
+		}
+	}
+	assert_eq!(count, 0x1_0000);
+}
+
+#[test]
+fn test_symbol_index() {
+	let file = PeFile::open(Path::new("tests\\bin\\dummy64.dll")).unwrap();
+	let view = file.view();
+	let exports = view.exports().unwrap();
+	let index = SymbolIndex::new(&exports).unwrap();
+	// The forwarded export is not an address in this module
+	assert_eq!(index.symbols().len(), 2);
+	assert!(index.lookup(0x100F).is_none());
+	assert_eq!(format!("{}", index.lookup(0x1010).unwrap()), "??0CDummy@@QEAA@XZ");
+	assert_eq!(format!("{}", index.lookup_va(view.virtual_base() + 0x1013).unwrap()), "??0CDummy@@QEAA@XZ+0x3");
+	let data = index.lookup(0x3640).unwrap();
+	assert_eq!((data.symbol.ord, data.symbol.name, data.offset), (6, None, 8));
+	assert_eq!(format!("{}", data), "#6+0x8");
+	// Addresses inside functions which are not exported are not attributed to the preceding export
+	let exceptions = view.exceptions().unwrap();
+	let function = exceptions.functions().map(|function| function.image().BeginAddress).find(|&rva| rva > 0x1010 && rva < 0x3638).unwrap();
+	assert!(index.lookup(function).is_none());
+	assert!(index.lookup(function + 1).is_none());
+}
+
+// User defined backend, here simply borrowing an already mapped image.
+struct Custom<'a> {
+	image: &'a [u8],
+}
+unsafe impl<'a> Pe<'a> for Custom<'a> {
+	fn image(&self) -> &'a [u8] {
+		self.image
+	}
+	fn rva_to_slice(&self, rva: Rva) -> Result<&'a [u8], PeError> {
+		if rva == 0 { Err(PeError::Null) }
+		else { self.image.get(rva as usize..).ok_or(PeError::Bounds) }
+	}
+}
+#[test]
+fn test_dummy64_dll_custom() {
+	let file = PeFile::open(Path::new("tests\\bin\\dummy64.dll")).unwrap();
+	let image = file.view().image();
+	validate_headers(image).unwrap();
+	run_tests(&Custom { image: image });
+}
 
-fn run_tests(view: &PeView) {
+fn run_tests<'a, P: Pe<'a>>(view: &P) {
 	println!("{}", view.imports().unwrap());
-	println!("{}", view.exports().unwrap());
+	let exports = view.exports().unwrap();
+	println!("{}", exports);
+	// Every named export is found by name and by its hint, even a wrong hint
+	for (hint, &name_rva) in exports.names().unwrap().iter().enumerate() {
+		let name = view.read_str(name_rva).unwrap();
+		let symbol = format!("{}", exports.symbol_by_name(name));
+		assert!(symbol != "None");
+		assert_eq!(format!("{}", exports.symbol_by_hint(hint as u16, name)), symbol);
+		assert_eq!(format!("{}", exports.symbol_by_hint(hint as u16 + 1, name)), symbol);
+	}
+	assert_eq!(format!("{}", exports.symbol_by_name("missing")), "None");
 	println!("{}", view.resources().unwrap());
 	println!("{}", view.relocs().unwrap());
+	let debug = view.debug().unwrap();
+	println!("{}", debug);
+	assert!(debug.code_view().unwrap().pdb_file_name().ends_with(".pdb"));
+	// The dummy dlls do not use thread local storage
+	assert!(view.tls().is_none());
+	assert!(view.delay_imports().is_none());
+	assert!(view.bound_imports().is_none());
+	let load_config = view.load_config().unwrap();
+	println!("{}", load_config);
+	assert!(load_config.security_cookie() != 0);
+	let rich = RichHeader::parse(view.image()).unwrap();
+	println!("{}", rich);
+	assert!(rich.verify_checksum());
+	let exceptions = view.exceptions().unwrap();
+	println!("{}", exceptions);
+	for function in exceptions.functions().filter(|function| function.image().BeginAddress != 0) {
+		let image = function.image();
+		let found = exceptions.lookup_function_entry(image.EndAddress - 1).unwrap();
+		assert_eq!(found.image() as *const _, image as *const _);
+	}
 }
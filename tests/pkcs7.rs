@@ -0,0 +1,97 @@
+extern crate pelite;
+use pelite::pkcs7::*;
+
+// There are no signed test binaries, build the signatures by hand.
+fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+	let mut der = vec![tag];
+	if contents.len() < 0x80 {
+		der.push(contents.len() as u8);
+	}
+	else {
+		der.extend_from_slice(&[0x82, (contents.len() >> 8) as u8, contents.len() as u8]);
+	}
+	der.extend_from_slice(contents);
+	der
+}
+fn cat(parts: &[Vec<u8>]) -> Vec<u8> {
+	parts.concat()
+}
+fn name(common_name: &str) -> Vec<u8> {
+	tlv(0x30, &tlv(0x31, &tlv(0x30, &cat(&[tlv(0x06, OID_COMMON_NAME), tlv(0x0C, common_name.as_bytes())]))))
+}
+fn algorithm(oid: &[u8]) -> Vec<u8> {
+	tlv(0x30, &cat(&[tlv(0x06, oid), tlv(0x05, &[])]))
+}
+fn signature(digest_oid: &[u8], digest: &[u8], unauthenticated: &[u8]) -> Vec<u8> {
+	let serial = tlv(0x02, &[0x01, 0x02]);
+	let issuer = name("Test CA");
+	let tbs = tlv(0x30, &cat(&[
+		tlv(0xA0, &tlv(0x02, &[2])),
+		serial.clone(),
+		algorithm(OID_SHA256),
+		issuer.clone(),
+		tlv(0x30, &cat(&[tlv(0x17, b"170101000000Z"), tlv(0x17, b"270101000000Z")])),
+		name("Test Signer"),
+		tlv(0x30, &[]),
+	]));
+	let cert = tlv(0x30, &cat(&[tbs, algorithm(OID_SHA256), tlv(0x03, &[0])]));
+	let spc = tlv(0x30, &cat(&[
+		tlv(0x30, &tlv(0x06, &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x0F])),
+		tlv(0x30, &cat(&[algorithm(digest_oid), tlv(0x04, digest)])),
+	]));
+	let signing_time = tlv(0x30, &cat(&[tlv(0x06, OID_SIGNING_TIME), tlv(0x31, &tlv(0x17, b"170102030405Z"))]));
+	let mut signer = vec![
+		tlv(0x02, &[1]),
+		tlv(0x30, &cat(&[issuer, serial])),
+		algorithm(digest_oid),
+		tlv(0xA0, &signing_time),
+		algorithm(&[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01]),
+		tlv(0x04, &[0; 16]),
+	];
+	if !unauthenticated.is_empty() {
+		signer.push(tlv(0xA1, unauthenticated));
+	}
+	let signed_data = tlv(0x30, &cat(&[
+		tlv(0x02, &[1]),
+		tlv(0x31, &algorithm(digest_oid)),
+		tlv(0x30, &cat(&[tlv(0x06, OID_SPC_INDIRECT_DATA), tlv(0xA0, &spc)])),
+		tlv(0xA0, &cert),
+		tlv(0x31, &tlv(0x30, &cat(&signer))),
+	]));
+	tlv(0x30, &cat(&[tlv(0x06, OID_SIGNED_DATA), tlv(0xA0, &signed_data)]))
+}
+
+#[test]
+fn test_signed_data() {
+	let nested = signature(OID_SHA1, &[0x11; 20], &[]);
+	let nested_attr = tlv(0x30, &cat(&[tlv(0x06, OID_SPC_NESTED_SIGNATURE), tlv(0x31, &nested)]));
+	let mut der = signature(OID_SHA256, &[0x22; 32], &nested_attr);
+	// Certificate table entries are padded
+	der.extend_from_slice(&[0; 5]);
+
+	let signed = SignedData::parse(&der).unwrap();
+	println!("{}", signed);
+	assert_eq!(signed.digest_algorithm().0, OID_SHA256);
+	assert_eq!(signed.digest_algorithm().to_string(), "2.16.840.1.101.3.4.2.1");
+	assert_eq!(signed.digest(), &[0x22; 32][..]);
+	assert_eq!(signed.raw().len(), der.len() - 5);
+
+	let cert = signed.try_signer_certificate().unwrap();
+	assert_eq!(cert.serial_number(), &[0x01, 0x02]);
+	assert_eq!(cert.issuer().to_string(), "CN=Test CA");
+	assert_eq!(cert.subject().common_name().unwrap(), "Test Signer");
+	assert_eq!(signed.certificates().count(), 1);
+
+	let time = signed.try_signing_time().unwrap();
+	assert_eq!(time.to_string(), "2017-01-02 03:04:05 UTC");
+
+	let nested: Vec<_> = signed.nested().collect();
+	assert_eq!(nested.len(), 1);
+	assert_eq!(nested[0].digest_algorithm().0, OID_SHA1);
+	assert_eq!(nested[0].digest(), &[0x11; 20][..]);
+	assert_eq!(nested[0].nested().count(), 0);
+
+	// Garbage is rejected without panicking
+	assert!(SignedData::parse(&der[..40]).is_err());
+	assert!(SignedData::parse(&tlv(0x30, &tlv(0x06, OID_SHA1))).is_err());
+}
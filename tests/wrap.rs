@@ -0,0 +1,35 @@
+extern crate pelite;
+use std::path::Path;
+use std::fs::File;
+use std::io::Read;
+use pelite::wrap::{PeView, PeFile};
+
+#[test]
+fn test_dummyd_dll() {
+	let file = PeFile::open(Path::new("tests\\bin\\dummyd.dll")).unwrap();
+	assert!(file.is_32());
+	run_tests(&file.view());
+}
+#[test]
+fn test_dummy64_dll() {
+	let file = PeFile::open(Path::new("tests\\bin\\dummy64.dll")).unwrap();
+	assert!(file.is_64());
+	assert!(file.overlay_range().is_err());
+	run_tests(&file.view());
+}
+
+fn run_tests(view: &PeView) {
+	println!("{}", view.imports().unwrap());
+	println!("{}", view.exports().unwrap());
+	println!("{}", view.resources().unwrap());
+	println!("{}", view.relocs().unwrap());
+}
+#[test]
+fn test_from_bytes() {
+	let mut bytes = Vec::new();
+	File::open("tests\\bin\\dummy64d.dll").unwrap().read_to_end(&mut bytes).unwrap();
+	let file = PeFile::from_bytes(&bytes).unwrap();
+	assert!(file.is_64());
+	assert!(file.overlay_range().is_err());
+	run_tests(&file.view());
+}